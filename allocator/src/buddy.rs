@@ -0,0 +1,416 @@
+use core::mem::size_of;
+use core::ptr;
+
+/// Smallest block handed out by the buddy allocator.
+pub const PAGE_SIZE: usize = 1 << PAGE_ORDER;
+pub const PAGE_ORDER: usize = 12;
+/// Largest block, 16 MiB.
+pub const MAX_ORDER: usize = 24;
+const ORDERS: usize = MAX_ORDER - PAGE_ORDER + 1;
+/// Smallest slab object, the object map has one bit per `MIN_OBJECT_SIZE` bytes.
+pub const MIN_OBJECT_SIZE: usize = 16;
+
+/// Page state of the first page of a free block, ored with the order.
+const PAGE_FREE: u8 = 0x80;
+/// Page state of the first page of an allocated block, ored with the order.
+const PAGE_USED: u8 = 0x40;
+const PAGE_ORDER_MASK: u8 = 0x3F;
+/// End of a list of pages.
+pub(crate) const NO_PAGE: u32 = u32::MAX;
+
+/// Bookkeeping of one page, kept apart from the memory it describes.
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub(crate) struct PageInfo {
+    state: u8,
+    /// objects in use if the page starts a slab
+    pub(crate) objects_in_use: u16,
+    /// neighbours in the free list of a free block or in the partial list of a slab
+    pub(crate) prev: u32,
+    pub(crate) next: u32,
+}
+
+/// Bytes of bookkeeping per page, a `PageInfo` and its part of the object map.
+const PAGE_METADATA_SIZE: usize = size_of::<PageInfo>() + PAGE_SIZE / MIN_OBJECT_SIZE / 8;
+
+/// Binary buddy allocator for blocks of `PAGE_SIZE` to `1 << MAX_ORDER` bytes.
+///
+/// A block of order `n` is `1 << n` bytes large and aligned to its size,  
+/// its buddy is the block at `address ^ (1 << n)`. Freed blocks are merged  
+/// with their buddy as long as it is free, too.  
+/// The state and the free list links of each block are kept in a table with  
+/// a `PageInfo` per page, followed by the object map of the slab caches.  
+/// The allocator never writes into the managed memory, the table either lives  
+/// in its first pages (`init`) or in memory of the caller (`init_with_metadata`).
+pub struct BuddyAllocator {
+    /// first page covered by the page table
+    base: usize,
+    /// first page which may be handed out
+    start: usize,
+    end: usize,
+    pages: *mut PageInfo,
+    /// one bit per `MIN_OBJECT_SIZE` bytes, set for free slab objects
+    object_map: *mut u32,
+    /// first page of the first free block of each order
+    free_lists: [u32; ORDERS],
+    free_bytes: usize,
+}
+
+impl BuddyAllocator {
+    /// Creates an allocator without memory, `init` has to be called before the first allocation.
+    pub const fn empty() -> BuddyAllocator {
+        BuddyAllocator {
+            base: 0,
+            start: 0,
+            end: 0,
+            pages: ptr::null_mut(),
+            object_map: ptr::null_mut(),
+            free_lists: [NO_PAGE; ORDERS],
+            free_bytes: 0,
+        }
+    }
+
+    /// Number of bytes of bookkeeping `init_with_metadata` needs for `size` bytes of memory.
+    pub const fn metadata_size(size: usize) -> usize {
+        size / PAGE_SIZE * PAGE_METADATA_SIZE
+    }
+
+    /// Hands the memory from `start` to `start + size` to the allocator,  
+    /// its first pages hold the bookkeeping.
+    ///
+    /// # Safety
+    /// The memory must be valid, unused and must not be handed out twice.  
+    /// Whoever can write to the memory can corrupt the allocator.
+    pub unsafe fn init(&mut self, start: usize, size: usize) {
+        let base = align_up(start, PAGE_SIZE);
+        let end = (start + size) & !(PAGE_SIZE - 1);
+        let metadata_size = align_up(
+            BuddyAllocator::metadata_size(end.saturating_sub(base)),
+            PAGE_SIZE,
+        );
+        assert!(base + metadata_size < end, "buddy: heap too small");
+        self.setup(base, base + metadata_size, end, base as *mut u8);
+    }
+
+    /// Hands the memory from `start` to `start + size` to the allocator  
+    /// and keeps the bookkeeping at `metadata`.
+    ///
+    /// # Safety
+    /// The memory must be valid, unused and must not be handed out twice.  
+    /// `metadata` must be aligned to 4 bytes, valid for `metadata_size(size)` bytes  
+    /// and must not be used for anything else while the allocator is in use.
+    pub unsafe fn init_with_metadata(&mut self, start: usize, size: usize, metadata: *mut u8) {
+        let base = align_up(start, PAGE_SIZE);
+        let end = (start + size) & !(PAGE_SIZE - 1);
+        assert!(base < end, "buddy: heap too small");
+        self.setup(base, base, end, metadata);
+    }
+
+    unsafe fn setup(&mut self, base: usize, start: usize, end: usize, metadata: *mut u8) {
+        let pages = (end - base) / PAGE_SIZE;
+        self.base = base;
+        self.start = start;
+        self.end = end;
+        self.pages = metadata as *mut PageInfo;
+        for index in 0..pages {
+            self.pages.add(index).write(PageInfo {
+                state: 0,
+                objects_in_use: 0,
+                prev: NO_PAGE,
+                next: NO_PAGE,
+            });
+        }
+        self.object_map = self.pages.add(pages) as *mut u32;
+        self.object_map.write_bytes(0, self.object_map_len());
+        self.free_lists = [NO_PAGE; ORDERS];
+        self.free_bytes = 0;
+
+        // carve the memory into the largest blocks that are aligned to their size
+        let mut address = self.start;
+        while address < end {
+            let mut order = MAX_ORDER;
+            while address & ((1 << order) - 1) != 0 || address + (1 << order) > end {
+                order -= 1;
+            }
+            self.push(address, order);
+            address += 1 << order;
+        }
+    }
+
+    /// Number of bytes managed by the allocator, without bookkeeping in its memory.
+    pub fn total(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Number of bytes in free blocks.
+    pub fn free(&self) -> usize {
+        self.free_bytes
+    }
+
+    /// Size of the largest free block, the largest allocation which can succeed.
+    pub fn largest_free_block(&self) -> usize {
+        (PAGE_ORDER..=MAX_ORDER)
+            .rev()
+            .find(|&order| self.free_lists[order - PAGE_ORDER] != NO_PAGE)
+            .map_or(0, |order| 1 << order)
+    }
+
+    /// Returns the order of the smallest block holding `size` bytes aligned to `align`.
+    pub fn order_for(size: usize, align: usize) -> Option<usize> {
+        let size = size.max(align).max(PAGE_SIZE).checked_next_power_of_two()?;
+        let order = size.trailing_zeros() as usize;
+        if order <= MAX_ORDER {
+            Some(order)
+        } else {
+            None
+        }
+    }
+
+    /// Allocates a block of `1 << order` bytes, splitting larger blocks if needed.
+    ///
+    /// Returns None if no block of this order is available.
+    pub fn allocate(&mut self, order: usize) -> Option<usize> {
+        debug_assert!((PAGE_ORDER..=MAX_ORDER).contains(&order));
+        let mut current_order = (order..=MAX_ORDER)
+            .find(|&current_order| self.free_lists[current_order - PAGE_ORDER] != NO_PAGE)?;
+        let address = self.page_address(self.free_lists[current_order - PAGE_ORDER]);
+        self.remove(address, current_order);
+
+        // split off the upper halves until the block has the requested size
+        while current_order > order {
+            current_order -= 1;
+            self.push(address + (1 << current_order), current_order);
+        }
+        self.page_info(address).state = PAGE_USED | order as u8;
+        Some(address)
+    }
+
+    /// Frees a block returned by `allocate` and merges it with its free buddies.
+    ///
+    /// # Safety
+    /// The block must have been allocated with the same order and must not be used anymore.
+    pub unsafe fn deallocate(&mut self, address: usize, order: usize) {
+        debug_assert_eq!(
+            self.page_info(address).state,
+            PAGE_USED | order as u8,
+            "buddy: invalid free of {:#x}",
+            address
+        );
+        self.page_info(address).state = 0;
+
+        let mut address = address;
+        let mut order = order;
+        while order < MAX_ORDER {
+            let buddy = address ^ (1 << order);
+            if buddy < self.start
+                || buddy + (1 << order) > self.end
+                || self.page_info(buddy).state != PAGE_FREE | order as u8
+            {
+                break;
+            }
+            self.remove(buddy, order);
+            address = address.min(buddy);
+            order += 1;
+        }
+        self.push(address, order);
+    }
+
+    pub(crate) fn page_index(&self, address: usize) -> u32 {
+        ((address - self.base) / PAGE_SIZE) as u32
+    }
+
+    pub(crate) fn page_address(&self, index: u32) -> usize {
+        self.base + index as usize * PAGE_SIZE
+    }
+
+    /// Bookkeeping of the page containing `address`.
+    pub(crate) fn page_info(&mut self, address: usize) -> &mut PageInfo {
+        let index = self.page_index(address);
+        self.page_info_at(index)
+    }
+
+    pub(crate) fn page_info_at(&mut self, index: u32) -> &mut PageInfo {
+        debug_assert!((index as usize) < (self.end - self.base) / PAGE_SIZE);
+        unsafe { &mut *self.pages.add(index as usize) }
+    }
+
+    fn object_map_len(&self) -> usize {
+        (self.end - self.base) / MIN_OBJECT_SIZE / 32
+    }
+
+    /// Free object bits of all pages, `object_bit` gives the bit of an address.
+    pub(crate) fn object_map(&mut self) -> &mut [u32] {
+        unsafe { core::slice::from_raw_parts_mut(self.object_map, self.object_map_len()) }
+    }
+
+    pub(crate) fn object_bit(&self, address: usize) -> usize {
+        (address - self.base) / MIN_OBJECT_SIZE
+    }
+
+    /// Puts a block at the front of the free list of its order.
+    fn push(&mut self, address: usize, order: usize) {
+        let index = self.page_index(address);
+        let head = self.free_lists[order - PAGE_ORDER];
+        if head != NO_PAGE {
+            self.page_info_at(head).prev = index;
+        }
+        let page = self.page_info_at(index);
+        page.state = PAGE_FREE | order as u8;
+        page.prev = NO_PAGE;
+        page.next = head;
+        self.free_lists[order - PAGE_ORDER] = index;
+        self.free_bytes += 1 << order;
+    }
+
+    /// Unlinks a free block from the free list of its order.
+    fn remove(&mut self, address: usize, order: usize) {
+        let index = self.page_index(address);
+        let PageInfo {
+            state, prev, next, ..
+        } = *self.page_info_at(index);
+        debug_assert_eq!(state & PAGE_ORDER_MASK, order as u8);
+        match prev {
+            NO_PAGE => self.free_lists[order - PAGE_ORDER] = next,
+            prev => self.page_info_at(prev).next = next,
+        }
+        if next != NO_PAGE {
+            self.page_info_at(next).prev = prev;
+        }
+        self.page_info_at(index).state = 0;
+        self.free_bytes -= 1 << order;
+    }
+}
+
+pub(crate) const fn align_up(value: usize, align: usize) -> usize {
+    (value + align - 1) & !(align - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::Region;
+
+    fn new_buddy(region: &Region) -> BuddyAllocator {
+        let mut buddy = BuddyAllocator::empty();
+        unsafe { buddy.init(region.start(), region.size()) };
+        buddy
+    }
+
+    #[test]
+    fn init_reserves_page_table() {
+        let region = Region::new(1 << 20);
+        let buddy = new_buddy(&region);
+        // 256 pages need 11 KiB of bookkeeping
+        assert_eq!(BuddyAllocator::metadata_size(1 << 20), 11 * 1024);
+        assert_eq!(buddy.total(), (1 << 20) - 3 * PAGE_SIZE);
+        assert_eq!(buddy.free(), buddy.total());
+        assert_eq!(buddy.largest_free_block(), 1 << 19);
+    }
+
+    #[test]
+    fn external_metadata_leaves_memory_untouched() {
+        let region = Region::new(1 << 20);
+        let mut metadata = std::vec![0u32; BuddyAllocator::metadata_size(1 << 20) / 4];
+        unsafe { (region.start() as *mut u8).write_bytes(0xAB, region.size()) };
+
+        let mut buddy = BuddyAllocator::empty();
+        unsafe {
+            buddy.init_with_metadata(
+                region.start(),
+                region.size(),
+                metadata.as_mut_ptr() as *mut u8,
+            )
+        };
+        assert_eq!(buddy.total(), 1 << 20);
+        assert_eq!(buddy.largest_free_block(), 1 << 20);
+
+        let pages: std::vec::Vec<usize> = (0..10)
+            .map(|_| buddy.allocate(PAGE_ORDER).unwrap())
+            .collect();
+        for page in pages {
+            unsafe { buddy.deallocate(page, PAGE_ORDER) };
+        }
+        assert_eq!(buddy.free(), 1 << 20);
+        let memory =
+            unsafe { core::slice::from_raw_parts(region.start() as *const u8, region.size()) };
+        assert!(memory.iter().all(|&byte| byte == 0xAB));
+    }
+
+    #[test]
+    fn order_for_rounds_up() {
+        assert_eq!(BuddyAllocator::order_for(1, 1), Some(PAGE_ORDER));
+        assert_eq!(
+            BuddyAllocator::order_for(PAGE_SIZE + 1, 8),
+            Some(PAGE_ORDER + 1)
+        );
+        assert_eq!(BuddyAllocator::order_for(16, 1 << 14), Some(14));
+        assert_eq!(
+            BuddyAllocator::order_for(1 << MAX_ORDER, 8),
+            Some(MAX_ORDER)
+        );
+        assert_eq!(BuddyAllocator::order_for((1 << MAX_ORDER) + 1, 8), None);
+    }
+
+    #[test]
+    fn split_and_merge() {
+        let region = Region::new(1 << 20);
+        let mut buddy = new_buddy(&region);
+        let free = buddy.free();
+
+        // the page after the page table has no free buddy, the next two come from a split
+        let first = buddy.allocate(PAGE_ORDER).unwrap();
+        let second = buddy.allocate(PAGE_ORDER).unwrap();
+        let third = buddy.allocate(PAGE_ORDER).unwrap();
+        assert_eq!(second ^ third, PAGE_SIZE, "pages of one split are buddies");
+        assert_eq!(buddy.free(), free - 3 * PAGE_SIZE);
+
+        unsafe {
+            buddy.deallocate(second, PAGE_ORDER);
+            buddy.deallocate(first, PAGE_ORDER);
+            buddy.deallocate(third, PAGE_ORDER);
+        }
+        assert_eq!(buddy.free(), free);
+        assert_eq!(buddy.largest_free_block(), 1 << 19);
+    }
+
+    #[test]
+    fn blocks_are_aligned_to_their_size() {
+        let region = Region::new(1 << 20);
+        let mut buddy = new_buddy(&region);
+        let page = buddy.allocate(PAGE_ORDER).unwrap();
+        for order in PAGE_ORDER..=17 {
+            let address = buddy.allocate(order).unwrap();
+            assert_eq!(address % (1 << order), 0);
+        }
+        unsafe { buddy.deallocate(page, PAGE_ORDER) };
+    }
+
+    #[test]
+    fn exhaustion_and_recovery() {
+        let region = Region::new(1 << 20);
+        let mut buddy = new_buddy(&region);
+        let total = buddy.total();
+
+        let mut pages = std::vec::Vec::new();
+        while let Some(page) = buddy.allocate(PAGE_ORDER) {
+            pages.push(page);
+        }
+        assert_eq!(pages.len() * PAGE_SIZE, total);
+        assert_eq!(buddy.free(), 0);
+        assert_eq!(buddy.largest_free_block(), 0);
+        assert_eq!(buddy.allocate(PAGE_ORDER), None);
+
+        // freeing every other page leaves nothing to merge
+        for page in pages.iter().step_by(2) {
+            unsafe { buddy.deallocate(*page, PAGE_ORDER) };
+        }
+        assert_eq!(buddy.largest_free_block(), PAGE_SIZE);
+        assert_eq!(buddy.allocate(PAGE_ORDER + 1), None);
+
+        for page in pages.iter().skip(1).step_by(2) {
+            unsafe { buddy.deallocate(*page, PAGE_ORDER) };
+        }
+        assert_eq!(buddy.free(), total);
+        assert_eq!(buddy.largest_free_block(), 1 << 19);
+    }
+}
@@ -0,0 +1,390 @@
+//! Kernel heap of rOSt.
+//!
+//! Large allocations come from a buddy allocator, small ones from slab caches  
+//! with power of two object sizes which take their slabs from the buddy allocator.  
+//! Neither writes into the memory it hands out, all bookkeeping is in a page table  
+//! which can be kept outside of the heap when user mode can write to the heap.  
+//! The crate does not depend on the target, its tests run on the host:  
+//! `$ cargo test --manifest-path allocator/Cargo.toml --target x86_64-unknown-linux-gnu -Z build-std=std`
+#![no_std]
+
+#[cfg(test)]
+extern crate std;
+
+mod buddy;
+mod slab;
+
+pub use buddy::{BuddyAllocator, MAX_ORDER, PAGE_SIZE};
+pub use slab::SlabCache;
+
+use core::alloc::Layout;
+use core::fmt;
+
+/// Object sizes of the slab caches.
+///
+/// `TCB`s, message nodes and `Box`es of the kernel fit into the small classes,  
+/// the 8 KiB class holds the default thread stacks.
+pub const SIZE_CLASSES: [usize; 9] = [16, 32, 64, 128, 256, 512, 1024, 2048, 8192];
+
+/// Buddy allocator with slab caches for `SIZE_CLASSES`.
+pub struct Heap {
+    buddy: BuddyAllocator,
+    caches: [SlabCache; SIZE_CLASSES.len()],
+    requested: usize,
+}
+
+/// Snapshot of the heap usage in bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HeapStats {
+    /// managed memory without the page table of the buddy allocator
+    pub total: usize,
+    /// blocks and objects handed out, including the rounding up to their size
+    pub used: usize,
+    /// sum of the requested sizes, `used - requested` is lost to rounding
+    pub requested: usize,
+    /// free blocks of the buddy allocator
+    pub free: usize,
+    /// free objects in the slabs of the caches
+    pub cached: usize,
+    /// the largest allocation above the slab sizes which can succeed
+    pub largest_free_block: usize,
+}
+
+impl HeapStats {
+    /// Share of the free memory in percent that is not part of the largest free block.
+    ///
+    /// 0 means all free memory is in one block, values close to 100 mean  
+    /// large allocations fail although there is enough free memory.
+    pub fn fragmentation_percent(&self) -> usize {
+        (self.largest_free_block * 100)
+            .checked_div(self.free)
+            .map_or(0, |largest| 100 - largest)
+    }
+}
+
+impl fmt::Display for HeapStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "used: {:#X} (requested: {:#X}) free: {:#X} cached: {:#X} largest_free_block: {:#X} fragmentation: {}% total: {:#X}",
+            self.used,
+            self.requested,
+            self.free,
+            self.cached,
+            self.largest_free_block,
+            self.fragmentation_percent(),
+            self.total
+        )
+    }
+}
+
+impl Heap {
+    /// Creates a heap without memory, `init` has to be called before the first allocation.
+    pub const fn empty() -> Heap {
+        Heap {
+            buddy: BuddyAllocator::empty(),
+            caches: [
+                SlabCache::new(SIZE_CLASSES[0]),
+                SlabCache::new(SIZE_CLASSES[1]),
+                SlabCache::new(SIZE_CLASSES[2]),
+                SlabCache::new(SIZE_CLASSES[3]),
+                SlabCache::new(SIZE_CLASSES[4]),
+                SlabCache::new(SIZE_CLASSES[5]),
+                SlabCache::new(SIZE_CLASSES[6]),
+                SlabCache::new(SIZE_CLASSES[7]),
+                SlabCache::new(SIZE_CLASSES[8]),
+            ],
+            requested: 0,
+        }
+    }
+
+    /// Hands the memory from `start` to `start + size` to the heap.
+    ///
+    /// # Safety
+    /// The memory must be valid, unused and must not be handed out twice.
+    /// The first pages of the memory hold the bookkeeping, so the memory has to be kernel-only.
+    pub unsafe fn init(&mut self, start: usize, size: usize) {
+        self.buddy.init(start, size);
+    }
+
+    /// Hands the memory from `start` to `start + size` to the heap,  
+    /// the bookkeeping is kept in `metadata_size(size)` bytes at `metadata`.
+    ///
+    /// # Safety
+    /// The memory must be valid, unused and must not be handed out twice.  
+    /// `metadata` must be aligned to 4 bytes and must stay reserved for the heap.
+    pub unsafe fn init_with_metadata(&mut self, start: usize, size: usize, metadata: *mut u8) {
+        self.buddy.init_with_metadata(start, size, metadata);
+    }
+
+    /// Number of bytes of bookkeeping `init_with_metadata` needs for `size` bytes of memory.
+    pub const fn metadata_size(size: usize) -> usize {
+        BuddyAllocator::metadata_size(size)
+    }
+
+    /// Returns the index of the cache serving `layout`, None for buddy allocations.
+    ///
+    /// Sizes between the page sized classes go to the buddy allocator  
+    /// if a buddy block is smaller than the next size class.
+    fn cache_index(layout: Layout) -> Option<usize> {
+        let size = layout.size().max(layout.align());
+        SIZE_CLASSES
+            .iter()
+            .position(|&object_size| size <= object_size)
+            .filter(|&index| {
+                SIZE_CLASSES[index] < PAGE_SIZE || SIZE_CLASSES[index] == size.next_power_of_two()
+            })
+    }
+
+    /// Returns the size of the block or object `allocate` uses for `layout`.
+    ///
+    /// None if the layout is larger than the largest buddy block.
+    pub fn allocation_size(layout: Layout) -> Option<usize> {
+        match Heap::cache_index(layout) {
+            Some(index) => Some(SIZE_CLASSES[index]),
+            None => {
+                BuddyAllocator::order_for(layout.size(), layout.align()).map(|order| 1 << order)
+            }
+        }
+    }
+
+    /// Allocates memory for `layout`, returns a null pointer if the heap is exhausted.
+    pub fn allocate(&mut self, layout: Layout) -> *mut u8 {
+        let address = match Heap::cache_index(layout) {
+            Some(index) => self.caches[index].allocate(&mut self.buddy),
+            None => BuddyAllocator::order_for(layout.size(), layout.align())
+                .and_then(|order| self.buddy.allocate(order)),
+        };
+        match address {
+            Some(address) => {
+                self.requested += layout.size();
+                address as *mut u8
+            }
+            None => core::ptr::null_mut(),
+        }
+    }
+
+    /// Frees memory returned by `allocate`.
+    ///
+    /// # Safety
+    /// `ptr` must have been allocated with the same `layout` and must not be used anymore.
+    pub unsafe fn deallocate(&mut self, ptr: *mut u8, layout: Layout) {
+        match Heap::cache_index(layout) {
+            Some(index) => self.caches[index].deallocate(&mut self.buddy, ptr as usize),
+            None => {
+                let order = BuddyAllocator::order_for(layout.size(), layout.align())
+                    .expect("heap: invalid layout");
+                self.buddy.deallocate(ptr as usize, order);
+            }
+        }
+        self.requested -= layout.size();
+    }
+
+    /// Slab caches in the order of `SIZE_CLASSES`.
+    pub fn caches(&self) -> &[SlabCache] {
+        &self.caches
+    }
+
+    pub fn stats(&self) -> HeapStats {
+        let slab_bytes: usize = self
+            .caches
+            .iter()
+            .map(|cache| cache.slabs() * cache.slab_size())
+            .sum();
+        let object_bytes: usize = self
+            .caches
+            .iter()
+            .map(|cache| cache.objects_in_use() * cache.object_size())
+            .sum();
+        let block_bytes = self.buddy.total() - self.buddy.free() - slab_bytes;
+        HeapStats {
+            total: self.buddy.total(),
+            used: block_bytes + object_bytes,
+            requested: self.requested,
+            free: self.buddy.free(),
+            cached: self.caches.iter().map(SlabCache::free_bytes).sum(),
+            largest_free_block: self.buddy.largest_free_block(),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::alloc::{alloc, dealloc};
+    use std::vec::Vec;
+
+    /// Memory from the host allocator which is aligned to its size like the kernel heap.
+    pub(crate) struct Region {
+        start: *mut u8,
+        layout: Layout,
+    }
+
+    impl Region {
+        pub(crate) fn new(size: usize) -> Region {
+            let layout = Layout::from_size_align(size, size).unwrap();
+            let start = unsafe { alloc(layout) };
+            assert!(!start.is_null());
+            Region { start, layout }
+        }
+
+        pub(crate) fn start(&self) -> usize {
+            self.start as usize
+        }
+
+        pub(crate) fn size(&self) -> usize {
+            self.layout.size()
+        }
+    }
+
+    impl Drop for Region {
+        fn drop(&mut self) {
+            unsafe { dealloc(self.start, self.layout) };
+        }
+    }
+
+    fn new_heap(region: &Region) -> Heap {
+        let mut heap = Heap::empty();
+        unsafe { heap.init(region.start(), region.size()) };
+        heap
+    }
+
+    #[test]
+    fn layouts_choose_cache_or_buddy() {
+        let layout = |size, align| Layout::from_size_align(size, align).unwrap();
+        assert_eq!(Heap::cache_index(layout(1, 1)), Some(0));
+        assert_eq!(Heap::cache_index(layout(17, 4)), Some(1));
+        assert_eq!(Heap::cache_index(layout(8, 64)), Some(2));
+        assert_eq!(Heap::cache_index(layout(8192, 8)), Some(8));
+        assert_eq!(Heap::cache_index(layout(3000, 8)), None);
+        assert_eq!(Heap::cache_index(layout(5000, 8)), Some(8));
+        assert_eq!(Heap::cache_index(layout(8193, 8)), None);
+
+        assert_eq!(Heap::allocation_size(layout(1, 1)), Some(16));
+        assert_eq!(Heap::allocation_size(layout(3000, 8)), Some(PAGE_SIZE));
+        assert_eq!(Heap::allocation_size(layout(5000, 8)), Some(8192));
+        assert_eq!(Heap::allocation_size(layout(40000, 8)), Some(64 * 1024));
+        assert_eq!(Heap::allocation_size(layout((1 << MAX_ORDER) + 1, 8)), None);
+    }
+
+    #[test]
+    fn allocations_respect_layout() {
+        let region = Region::new(1 << 20);
+        let mut heap = new_heap(&region);
+        for &(size, align) in &[
+            (1, 1),
+            (24, 8),
+            (100, 4),
+            (8, 512),
+            (5000, 8),
+            (16, 1 << 14),
+            (40000, 16),
+        ] {
+            let layout = Layout::from_size_align(size, align).unwrap();
+            let ptr = heap.allocate(layout);
+            assert!(!ptr.is_null());
+            assert_eq!(ptr as usize % align, 0);
+            unsafe {
+                ptr.write_bytes(0xAB, size);
+                heap.deallocate(ptr, layout);
+            }
+        }
+        assert_eq!(heap.stats().used, 0);
+        assert_eq!(heap.stats().requested, 0);
+    }
+
+    #[test]
+    fn stats_account_all_memory() {
+        let region = Region::new(1 << 20);
+        let mut heap = new_heap(&region);
+        let empty = heap.stats();
+        assert_eq!(empty.free, empty.total);
+        assert_eq!(
+            empty.fragmentation_percent(),
+            100 - (1 << 19) * 100 / empty.free
+        );
+
+        let small = Layout::from_size_align(100, 8).unwrap();
+        let large = Layout::from_size_align(20000, 8).unwrap();
+        let a = heap.allocate(small);
+        let b = heap.allocate(large);
+        let stats = heap.stats();
+        assert_eq!(stats.used, 128 + 32 * 1024);
+        assert_eq!(stats.requested, 100 + 20000);
+        assert_eq!(stats.cached, heap.caches()[3].free_bytes());
+        assert!(stats.used + stats.free + stats.cached <= stats.total);
+
+        unsafe {
+            heap.deallocate(a, small);
+            heap.deallocate(b, large);
+        }
+        let stats = heap.stats();
+        assert_eq!(stats.used, 0);
+        // the empty slab of the 128 byte cache stays cached
+        assert_eq!(stats.free, empty.free - heap.caches()[3].slab_size());
+    }
+
+    #[test]
+    fn exhausted_heap_returns_null() {
+        let region = Region::new(1 << 18);
+        let mut heap = new_heap(&region);
+        let layout = Layout::from_size_align(1 << 18, 8).unwrap();
+        assert!(heap.allocate(layout).is_null());
+        let huge = Layout::from_size_align((1 << MAX_ORDER) + 1, 8).unwrap();
+        assert!(heap.allocate(huge).is_null());
+    }
+
+    #[test]
+    fn random_allocations_do_not_overlap() {
+        let region = Region::new(1 << 22);
+        let mut heap = new_heap(&region);
+        let initial_free = heap.stats().free;
+        let mut live: Vec<(*mut u8, Layout, u8)> = Vec::new();
+        let mut seed: u32 = 0x1234_5678;
+        let mut next = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed as usize
+        };
+
+        for round in 0..20000 {
+            if live.is_empty() || next() % 3 != 0 {
+                let size = match next() % 8 {
+                    0 => 8192,
+                    1 => 1 + next() % 40000,
+                    _ => 1 + next() % 600,
+                };
+                let layout = Layout::from_size_align(size, 1 << (next() % 4)).unwrap();
+                let ptr = heap.allocate(layout);
+                if ptr.is_null() {
+                    continue;
+                }
+                let tag = round as u8;
+                unsafe { ptr.write_bytes(tag, size) };
+                live.push((ptr, layout, tag));
+            } else {
+                let (ptr, layout, tag) = live.swap_remove(next() % live.len());
+                let contents = unsafe { core::slice::from_raw_parts(ptr, layout.size()) };
+                assert!(
+                    contents.iter().all(|&byte| byte == tag),
+                    "allocation was overwritten"
+                );
+                unsafe { heap.deallocate(ptr, layout) };
+            }
+        }
+
+        for (ptr, layout, _) in live.drain(..) {
+            unsafe { heap.deallocate(ptr, layout) };
+        }
+        let stats = heap.stats();
+        assert_eq!(stats.used, 0);
+        assert_eq!(stats.requested, 0);
+        let cached_slabs: usize = heap
+            .caches()
+            .iter()
+            .map(|cache| cache.slabs() * cache.slab_size())
+            .sum();
+        assert_eq!(stats.free + cached_slabs, initial_free);
+    }
+}
@@ -0,0 +1,309 @@
+use crate::buddy::{BuddyAllocator, MIN_OBJECT_SIZE, NO_PAGE, PAGE_ORDER};
+
+/// Cache of equally sized objects carved from buddy blocks.
+///
+/// Every slab is a buddy block of `1 << slab_order` bytes filled with objects.  
+/// Objects are aligned to their power of two size, the slab of an object is found  
+/// by aligning its address down to the slab size. The free objects of a slab are  
+/// marked in the object map of the buddy allocator, its counters and list links  
+/// live in the `PageInfo` of its first page, so slabs hold nothing but objects.  
+/// Only slabs with free objects are kept in a list, one empty slab stays  
+/// in the cache so alternating allocations and frees do not hit the buddy allocator.
+pub struct SlabCache {
+    object_size: usize,
+    slab_order: usize,
+    /// first page of the first slab with free objects
+    partial_slabs: u32,
+    empty_slabs: usize,
+    slabs: usize,
+    objects_in_use: usize,
+}
+
+impl SlabCache {
+    /// Creates a cache for objects of `object_size` bytes, which has to be a power of two  
+    /// of at least `MIN_OBJECT_SIZE`.
+    ///
+    /// Slabs hold at least 16 objects to keep the number of buddy allocations low.
+    pub const fn new(object_size: usize) -> SlabCache {
+        let slab_order = object_size.trailing_zeros() as usize + 4;
+        SlabCache {
+            object_size,
+            slab_order: if slab_order > PAGE_ORDER {
+                slab_order
+            } else {
+                PAGE_ORDER
+            },
+            partial_slabs: NO_PAGE,
+            empty_slabs: 0,
+            slabs: 0,
+            objects_in_use: 0,
+        }
+    }
+
+    pub fn object_size(&self) -> usize {
+        self.object_size
+    }
+
+    pub fn slab_size(&self) -> usize {
+        1 << self.slab_order
+    }
+
+    /// Number of slabs taken from the buddy allocator.
+    pub fn slabs(&self) -> usize {
+        self.slabs
+    }
+
+    pub fn objects_in_use(&self) -> usize {
+        self.objects_in_use
+    }
+
+    /// Number of bytes in free objects of this cache's slabs.
+    pub fn free_bytes(&self) -> usize {
+        (self.slabs * self.objects_per_slab() - self.objects_in_use) * self.object_size
+    }
+
+    fn objects_per_slab(&self) -> usize {
+        self.slab_size() / self.object_size
+    }
+
+    /// Takes a free object, allocating a new slab from `buddy` if all slabs are full.
+    ///
+    /// Returns None if the buddy allocator is out of memory.
+    pub fn allocate(&mut self, buddy: &mut BuddyAllocator) -> Option<usize> {
+        if self.partial_slabs == NO_PAGE {
+            self.grow(buddy)?;
+        }
+        let slab_page = self.partial_slabs;
+        let slab_address = buddy.page_address(slab_page);
+        let first_bit = buddy.object_bit(slab_address);
+        let index = take_bit(buddy.object_map(), first_bit, self.objects_per_slab())
+            .expect("slab: partial slab without free objects");
+
+        let slab = buddy.page_info_at(slab_page);
+        if slab.objects_in_use == 0 {
+            self.empty_slabs -= 1;
+        }
+        slab.objects_in_use += 1;
+        if slab.objects_in_use as usize == self.objects_per_slab() {
+            self.unlink(buddy, slab_page);
+        }
+        self.objects_in_use += 1;
+        Some(slab_address + index * self.object_size)
+    }
+
+    /// Returns an object to its slab, a second empty slab is given back to `buddy`.
+    ///
+    /// # Safety
+    /// The object must have been allocated from this cache and must not be used anymore.
+    pub unsafe fn deallocate(&mut self, buddy: &mut BuddyAllocator, address: usize) {
+        let slab_address = address & !(self.slab_size() - 1);
+        let slab_page = buddy.page_index(slab_address);
+        let bit = buddy.object_bit(slab_address) + (address - slab_address) / self.object_size;
+        let map = buddy.object_map();
+        debug_assert!(
+            map[bit / 32] & 1 << (bit % 32) == 0,
+            "slab: invalid free of {:#x}",
+            address
+        );
+        map[bit / 32] |= 1 << (bit % 32);
+
+        let objects_in_use = buddy.page_info_at(slab_page).objects_in_use as usize;
+        debug_assert!(objects_in_use > 0, "slab: invalid free of {:#x}", address);
+        if objects_in_use == self.objects_per_slab() {
+            self.link(buddy, slab_page);
+        }
+        buddy.page_info_at(slab_page).objects_in_use -= 1;
+        self.objects_in_use -= 1;
+
+        if objects_in_use == 1 {
+            if self.empty_slabs > 0 {
+                self.unlink(buddy, slab_page);
+                self.slabs -= 1;
+                buddy.deallocate(slab_address, self.slab_order);
+            } else {
+                self.empty_slabs += 1;
+            }
+        }
+    }
+
+    /// Allocates an empty slab and marks all of its objects as free.
+    fn grow(&mut self, buddy: &mut BuddyAllocator) -> Option<()> {
+        debug_assert!(self.object_size >= MIN_OBJECT_SIZE);
+        let slab_address = buddy.allocate(self.slab_order)?;
+        let first_bit = buddy.object_bit(slab_address);
+        let objects = self.objects_per_slab();
+        let map = buddy.object_map();
+        for bit in first_bit..first_bit + objects {
+            map[bit / 32] |= 1 << (bit % 32);
+        }
+
+        let slab_page = buddy.page_index(slab_address);
+        buddy.page_info_at(slab_page).objects_in_use = 0;
+        self.link(buddy, slab_page);
+        self.slabs += 1;
+        self.empty_slabs += 1;
+        Some(())
+    }
+
+    fn link(&mut self, buddy: &mut BuddyAllocator, slab_page: u32) {
+        let next = self.partial_slabs;
+        if next != NO_PAGE {
+            buddy.page_info_at(next).prev = slab_page;
+        }
+        let slab = buddy.page_info_at(slab_page);
+        slab.prev = NO_PAGE;
+        slab.next = next;
+        self.partial_slabs = slab_page;
+    }
+
+    fn unlink(&mut self, buddy: &mut BuddyAllocator, slab_page: u32) {
+        let slab = *buddy.page_info_at(slab_page);
+        match slab.prev {
+            NO_PAGE => self.partial_slabs = slab.next,
+            prev => buddy.page_info_at(prev).next = slab.next,
+        }
+        if slab.next != NO_PAGE {
+            buddy.page_info_at(slab.next).prev = slab.prev;
+        }
+    }
+}
+
+/// Clears the first set bit of `map` among `count` bits from `first`,  
+/// which has to be a multiple of 32, and returns its offset to `first`.
+fn take_bit(map: &mut [u32], first: usize, count: usize) -> Option<usize> {
+    debug_assert_eq!(first % 32, 0);
+    for word_start in (first..first + count).step_by(32) {
+        let word = &mut map[word_start / 32];
+        if *word != 0 {
+            let bit = word_start - first + word.trailing_zeros() as usize;
+            if bit >= count {
+                return None;
+            }
+            *word &= *word - 1;
+            return Some(bit);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::Region;
+    use std::vec::Vec;
+
+    fn new_buddy(region: &Region) -> BuddyAllocator {
+        let mut buddy = BuddyAllocator::empty();
+        unsafe { buddy.init(region.start(), region.size()) };
+        buddy
+    }
+
+    #[test]
+    fn slab_geometry() {
+        let small = SlabCache::new(16);
+        assert_eq!(small.slab_size(), 1 << PAGE_ORDER);
+        assert_eq!(small.objects_per_slab(), (1 << PAGE_ORDER) / 16);
+
+        let stacks = SlabCache::new(8192);
+        assert_eq!(stacks.slab_size(), 128 * 1024);
+        assert_eq!(stacks.objects_per_slab(), 16);
+    }
+
+    #[test]
+    fn objects_are_aligned_and_distinct() {
+        let region = Region::new(1 << 20);
+        let mut buddy = new_buddy(&region);
+        let mut cache = SlabCache::new(64);
+
+        let mut objects: Vec<usize> = (0..200)
+            .map(|_| cache.allocate(&mut buddy).unwrap())
+            .collect();
+        assert!(objects.iter().all(|object| object % 64 == 0));
+        objects.sort_unstable();
+        objects.dedup();
+        assert_eq!(objects.len(), 200);
+        assert_eq!(cache.objects_in_use(), 200);
+        assert_eq!(cache.slabs(), 4);
+    }
+
+    #[test]
+    fn objects_keep_their_contents() {
+        let region = Region::new(1 << 20);
+        let mut buddy = new_buddy(&region);
+        let mut cache = SlabCache::new(32);
+
+        let objects: Vec<usize> = (0..300)
+            .map(|_| cache.allocate(&mut buddy).unwrap())
+            .collect();
+        for object in &objects {
+            unsafe { (*object as *mut u8).write_bytes(0xAB, 32) };
+        }
+        // freeing and allocating must not write into the objects
+        for object in objects.iter().step_by(2) {
+            unsafe { cache.deallocate(&mut buddy, *object) };
+        }
+        for _ in 0..100 {
+            cache.allocate(&mut buddy).unwrap();
+        }
+        for object in &objects {
+            let contents = unsafe { core::slice::from_raw_parts(*object as *const u8, 32) };
+            assert!(contents.iter().all(|&byte| byte == 0xAB));
+        }
+    }
+
+    #[test]
+    fn freed_objects_are_reused() {
+        let region = Region::new(1 << 20);
+        let mut buddy = new_buddy(&region);
+        let mut cache = SlabCache::new(256);
+
+        let first = cache.allocate(&mut buddy).unwrap();
+        let _second = cache.allocate(&mut buddy).unwrap();
+        unsafe { cache.deallocate(&mut buddy, first) };
+        assert_eq!(cache.allocate(&mut buddy), Some(first));
+    }
+
+    #[test]
+    fn keeps_one_empty_slab() {
+        let region = Region::new(1 << 20);
+        let mut buddy = new_buddy(&region);
+        let free = buddy.free();
+        let mut cache = SlabCache::new(8192);
+
+        // two full slabs
+        let stacks: Vec<usize> = (0..32)
+            .map(|_| cache.allocate(&mut buddy).unwrap())
+            .collect();
+        assert_eq!(cache.slabs(), 2);
+        assert_eq!(cache.free_bytes(), 0);
+
+        for stack in &stacks {
+            unsafe { cache.deallocate(&mut buddy, *stack) };
+        }
+        assert_eq!(cache.slabs(), 1);
+        assert_eq!(cache.objects_in_use(), 0);
+        assert_eq!(cache.free_bytes(), 16 * 8192);
+        assert_eq!(buddy.free(), free - cache.slab_size());
+
+        // the cached slab is used again without touching the buddy allocator
+        let stack = cache.allocate(&mut buddy).unwrap();
+        assert_eq!(buddy.free(), free - cache.slab_size());
+        unsafe { cache.deallocate(&mut buddy, stack) };
+    }
+
+    #[test]
+    fn out_of_memory() {
+        let region = Region::new(1 << 18);
+        let mut buddy = new_buddy(&region);
+        let mut cache = SlabCache::new(8192);
+
+        // only the upper 128 KiB block of the region is left after the page table
+        let stacks: Vec<usize> = (0..16)
+            .map(|_| cache.allocate(&mut buddy).unwrap())
+            .collect();
+        assert_eq!(cache.allocate(&mut buddy), None);
+        for stack in stacks {
+            unsafe { cache.deallocate(&mut buddy, stack) };
+        }
+    }
+}
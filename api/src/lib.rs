@@ -3,4 +3,6 @@
 
 extern crate alloc;
 
+pub mod pipe;
+pub mod sync;
 pub mod syscalls;
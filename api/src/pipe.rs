@@ -0,0 +1,59 @@
+use crate::syscalls::{self, SyscallError};
+use core::fmt;
+
+/// Handle of a named kernel pipe, closes the pipe when dropped.
+///
+/// Implements `core::fmt::Write`, so formatted text can be written  
+/// into the pipe with `write!`.
+///
+/// ```ignore
+/// let mut pipe = Pipe::open("results")?;
+/// writeln!(pipe, "{}", 42).unwrap();
+/// ```
+pub struct Pipe {
+    id: usize,
+}
+
+impl Pipe {
+    /// Creates a new pipe which other threads can open by `name`.
+    pub fn create(name: &str) -> Result<Self, SyscallError> {
+        syscalls::create_pipe(name).map(|id| Pipe { id })
+    }
+
+    /// Opens a pipe created by any thread.
+    pub fn open(name: &str) -> Result<Self, SyscallError> {
+        syscalls::open_pipe(name).map(|id| Pipe { id })
+    }
+
+    /// Returns the id of the pipe, e.g. for `Event::pipe_readable`.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Reads at least one byte, waits while the pipe is empty.  
+    /// Returns the amount of bytes read, see `syscalls::read_pipe` for the timeout.
+    pub fn read(&self, buffer: &mut [u8], timeout: Option<usize>) -> Result<usize, SyscallError> {
+        syscalls::read_pipe(self.id, buffer, timeout)
+    }
+
+    /// Writes `data`, waits while the pipe is full.  
+    /// Returns the amount of bytes written, see `syscalls::write_pipe` for the timeout.
+    pub fn write(&self, data: &[u8], timeout: Option<usize>) -> Result<usize, SyscallError> {
+        syscalls::write_pipe(self.id, data, timeout)
+    }
+}
+
+impl fmt::Write for Pipe {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        match self.write(s.as_bytes(), None) {
+            Ok(written) if written == s.len() => Ok(()),
+            _ => Err(fmt::Error),
+        }
+    }
+}
+
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        let _ = syscalls::close_pipe(self.id);
+    }
+}
@@ -0,0 +1,202 @@
+use crate::syscalls::{self, SyscallError};
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+
+/// Converts the result of a blocking syscall whose object is owned by the caller,  
+/// only `SyscallError::TimedOut` is passed on.
+fn expect_timed_out(result: Result<(), SyscallError>, operation: &str) -> Result<(), SyscallError> {
+    match result {
+        Ok(()) => Ok(()),
+        Err(SyscallError::TimedOut) => Err(SyscallError::TimedOut),
+        Err(error) => panic!("{}: {:?}", operation, error),
+    }
+}
+
+/// Mutual exclusion of the contained value backed by a kernel mutex.
+///
+/// The value is only accessible through the `MutexGuard` returned by `lock`,  
+/// which unlocks the mutex when dropped. Without atomics on the target there  
+/// is no `Arc`, share it between threads through a `'static` reference.
+///
+/// ```ignore
+/// let counter: &'static Mutex<usize> = Box::leak(Box::new(Mutex::new(0)));
+/// *counter.lock() += 1;
+/// ```
+pub struct Mutex<T> {
+    id: usize,
+    value: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Send for Mutex<T> {}
+unsafe impl<T: Send> Sync for Mutex<T> {}
+
+impl<T> Mutex<T> {
+    /// Creates a mutex, panics if the kernel cannot create it.
+    pub fn new(value: T) -> Self {
+        Self::try_new(value).expect("Mutex::new: cannot create kernel mutex")
+    }
+
+    /// Creates a mutex, fails if the kernel cannot create it.
+    pub fn try_new(value: T) -> Result<Self, SyscallError> {
+        Ok(Mutex {
+            id: syscalls::create_mutex()?,
+            value: UnsafeCell::new(value),
+        })
+    }
+
+    /// Locks the mutex, waits while another thread holds it.
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        syscalls::lock_mutex(self.id, None).expect("lock: mutex already locked by this thread");
+        MutexGuard { mutex: self }
+    }
+
+    /// Locks the mutex, waits at most `timeout_ms` milliseconds for another thread to unlock it.
+    pub fn lock_timeout(&self, timeout_ms: usize) -> Result<MutexGuard<'_, T>, SyscallError> {
+        expect_timed_out(
+            syscalls::lock_mutex(self.id, Some(timeout_ms)),
+            "lock_timeout",
+        )?;
+        Ok(MutexGuard { mutex: self })
+    }
+}
+
+impl<T> Drop for Mutex<T> {
+    fn drop(&mut self) {
+        let _ = syscalls::destroy_sync_object(self.id);
+    }
+}
+
+/// Access to the value of a locked `Mutex`, unlocks it when dropped.
+pub struct MutexGuard<'a, T> {
+    mutex: &'a Mutex<T>,
+}
+
+impl<T> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.value.get() }
+    }
+}
+
+impl<T> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.value.get() }
+    }
+}
+
+impl<T> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        syscalls::unlock_mutex(self.mutex.id).expect("unlock: mutex not owned");
+    }
+}
+
+/// Counting semaphore backed by the kernel.
+pub struct Semaphore {
+    id: usize,
+}
+
+impl Semaphore {
+    /// Creates a semaphore with `count` available permits, panics if the kernel cannot create it.
+    pub fn new(count: usize) -> Self {
+        Self::try_new(count).expect("Semaphore::new: cannot create kernel semaphore")
+    }
+
+    /// Creates a semaphore with `count` available permits, fails if the kernel cannot create it.
+    pub fn try_new(count: usize) -> Result<Self, SyscallError> {
+        Ok(Semaphore {
+            id: syscalls::create_semaphore(count)?,
+        })
+    }
+
+    /// Takes a permit, waits until one is available.
+    pub fn acquire(&self) {
+        expect_timed_out(syscalls::wait_semaphore(self.id, None), "acquire")
+            .expect("acquire: timed out without timeout");
+    }
+
+    /// Takes a permit, waits at most `timeout_ms` milliseconds for one.
+    pub fn acquire_timeout(&self, timeout_ms: usize) -> Result<(), SyscallError> {
+        expect_timed_out(
+            syscalls::wait_semaphore(self.id, Some(timeout_ms)),
+            "acquire_timeout",
+        )
+    }
+
+    /// Returns a permit and wakes a thread waiting for one.
+    pub fn release(&self) {
+        syscalls::signal_semaphore(self.id).expect("release: invalid semaphore");
+    }
+}
+
+impl Drop for Semaphore {
+    fn drop(&mut self) {
+        let _ = syscalls::destroy_sync_object(self.id);
+    }
+}
+
+/// Condition variable backed by the kernel, used together with a `Mutex`.
+pub struct Condvar {
+    id: usize,
+}
+
+impl Default for Condvar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Condvar {
+    /// Creates a condition variable, panics if the kernel cannot create it.
+    pub fn new() -> Self {
+        Self::try_new().expect("Condvar::new: cannot create kernel condvar")
+    }
+
+    /// Creates a condition variable, fails if the kernel cannot create it.
+    pub fn try_new() -> Result<Self, SyscallError> {
+        Ok(Condvar {
+            id: syscalls::create_condvar()?,
+        })
+    }
+
+    /// Unlocks the mutex of the guard and waits for a notification.  
+    /// The mutex is locked again before returning.
+    pub fn wait<'a, T>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        expect_timed_out(
+            syscalls::wait_condvar(self.id, guard.mutex.id, None),
+            "wait",
+        )
+        .expect("wait: timed out without timeout");
+        guard
+    }
+
+    /// Like `wait` but waits at most `timeout_ms` milliseconds for a notification.  
+    /// The mutex is locked again in both cases.
+    pub fn wait_timeout<'a, T>(
+        &self,
+        guard: MutexGuard<'a, T>,
+        timeout_ms: usize,
+    ) -> (MutexGuard<'a, T>, Result<(), SyscallError>) {
+        let result = expect_timed_out(
+            syscalls::wait_condvar(self.id, guard.mutex.id, Some(timeout_ms)),
+            "wait_timeout",
+        );
+        (guard, result)
+    }
+
+    /// Wakes one thread waiting for this condition variable.
+    pub fn notify_one(&self) {
+        syscalls::signal_condvar(self.id).expect("notify_one: invalid condvar");
+    }
+
+    /// Wakes all threads waiting for this condition variable.
+    pub fn notify_all(&self) {
+        syscalls::broadcast_condvar(self.id).expect("notify_all: invalid condvar");
+    }
+}
+
+impl Drop for Condvar {
+    fn drop(&mut self) {
+        let _ = syscalls::destroy_sync_object(self.id);
+    }
+}
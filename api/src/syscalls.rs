@@ -1,156 +1,1122 @@
 use alloc::boxed::Box;
+use core::convert::{Infallible, TryFrom};
+use core::ffi::c_void;
 use num_enum::IntoPrimitive;
 use num_enum::TryFromPrimitive;
+use rost_macros::syscalls;
 
-/// syscall calling convention
-/// syscall id via swi assembly and r0-r2 are used for possible arguments 
+/// Signatures of all syscalls, implemented by the kernel.
+///
+/// Every syscall passes its arguments in r0-r2 and the syscall id as immediate  
+/// of the `swi` instruction, the result is returned in r0 and encoded by  
+/// `encode_result`. `#[syscalls]` generates the `Syscalls` ids, the user stubs  
+/// in `raw` and the kernel's `dispatch` from these signatures.
+#[syscalls]
+pub trait SyscallHandler {
+    /// Writes a character to the DBGU.
+    #[syscall(SendDBGU = 10)]
+    fn send_dbgu(character: u8) -> Result<usize, SyscallError>;
+    /// Receives a character of the subscribed DBGU service, a timeout of `NO_TIMEOUT` waits without limit.
+    #[syscall(ReceiveDBGU = 11)]
+    fn receive_dbgu(blocking: bool, timeout_ms: usize) -> Result<usize, SyscallError>;
+    /// Writes up to `MAX_DBGU_WRITE_SIZE` bytes to the DBGU at once, returns the amount written.
+    #[syscall(WriteDBGU = 12)]
+    fn write_dbgu(data: *const u8, length: usize) -> Result<usize, SyscallError>;
+    /// Reads characters of the subscribed DBGU service, returns the amount read.  
+    /// The timeout of `transfer` limits how long `mode` waits for them.
+    #[syscall(ReadDBGU = 13)]
+    fn read_dbgu(
+        transfer: *const TransferParameters,
+        mode: ReadMode,
+    ) -> Result<usize, SyscallError>;
+    /// Prints kernel state like the thread list to the DBGU, user threads cannot read it.
+    #[syscall(PrintKernelInfo = 14)]
+    fn print_kernel_info(info: KernelInfo) -> Result<usize, SyscallError>;
+    /// Allocates memory on the user heap, returns its address.
+    #[syscall(Allocate = 20)]
+    fn allocate(size: usize, align: usize) -> Result<usize, SyscallError>;
+    /// Frees memory with the size and alignment it was allocated with.
+    #[syscall(Deallocate = 21)]
+    fn deallocate(ptr: *mut u8, size: usize, align: usize) -> Result<usize, SyscallError>;
+    /// Sets how many bytes of the user heap a descendant of the current thread may allocate.
+    #[syscall(SetHeapQuota = 22)]
+    fn set_heap_quota(thread_id: usize, quota: usize) -> Result<usize, SyscallError>;
+    /// Creates a thread which calls `entry(arg)`, returns the id of the thread.  
+    /// A stack size of zero selects the default, others have to be 1 KiB to 1 MiB.
+    #[syscall(CreateThread = 30)]
+    fn create_thread(
+        entry: ThreadEntry,
+        arg: *mut c_void,
+        stack_size: usize,
+    ) -> Result<usize, SyscallError>;
+    /// Stops the current thread, the exit code is passed to the joining parent.  
+    /// Exit codes above `MAX_EXIT_CODE` are clamped to it, they are reserved for  
+    /// `EXIT_CODE_KILLED` and `EXIT_CODE_FAULT`.
+    #[syscall(ExitThread = 31)]
+    fn exit_thread(exit_code: usize) -> Result<usize, SyscallError>;
+    /// Gives the processor to the next ready thread.
+    #[syscall(YieldThread = 32)]
+    fn yield_thread() -> Result<usize, SyscallError>;
+    /// Waits for a child thread to exit and returns its exit code.  
+    /// Fails with `NotAChild` for other threads and `Detached` for adopted children.  
+    /// A timeout of `NO_TIMEOUT` waits without limit.
+    #[syscall(JoinThread = 33)]
+    fn join_thread(thread_id: usize, timeout_ms: usize) -> Result<usize, SyscallError>;
+    /// Subscribes a service, fails if it is already subscribed.
+    #[syscall(Subscribe = 34)]
+    fn subscribe(service: ThreadServices) -> Result<usize, SyscallError>;
+    /// Unsubscribes a subscribed service.
+    #[syscall(Unsubscribe = 35)]
+    fn unsubscribe(service: ThreadServices) -> Result<usize, SyscallError>;
+    /// Sets the priority of the current thread or one of its children.
+    #[syscall(SetPriority = 36)]
+    fn set_priority(thread_id: usize, priority: Priority) -> Result<usize, SyscallError>;
+    /// Returns the priority of a thread without inherited priority.
+    #[syscall(GetPriority = 37)]
+    fn get_priority(thread_id: usize) -> Result<usize, SyscallError>;
+    /// Creates a periodic real-time thread which calls `entry(arg)`, returns the id of the thread.
+    #[syscall(CreatePeriodicThread = 38)]
+    fn create_periodic_thread(
+        entry: ThreadEntry,
+        arg: *mut c_void,
+        parameters: *const PeriodicParameters,
+    ) -> Result<usize, SyscallError>;
+    /// Waits for the next period of a periodic thread, returns its deadline misses so far.
+    #[syscall(WaitNextPeriod = 39)]
+    fn wait_next_period() -> Result<usize, SyscallError>;
+    /// Returns the real time since boot in real time units.
+    #[syscall(GetCurrentRealTime = 40)]
+    fn get_current_real_time() -> Result<usize, SyscallError>;
+    /// Sleeps at least `time_ms` milliseconds, returns the slept time in milliseconds.
+    #[syscall(Sleep = 41)]
+    fn sleep(time_ms: usize) -> Result<usize, SyscallError>;
+    /// Starts a timer of the subscribed timer service, returns the timer id.
+    #[syscall(CreateTimer = 42)]
+    fn create_timer(interval_ms: usize, periodic: bool) -> Result<usize, SyscallError>;
+    /// Stops a timer of the current thread.
+    #[syscall(CancelTimer = 43)]
+    fn cancel_timer(timer_id: usize) -> Result<usize, SyscallError>;
+    /// Receives the timer id of the next tick, a timeout of `NO_TIMEOUT` waits without limit.
+    #[syscall(ReceiveTimer = 44)]
+    fn receive_timer(blocking: bool, timeout_ms: usize) -> Result<usize, SyscallError>;
+    /// Returns the real time since boot in milliseconds.
+    #[syscall(GetUptime = 45)]
+    fn get_uptime() -> Result<usize, SyscallError>;
+    /// Stops a descendant of the current thread.
+    #[syscall(KillThread = 50)]
+    fn kill_thread(thread_id: usize) -> Result<usize, SyscallError>;
+    /// Stops scheduling a descendant of the current thread.
+    #[syscall(SuspendThread = 51)]
+    fn suspend_thread(thread_id: usize) -> Result<usize, SyscallError>;
+    /// Continues a suspended thread.
+    #[syscall(ResumeThread = 52)]
+    fn resume_thread(thread_id: usize) -> Result<usize, SyscallError>;
+    /// Starts or stops recording the syscalls of a descendant of the current thread.
+    #[syscall(TraceThread = 53)]
+    fn trace_thread(thread_id: usize, enabled: bool) -> Result<usize, SyscallError>;
+    /// Starts a process running a copy of a user binary, returns the id of its main thread.
+    #[syscall(SpawnProcess = 54)]
+    fn spawn_process(image: *const u8, length: usize) -> Result<usize, SyscallError>;
+    /// Creates a kernel mutex, returns its id.
+    #[syscall(CreateMutex = 60)]
+    fn create_mutex() -> Result<usize, SyscallError>;
+    /// Locks a mutex, a timeout of `NO_TIMEOUT` waits without limit.
+    #[syscall(LockMutex = 61)]
+    fn lock_mutex(mutex_id: usize, timeout_ms: usize) -> Result<usize, SyscallError>;
+    /// Unlocks a mutex owned by the current thread.
+    #[syscall(UnlockMutex = 62)]
+    fn unlock_mutex(mutex_id: usize) -> Result<usize, SyscallError>;
+    /// Creates a counting semaphore with `count` permits, returns its id.
+    #[syscall(CreateSemaphore = 63)]
+    fn create_semaphore(count: usize) -> Result<usize, SyscallError>;
+    /// Takes a permit of a semaphore, a timeout of `NO_TIMEOUT` waits without limit.
+    #[syscall(WaitSemaphore = 64)]
+    fn wait_semaphore(semaphore_id: usize, timeout_ms: usize) -> Result<usize, SyscallError>;
+    /// Returns a permit to a semaphore.
+    #[syscall(SignalSemaphore = 65)]
+    fn signal_semaphore(semaphore_id: usize) -> Result<usize, SyscallError>;
+    /// Creates a condition variable, returns its id.
+    #[syscall(CreateCondvar = 66)]
+    fn create_condvar() -> Result<usize, SyscallError>;
+    /// Unlocks the mutex and waits for a signal of the condition variable,  
+    /// a timeout of `NO_TIMEOUT` waits without limit.
+    #[syscall(WaitCondvar = 67)]
+    fn wait_condvar(
+        condvar_id: usize,
+        mutex_id: usize,
+        timeout_ms: usize,
+    ) -> Result<usize, SyscallError>;
+    /// Wakes one thread waiting for the condition variable.
+    #[syscall(SignalCondvar = 68)]
+    fn signal_condvar(condvar_id: usize) -> Result<usize, SyscallError>;
+    /// Wakes all threads waiting for the condition variable.
+    #[syscall(BroadcastCondvar = 69)]
+    fn broadcast_condvar(condvar_id: usize) -> Result<usize, SyscallError>;
+    /// Removes a mutex, semaphore or condition variable.
+    #[syscall(DestroySyncObject = 70)]
+    fn destroy_sync_object(id: usize) -> Result<usize, SyscallError>;
+    /// Puts a copy of the message into the mailbox of a thread.
+    #[syscall(SendMessage = 80)]
+    fn send_message(
+        thread_id: usize,
+        data: *const u8,
+        length: usize,
+    ) -> Result<usize, SyscallError>;
+    /// Takes the oldest message out of the mailbox, returns its length.  
+    /// A timeout of `NO_TIMEOUT` waits without limit.
+    #[syscall(ReceiveMessage = 81)]
+    fn receive_message(
+        buffer: *mut u8,
+        length: usize,
+        timeout_ms: usize,
+    ) -> Result<usize, SyscallError>;
+    /// Creates and opens a named pipe, returns its id.
+    #[syscall(CreatePipe = 90)]
+    fn create_pipe(name: *const u8, length: usize) -> Result<usize, SyscallError>;
+    /// Opens a named pipe, returns its id.
+    #[syscall(OpenPipe = 91)]
+    fn open_pipe(name: *const u8, length: usize) -> Result<usize, SyscallError>;
+    /// Reads from a pipe, returns the amount of bytes read.
+    #[syscall(ReadPipe = 92)]
+    fn read_pipe(
+        pipe_id: usize,
+        transfer: *const TransferParameters,
+    ) -> Result<usize, SyscallError>;
+    /// Writes the data into a pipe, returns the amount of bytes written.
+    #[syscall(WritePipe = 93)]
+    fn write_pipe(
+        pipe_id: usize,
+        transfer: *const TransferParameters,
+    ) -> Result<usize, SyscallError>;
+    /// Closes a pipe opened by a thread of the current process.
+    #[syscall(ClosePipe = 94)]
+    fn close_pipe(pipe_id: usize) -> Result<usize, SyscallError>;
+    /// Waits for the first of `count` events, returns its index.  
+    /// A timeout of `NO_TIMEOUT` waits without limit.
+    #[syscall(WaitAny = 100)]
+    fn wait_any(
+        events: *const Event,
+        count: usize,
+        timeout_ms: usize,
+    ) -> Result<usize, SyscallError>;
+    /// Registers the handler of a signal for the current thread, None removes it.
+    #[syscall(RegisterSignalHandler = 110)]
+    fn register_signal_handler(
+        signal: Signal,
+        handler: Option<SignalHandler>,
+    ) -> Result<usize, SyscallError>;
+    /// Sends a signal to a descendant thread, returns zero if it has no handler for it.
+    #[syscall(SendSignal = 111)]
+    fn send_signal(thread_id: usize, signal: Signal) -> Result<usize, SyscallError>;
+    /// Continues the code interrupted by a signal, called by the kernel's signal entry only.
+    #[syscall(SignalReturn = 112)]
+    fn signal_return() -> Result<usize, SyscallError>;
+}
 
-#[derive(Debug, Eq, PartialEq, TryFromPrimitive)]
-#[repr(u32)]
-pub enum Syscalls {
-    SendDBGU = 10,
-    ReceiveDBGU = 11,
-    Allocate = 20,
-    Deallocate = 21,
-    CreateThread = 30,
-    ExitThread = 31,
-    YieldThread = 32,
-    JoinThread = 33,
-    Subscribe = 34,
-    Unsubscribe = 35,
-    GetCurrentRealTime = 40,
-    Sleep = 41,
+/// Maximum amount of messages waiting in the mailbox of a thread.
+pub const MAILBOX_CAPACITY: usize = 16;
+/// Maximum size of a single message in bytes.
+pub const MAX_MESSAGE_SIZE: usize = 256;
+/// Maximum amount of events passed to `wait_any`.
+pub const MAX_WAIT_EVENTS: usize = 8;
+/// Size of the ring buffer of a pipe in bytes.
+pub const PIPE_CAPACITY: usize = 256;
+/// Maximum length of a pipe name in bytes.
+pub const MAX_PIPE_NAME_LENGTH: usize = 32;
+/// Maximum amount of bytes written to the DBGU by one syscall.
+pub const MAX_DBGU_WRITE_SIZE: usize = 256;
+/// Maximum size of the image of a process, which is mapped to the start of the user code.
+pub const MAX_PROCESS_IMAGE_SIZE: usize = 1024 * 1024;
+/// Bytes of the user heap a thread may allocate unless its parent set another quota,  
+/// new threads start with the quota of their parent.
+pub const DEFAULT_HEAP_QUOTA: usize = 1024 * 1024;
+/// Timeout in milliseconds of blocking syscalls which waits without limit.  
+/// A timeout of zero does not wait, it fails with `SyscallError::TimedOut` right away.
+pub const NO_TIMEOUT: usize = usize::MAX;
+
+/// Highest exit code a thread can pass to `exit_thread`, larger ones are clamped by the  
+/// kernel because the values above are reserved for the kernel's exit codes and errors.
+pub const MAX_EXIT_CODE: usize = usize::MAX - 0x102;
+/// Exit code of a thread stopped by `kill_thread`.
+pub const EXIT_CODE_KILLED: usize = MAX_EXIT_CODE + 1;
+/// Exit code of a thread stopped by a fault in its code, e.g. a data abort.
+pub const EXIT_CODE_FAULT: usize = MAX_EXIT_CODE + 2;
+
+/// Lowest return register value which encodes a `SyscallError` instead of a result.
+pub const SYSCALL_ERROR_MIN: usize = EXIT_CODE_FAULT + 1;
+
+/// Defines `SyscallError` from `Variant = code` lines, adds the catch-all `Unknown(u8)`  
+/// and the conversions from and into the codes.
+macro_rules! syscall_errors {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $($(#[doc = $doc:literal])* $variant:ident = $code:literal,)*
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $($(#[doc = $doc])* $variant,)*
+            /// an error code unknown to this version of the api, e.g. of a newer kernel
+            Unknown(u8),
+        }
+
+        impl $name {
+            /// Returns the stable code of the error.
+            pub fn code(self) -> u8 {
+                match self {
+                    $($name::$variant => $code,)*
+                    $name::Unknown(code) => code,
+                }
+            }
+
+            /// Returns the error of a code, `Unknown` for codes this version does not know.
+            pub fn from_code(code: u8) -> Self {
+                match code {
+                    $($code => $name::$variant,)*
+                    code => $name::Unknown(code),
+                }
+            }
+        }
+    };
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Debug, TryFromPrimitive, IntoPrimitive, Ord, PartialOrd)]
-#[repr(u32)]
-pub enum ThreadServices {
-    DBGU = 10,
+#[rustfmt::skip]
+syscall_errors! {
+/// Errors of the syscalls, shared by all of them.
+///
+/// The kernel encodes an error as `usize::MAX - code` in the return register,  
+/// so results stay below `SYSCALL_ERROR_MIN`. The codes are stable, new  
+/// errors only get appended. Codes unknown to this version of the api  
+/// decode to `SyscallError::Unknown`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SyscallError {
+    /// the timeout of a blocking syscall elapsed before the awaited event
+    TimedOut = 0,
+    /// the kernel does not know the syscall id
+    UnknownSyscall = 1,
+    /// an argument is out of range, e.g. an unknown service or signal or an invalid layout
+    InvalidArgument = 2,
+    /// no thread with this id exists, it already stopped or was joined
+    UnknownThread = 3,
+    /// the thread may not do this, e.g. kill a thread which is not its descendant
+    NotPermitted = 4,
+    /// the service is already subscribed by the thread
+    AlreadySubscribed = 5,
+    /// the service has to be subscribed first
+    NotSubscribed = 6,
+    /// a non-blocking receive found nothing to receive
+    WouldBlock = 7,
+    /// the kernel or user heap is exhausted
+    OutOfMemory = 8,
+    /// the synchronization object does not exist, has another type or the operation  
+    /// is not allowed, e.g. unlocking a mutex owned by another thread
+    InvalidSyncObject = 9,
+    /// the mailbox of the receiver holds `MAILBOX_CAPACITY` messages, try again later
+    MailboxFull = 10,
+    /// the message is larger than `MAX_MESSAGE_SIZE`
+    MessageTooLarge = 11,
+    /// the next message is larger than the buffer, it stays in the mailbox
+    BufferTooSmall = 12,
+    /// `create_pipe` was called with the name of an existing pipe
+    PipeAlreadyExists = 13,
+    /// no pipe with this name or id exists, or it was closed while waiting
+    PipeNotFound = 14,
+    /// the pipe name is empty, longer than `MAX_PIPE_NAME_LENGTH` or not valid UTF-8
+    InvalidName = 15,
+    /// the timer does not exist anymore or belongs to another thread
+    UnknownTimer = 16,
+    /// a buffer passed to the kernel is not in user memory
+    BadAddress = 17,
+    /// the allocation would exceed the heap quota of the thread
+    QuotaExceeded = 18,
+    /// the thread to join is not a child of the calling thread
+    NotAChild = 19,
+    /// the thread to join was adopted after its parent stopped, nobody can join it
+    Detached = 20,
+}
+}
+
+impl SyscallError {
+    /// Returns the return register value of the error.
+    pub fn encode(self) -> usize {
+        usize::MAX - self.code() as usize
+    }
 }
 
-#[inline(never)]
-pub extern "C" fn subscribe(service: ThreadServices) {
-    unsafe {
-        asm!("swi #{call_id}", call_id = const Syscalls::Subscribe as u32, in("r0") service as u32);
+/// Encodes the result of a syscall for the return register.
+pub fn encode_result(result: Result<usize, SyscallError>) -> usize {
+    match result {
+        Ok(value) => {
+            debug_assert!(value < SYSCALL_ERROR_MIN, "syscall result in error range");
+            value
+        }
+        Err(error) => error.encode(),
     }
 }
 
-#[inline(never)]
-pub extern "C" fn unsubscribe(service: ThreadServices) {
-    unsafe {
-        asm!("swi #{call_id}", call_id = const Syscalls::Unsubscribe as u32, in("r0") service as u32);
+/// Decodes the return register value of a syscall.
+pub fn decode_result(value: usize) -> Result<usize, SyscallError> {
+    if value < SYSCALL_ERROR_MIN {
+        return Ok(value);
     }
+    Err(SyscallError::from_code((usize::MAX - value) as u8))
+}
+
+/// Conversion of syscall arguments from and into the registers r0-r2.
+pub trait SyscallArgument: Sized {
+    fn into_register(self) -> usize;
+    /// Fails with `SyscallError::InvalidArgument` if the register holds no valid value.
+    fn from_register(register: usize) -> Result<Self, SyscallError>;
 }
 
-#[inline(never)]
-pub extern "C" fn get_current_realtime() -> usize {
-    let time: usize;
-    unsafe {
-        asm!("swi #{call_id}", call_id = const Syscalls::GetCurrentRealTime as u32, lateout("r0") time);
+impl SyscallArgument for usize {
+    fn into_register(self) -> usize {
+        self
+    }
+
+    fn from_register(register: usize) -> Result<Self, SyscallError> {
+        Ok(register)
     }
-    time
 }
 
-#[inline(never)]
-pub extern "C" fn sleep_ms(time_ms: usize) -> usize {
-    let actual_sleep: usize;
-    unsafe {
-        asm!("swi #{call_id}", 
-        call_id = const Syscalls::Sleep as u32, in("r0") time_ms, lateout("r0") actual_sleep)
+impl SyscallArgument for u8 {
+    fn into_register(self) -> usize {
+        self as usize
+    }
+
+    fn from_register(register: usize) -> Result<Self, SyscallError> {
+        u8::try_from(register).map_err(|_| SyscallError::InvalidArgument)
     }
-    actual_sleep
 }
 
-#[inline(never)]
-pub fn join_thread(thread_id: usize, timeout: Option<usize>) -> usize {
-    let child_thread_result: usize;
-    unsafe {
-        asm!("swi #{call_id}", 
-        call_id = const Syscalls::JoinThread as u32, in("r0") thread_id, in("r1") timeout.unwrap_or_default(), lateout("r0") child_thread_result)
+impl SyscallArgument for bool {
+    fn into_register(self) -> usize {
+        self as usize
+    }
+
+    fn from_register(register: usize) -> Result<Self, SyscallError> {
+        Ok(register != 0)
     }
-    child_thread_result
 }
 
-#[inline(never)]
-pub extern "C" fn allocate(size: usize, align: usize) -> *mut u8 {
-    let out_ptr: usize;
-    unsafe {
-        asm!("
-              swi #{call_id}
-            ", call_id = const Syscalls::Allocate as u32, lateout("r0") out_ptr, in("r0") size, in("r1") align,);
+impl<T> SyscallArgument for *const T {
+    fn into_register(self) -> usize {
+        self as usize
+    }
+
+    fn from_register(register: usize) -> Result<Self, SyscallError> {
+        Ok(register as *const T)
     }
-    out_ptr as *mut u8
 }
 
-#[inline(never)]
-pub extern "C" fn deallocate(ptr: *mut u8, size: usize, align: usize) {
-    unsafe {
-        asm!("
-              swi #{call_id}
-            ", call_id = const Syscalls::Deallocate as u32, in("r0") ptr, in("r1") size, in("r2") align);
+impl<T> SyscallArgument for *mut T {
+    fn into_register(self) -> usize {
+        self as usize
+    }
+
+    fn from_register(register: usize) -> Result<Self, SyscallError> {
+        Ok(register as *mut T)
     }
 }
 
-#[inline(never)]
-pub fn send_str_to_dbgu(chars: &str) {
-    for character in chars.chars() {
-        send_character_to_dbgu(character as u8);
+/// Implements `SyscallArgument` for enums with `u32` representation.
+macro_rules! enum_syscall_argument {
+    ($($enum:ty),*) => {
+        $(
+            impl SyscallArgument for $enum {
+                fn into_register(self) -> usize {
+                    u32::from(self) as usize
+                }
+
+                fn from_register(register: usize) -> Result<Self, SyscallError> {
+                    u32::try_from(register)
+                        .ok()
+                        .and_then(|value| Self::try_from(value).ok())
+                        .ok_or(SyscallError::InvalidArgument)
+                }
+            }
+        )*
+    };
+}
+
+enum_syscall_argument!(ThreadServices, ReadMode, KernelInfo, Signal);
+
+impl SyscallArgument for ThreadEntry {
+    fn into_register(self) -> usize {
+        self as usize
+    }
+
+    fn from_register(register: usize) -> Result<Self, SyscallError> {
+        if register == 0 {
+            return Err(SyscallError::InvalidArgument);
+        }
+        Ok(unsafe { core::mem::transmute::<usize, ThreadEntry>(register) })
     }
 }
 
-#[inline(never)]
-pub extern "C" fn send_character_to_dbgu(character: u8) {
-    unsafe {
-        asm!("swi #{call_id}", call_id = const Syscalls::SendDBGU as u32, in("r0") character as u8);
+impl SyscallArgument for Option<SignalHandler> {
+    fn into_register(self) -> usize {
+        self.map_or(0, |handler| handler as usize)
     }
+
+    fn from_register(register: usize) -> Result<Self, SyscallError> {
+        match register {
+            0 => Ok(None),
+            handler => Ok(Some(unsafe {
+                core::mem::transmute::<usize, SignalHandler>(handler)
+            })),
+        }
+    }
+}
+
+/// Return values of thread closures which can be used as exit code.
+pub trait ExitCode {
+    fn into_exit_code(self) -> usize;
 }
 
-#[inline(never)]
-pub extern "C" fn receive_character_from_dbgu() -> u8 {
-    let out_char: u32;
-    unsafe {
-        asm!("swi #{call_id}", call_id = const Syscalls::ReceiveDBGU as u32, lateout("r0") out_char, in("r0") 1);
+impl ExitCode for () {
+    fn into_exit_code(self) -> usize {
+        0
     }
-    out_char as u8
 }
 
-#[inline(never)]
-pub fn receive_character_from_dbgu_noblock() -> Option<u8> {
-    let out_char: u32;
-    unsafe {
-        asm!("swi #{call_id}", call_id = const Syscalls::ReceiveDBGU as u32, lateout("r0") out_char, in("r0") 0);
+impl ExitCode for usize {
+    fn into_exit_code(self) -> usize {
+        self
     }
-    if out_char == 0xFFFF {
-        return None;
+}
+
+/// Scheduling priority of a thread, higher values are scheduled first.
+pub type Priority = u8;
+
+/// Lowest priority a user thread can have, only the idle thread is below it.
+pub const PRIORITY_MIN: Priority = 1;
+/// Priority of newly created threads.
+pub const PRIORITY_DEFAULT: Priority = 10;
+/// Highest possible priority.
+pub const PRIORITY_MAX: Priority = Priority::MAX;
+
+/// Entry of a thread as passed to the kernel, called with the argument given at creation.  
+/// The return value is the exit code of the thread.
+pub type ThreadEntry = extern "C" fn(arg: *mut c_void) -> usize;
+
+/// Timing of a periodic thread passed to the kernel by `create_periodic_thread`.
+#[repr(C)]
+pub struct PeriodicParameters {
+    pub period_ms: usize,
+    /// deadline relative to the start of each period, at least one real-time  
+    /// unit and at most `period_ms`
+    pub deadline_ms: usize,
+}
+
+/// Buffer and timeout passed to the kernel by `read_pipe`, `write_pipe` and `read_dbgu`.
+#[repr(C)]
+pub struct TransferParameters {
+    /// bytes to write, or space for the bytes read
+    pub buffer: *mut u8,
+    pub length: usize,
+    /// timeout in milliseconds, `NO_TIMEOUT` waits without limit
+    pub timeout_ms: usize,
+}
+
+/// Kinds of event sources `wait_any` can wait for.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, TryFromPrimitive)]
+#[repr(u32)]
+pub enum EventKind {
+    /// a character is available with the subscribed `ThreadServices::DBGU`
+    DBGU = 0,
+    /// the child thread `id` exited and can be joined, fails like `join_thread`,  
+    /// e.g. with `SyscallError::UnknownThread` if there is no such thread
+    ThreadExit = 1,
+    /// a message is in the mailbox of the current thread
+    Message = 2,
+    /// the pipe `id` can be read without waiting
+    PipeReadable = 3,
+    /// a tick is available with the subscribed `ThreadServices::Timer`
+    Timer = 4,
+}
+
+/// Event source passed to the kernel by `wait_any`.
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Event {
+    pub kind: EventKind,
+    /// thread or pipe id, unused for the other kinds
+    pub id: usize,
+}
+
+impl Event {
+    pub fn dbgu() -> Self {
+        Event {
+            kind: EventKind::DBGU,
+            id: 0,
+        }
     }
-    Some(out_char as u8)
+
+    pub fn thread_exit(thread_id: usize) -> Self {
+        Event {
+            kind: EventKind::ThreadExit,
+            id: thread_id,
+        }
+    }
+
+    pub fn message() -> Self {
+        Event {
+            kind: EventKind::Message,
+            id: 0,
+        }
+    }
+
+    pub fn pipe_readable(pipe_id: usize) -> Self {
+        Event {
+            kind: EventKind::PipeReadable,
+            id: pipe_id,
+        }
+    }
+
+    pub fn timer() -> Self {
+        Event {
+            kind: EventKind::Timer,
+            id: 0,
+        }
+    }
+}
+
+/// Signals delivered to user threads, numbered like their POSIX counterparts.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, TryFromPrimitive, IntoPrimitive, Ord, PartialOrd)]
+#[repr(u32)]
+pub enum Signal {
+    Interrupt = 2,
+    /// raised by the kernel for an undefined instruction in user code
+    IllegalInstruction = 4,
+    User1 = 10,
+    /// raised by the kernel for a data or prefetch abort in user code
+    SegmentationFault = 11,
+    User2 = 12,
+    Terminate = 15,
+}
+
+/// Handler of a signal, runs on the stack of the interrupted thread.
+pub type SignalHandler = extern "C" fn(Signal);
+
+/// How long `read_dbgu` waits for characters.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, TryFromPrimitive, IntoPrimitive)]
+#[repr(u32)]
+pub enum ReadMode {
+    /// returns the received characters, fails with `SyscallError::WouldBlock` if there are none
+    NonBlocking = 0,
+    /// waits for the first character, then returns it with all other received characters
+    Available = 1,
+    /// waits until the buffer is full
+    Full = 2,
+}
+
+/// Kernel state printed by `print_kernel_info`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, TryFromPrimitive, IntoPrimitive)]
+#[repr(u32)]
+pub enum KernelInfo {
+    /// all threads with their state, priority and stack usage
+    Threads = 0,
+    /// used and free kernel heap, usage of the user heap per thread
+    HeapSize = 1,
+    /// statistics of the kernel heap and its slab caches
+    HeapStats = 2,
+    /// syscalls recorded for threads traced with `trace_thread`, cleared afterwards
+    SyscallTrace = 3,
+    /// all processes with their main thread and heap usage
+    Processes = 4,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, TryFromPrimitive, IntoPrimitive, Ord, PartialOrd)]
+#[repr(u32)]
+pub enum ThreadServices {
+    DBGU = 10,
+    /// ticks of the timers created with `create_timer`
+    Timer = 20,
+}
+
+/// System call to receive the messages of a service, fails if it is already subscribed.
+pub fn subscribe(service: ThreadServices) -> Result<(), SyscallError> {
+    raw::subscribe(service).map(|_| ())
+}
+
+/// System call to stop receiving the messages of a subscribed service.
+pub fn unsubscribe(service: ThreadServices) -> Result<(), SyscallError> {
+    raw::unsubscribe(service).map(|_| ())
+}
+
+/// System call to get the real time since boot in units of the system timer.
+pub fn get_current_realtime() -> Result<usize, SyscallError> {
+    raw::get_current_real_time()
+}
+
+/// System call to get the real time since boot.
+pub fn get_uptime() -> Result<core::time::Duration, SyscallError> {
+    raw::get_uptime().map(|uptime_ms| core::time::Duration::from_millis(uptime_ms as u64))
+}
+
+/// System call to wait for `time_ms` milliseconds, returns the milliseconds actually slept.
+pub fn sleep_ms(time_ms: usize) -> Result<usize, SyscallError> {
+    raw::sleep(time_ms)
+}
+
+/// System call to wait for a child thread to exit.
+/// Returns the exit code of the thread, a timeout can be given in milliseconds.
+/// Fails with `SyscallError::NotAChild` for threads which are not children of the current one  
+/// and with `SyscallError::Detached` for children adopted after their parent stopped.
+pub fn join_thread(thread_id: usize, timeout: Option<usize>) -> Result<usize, SyscallError> {
+    raw::join_thread(thread_id, timeout.unwrap_or(NO_TIMEOUT))
+}
+
+/// System call to allocate memory on the user heap.
+/// Fails with `SyscallError::QuotaExceeded` if the current thread would exceed its heap quota.
+pub fn allocate(size: usize, align: usize) -> Result<*mut u8, SyscallError> {
+    raw::allocate(size, align).map(|ptr| ptr as *mut u8)
+}
+
+pub fn deallocate(ptr: *mut u8, size: usize, align: usize) -> Result<(), SyscallError> {
+    raw::deallocate(ptr, size, align).map(|_| ())
+}
+
+/// System call to set the heap quota of a descendant of the current thread in bytes.
+/// Memory already allocated stays allocated if it exceeds the new quota.
+pub fn set_heap_quota(thread_id: usize, quota: usize) -> Result<(), SyscallError> {
+    raw::set_heap_quota(thread_id, quota).map(|_| ())
+}
+
+/// System call to write a string to the DBGU, see `write_dbgu`.
+pub fn send_str_to_dbgu(chars: &str) -> Result<(), SyscallError> {
+    write_dbgu(chars.as_bytes())
+}
+
+/// System call to write `data` to the DBGU.  
+/// Every `MAX_DBGU_WRITE_SIZE` bytes are printed at once, without output of other  
+/// threads in between.
+pub fn write_dbgu(data: &[u8]) -> Result<(), SyscallError> {
+    let mut written = 0;
+    while written < data.len() {
+        written += raw::write_dbgu(data[written..].as_ptr(), data.len() - written)?;
+    }
+    Ok(())
+}
+
+/// System call to print kernel state to the DBGU.
+pub fn print_kernel_info(info: KernelInfo) -> Result<(), SyscallError> {
+    raw::print_kernel_info(info).map(|_| ())
+}
+
+/// System call to read characters of the subscribed `ThreadServices::DBGU` into `buffer`.  
+/// Returns the amount of characters read, `mode` selects how long to wait for them.  
+/// A timeout can be given in milliseconds, None waits as long as `mode` says. Once it  
+/// elapsed the characters read so far are returned, without any it fails with `SyscallError::TimedOut`.
+pub fn read_dbgu(
+    buffer: &mut [u8],
+    mode: ReadMode,
+    timeout: Option<usize>,
+) -> Result<usize, SyscallError> {
+    let transfer = TransferParameters {
+        buffer: buffer.as_mut_ptr(),
+        length: buffer.len(),
+        timeout_ms: timeout.unwrap_or(NO_TIMEOUT),
+    };
+    raw::read_dbgu(&transfer, mode)
+}
+
+/// System call to write a single character to the DBGU.
+pub fn send_character_to_dbgu(character: u8) -> Result<(), SyscallError> {
+    raw::send_dbgu(character).map(|_| ())
+}
+
+/// Waits for the next character of the subscribed `ThreadServices::DBGU`.
+pub fn receive_character_from_dbgu() -> Result<u8, SyscallError> {
+    raw::receive_dbgu(true, NO_TIMEOUT).map(|character| character as u8)
+}
+
+/// Waits for a character like `receive_character_from_dbgu`.
+/// Fails with `SyscallError::TimedOut` if no character arrived within the optional timeout in milliseconds.
+pub fn receive_character_from_dbgu_timeout(timeout: Option<usize>) -> Result<u8, SyscallError> {
+    raw::receive_dbgu(true, timeout.unwrap_or(NO_TIMEOUT)).map(|character| character as u8)
+}
+
+/// Fails with `SyscallError::WouldBlock` if no character is available.
+pub fn receive_character_from_dbgu_noblock() -> Result<u8, SyscallError> {
+    raw::receive_dbgu(false, 0).map(|character| character as u8)
+}
+
+/// System call to start a timer of the current thread, which has to be subscribed to  
+/// `ThreadServices::Timer`. The timer expires after `interval_ms` milliseconds and, if  
+/// periodic, again every `interval_ms` milliseconds until it gets cancelled. The interval  
+/// is rounded up to whole real time units, an interval of zero fails.  
+/// Returns the timer id, which is received as tick on every expiry.
+pub fn create_timer(interval_ms: usize, periodic: bool) -> Result<usize, SyscallError> {
+    raw::create_timer(interval_ms, periodic)
+}
+
+/// System call to stop a timer of the current thread, a tick not received yet is discarded.
+/// Fails with `SyscallError::UnknownTimer` if the timer does not exist anymore or belongs to another thread.
+pub fn cancel_timer(timer_id: usize) -> Result<(), SyscallError> {
+    raw::cancel_timer(timer_id).map(|_| ())
+}
+
+/// Waits for the next tick of a timer of the current thread and returns the timer id.  
+/// Only one tick per timer is queued, ticks of a timer whose last tick was not received  
+/// yet are dropped.
+pub fn receive_timer_tick() -> Result<usize, SyscallError> {
+    raw::receive_timer(true, NO_TIMEOUT)
+}
+
+/// Waits for a tick like `receive_timer_tick`.
+/// Fails with `SyscallError::TimedOut` if no tick arrived within the optional timeout in milliseconds.
+pub fn receive_timer_tick_timeout(timeout: Option<usize>) -> Result<usize, SyscallError> {
+    raw::receive_timer(true, timeout.unwrap_or(NO_TIMEOUT))
+}
+
+/// Returns the id of the timer of the next tick.
+/// Fails with `SyscallError::WouldBlock` if no tick is queued.
+pub fn receive_timer_tick_noblock() -> Result<usize, SyscallError> {
+    raw::receive_timer(false, 0)
+}
+
+type BoxedEntry = Box<dyn FnOnce() -> usize + 'static>;
+
+/// Boxes a thread closure into the argument of `closure_entry`.
+///
+/// The closure is boxed twice, so a thin pointer fits the argument register.
+fn into_raw_entry<F, R>(entry: F) -> *mut c_void
+where
+    F: FnOnce() -> R + 'static,
+    R: ExitCode,
+{
+    let entry: BoxedEntry = Box::new(move || entry().into_exit_code());
+    Box::into_raw(Box::new(entry)) as *mut c_void
+}
+
+/// Takes back a closure boxed by `into_raw_entry`.
+unsafe fn from_raw_entry(arg: *mut c_void) -> Box<BoxedEntry> {
+    Box::from_raw(arg as *mut BoxedEntry)
+}
+
+/// Thread entry of threads created from a Rust closure.
+extern "C" fn closure_entry(arg: *mut c_void) -> usize {
+    let entry = unsafe { from_raw_entry(arg) };
+    entry()
+}
+
+/// Passes on the result of a thread creation, frees the closure if no thread took it.
+fn into_spawn_result(
+    result: Result<usize, SyscallError>,
+    arg: *mut c_void,
+) -> Result<usize, SyscallError> {
+    if result.is_err() {
+        drop(unsafe { from_raw_entry(arg) });
+    }
+    result
 }
 
 /// System call to create a thread via software interrupt.
-#[inline(never)]
-pub fn create_thread<F: FnMut() + 'static>(entry: F) -> usize {
-    let id: usize;
-    unsafe {
-        let entry_raw: (u32, u32) =
-            core::mem::transmute(Box::into_raw(Box::new(entry) as Box<dyn FnMut() + 'static>));
+///
+/// The value returned by the closure is the exit code of the thread  
+/// which the parent receives through `join_thread`.  
+/// Use `ThreadBuilder` to configure the thread.
+pub fn create_thread<F, R>(entry: F) -> Result<usize, SyscallError>
+where
+    F: FnOnce() -> R + 'static,
+    R: ExitCode,
+{
+    ThreadBuilder::new().spawn(entry)
+}
 
-        asm!("swi #{call_id}", call_id = const Syscalls::CreateThread as u32, in("r0") entry_raw.0, in("r1") entry_raw.1, lateout("r0") id);
+/// Configuration of a thread created by `spawn`.
+///
+/// ```ignore
+/// let id = ThreadBuilder::new().stack_size(16 * 1024).spawn(|| worker());
+/// ```
+pub struct ThreadBuilder {
+    stack_size: usize,
+}
+
+impl Default for ThreadBuilder {
+    fn default() -> Self {
+        Self::new()
     }
-    id
 }
 
-/// System call to stop and exit the current thread via software interrupt.
-#[inline(never)]
-pub extern "C" fn exit_thread() {
-    unsafe {
-        asm!("swi #{call_id}", call_id = const Syscalls::ExitThread as u32);
+impl ThreadBuilder {
+    /// Creates a builder for a thread with the default stack size of the kernel.
+    pub fn new() -> Self {
+        ThreadBuilder { stack_size: 0 }
     }
+
+    /// Sets the stack size in bytes, spawning fails with `SyscallError::InvalidArgument`  
+    /// unless it is in the range of 1 KiB to 1 MiB.
+    pub fn stack_size(mut self, stack_size: usize) -> Self {
+        self.stack_size = stack_size;
+        self
+    }
+
+    /// System call to create the configured thread via software interrupt.  
+    /// Returns the id of the new thread.
+    pub fn spawn<F, R>(self, entry: F) -> Result<usize, SyscallError>
+    where
+        F: FnOnce() -> R + 'static,
+        R: ExitCode,
+    {
+        let arg = into_raw_entry(entry);
+        into_spawn_result(raw::create_thread(closure_entry, arg, self.stack_size), arg)
+    }
+}
+
+/// System call to create a periodic real-time thread via software interrupt.
+///
+/// Periodic threads run before all other threads and are scheduled  
+/// earliest deadline first. The entry is called once and should call  
+/// `wait_next_period` at the end of each period's work.
+pub fn create_periodic_thread<F, R>(
+    period_ms: usize,
+    deadline_ms: usize,
+    entry: F,
+) -> Result<usize, SyscallError>
+where
+    F: FnOnce() -> R + 'static,
+    R: ExitCode,
+{
+    let parameters = PeriodicParameters {
+        period_ms,
+        deadline_ms,
+    };
+    let arg = into_raw_entry(entry);
+    into_spawn_result(
+        raw::create_periodic_thread(closure_entry, arg, &parameters),
+        arg,
+    )
+}
+
+/// System call to wait for the start of the next period of a periodic thread.
+/// Returns the amount of missed deadlines of the thread so far.
+pub fn wait_next_period() -> Result<usize, SyscallError> {
+    raw::wait_next_period()
+}
+
+/// System call to stop and exit the current thread via software interrupt.
+/// The exit code is passed to the parent joining the thread, it ranges from 0 to  
+/// `MAX_EXIT_CODE` so the parent can tell it from `EXIT_CODE_KILLED` and `EXIT_CODE_FAULT`.  
+/// Only returns if the kernel refused to stop the thread.
+pub fn exit_thread(exit_code: usize) -> Result<Infallible, SyscallError> {
+    raw::exit_thread(exit_code)?;
+    unreachable!("exit_thread returned without an error")
+}
+
+/// System call to stop another thread, only descendants of the current thread can be killed.
+/// Fails if the thread does not exist or may not be killed.
+pub fn kill_thread(thread_id: usize) -> Result<(), SyscallError> {
+    raw::kill_thread(thread_id).map(|_| ())
+}
+
+/// System call to stop scheduling a descendant of the current thread until it gets resumed.
+/// Fails if the thread does not exist or may not be suspended.
+pub fn suspend_thread(thread_id: usize) -> Result<(), SyscallError> {
+    raw::suspend_thread(thread_id).map(|_| ())
+}
+
+/// System call to continue a thread stopped by `suspend_thread`.
+/// Fails if the thread does not exist or may not be resumed.
+pub fn resume_thread(thread_id: usize) -> Result<(), SyscallError> {
+    raw::resume_thread(thread_id).map(|_| ())
+}
+
+/// System call to start or stop recording the syscalls of a descendant of the current thread.  
+/// The kernel buffers the name, arguments, result and duration of every traced syscall per thread.
+pub fn trace_thread(thread_id: usize, enabled: bool) -> Result<(), SyscallError> {
+    raw::trace_thread(thread_id, enabled).map(|_| ())
+}
+
+/// System call to start a process with its own address space, which runs a copy of `image`  
+/// linked to the start of the user code. Returns the id of the main thread, which is a child  
+/// of the current thread. The process ends with its main thread, which can be joined and  
+/// killed like any other child. Several processes may run the same image at once.
+pub fn spawn_process(image: &[u8]) -> Result<usize, SyscallError> {
+    raw::spawn_process(image.as_ptr(), image.len())
+}
+
+/// System call to set the priority of the current thread or one of its children.
+/// Fails if the thread does not exist or is not allowed to be changed, or if the  
+/// priority is below `PRIORITY_MIN`.
+pub fn set_priority(thread_id: usize, priority: Priority) -> Result<(), SyscallError> {
+    raw::set_priority(thread_id, priority).map(|_| ())
+}
+
+/// System call to get the priority of a thread as set by `set_priority`, without  
+/// priority inherited through mutexes.
+pub fn get_priority(thread_id: usize) -> Result<Priority, SyscallError> {
+    raw::get_priority(thread_id).map(|priority| priority as Priority)
 }
 
 /// System call to yield the current thread via software interrupt.
-#[inline(never)]
-pub extern "C" fn yield_thread() {
-    unsafe {
-        asm!("swi #{call_id}", call_id = const Syscalls::YieldThread as u32);
-    }
+pub fn yield_thread() -> Result<(), SyscallError> {
+    raw::yield_thread().map(|_| ())
+}
+
+/// System call to create a kernel mutex, returns its id.
+pub fn create_mutex() -> Result<usize, SyscallError> {
+    raw::create_mutex()
+}
+
+/// System call to lock a mutex, waits while another thread owns it.
+/// A timeout of None waits until the mutex is available.
+pub fn lock_mutex(mutex_id: usize, timeout: Option<usize>) -> Result<(), SyscallError> {
+    raw::lock_mutex(mutex_id, timeout.unwrap_or(NO_TIMEOUT)).map(|_| ())
+}
+
+/// System call to unlock a mutex owned by the current thread.
+pub fn unlock_mutex(mutex_id: usize) -> Result<(), SyscallError> {
+    raw::unlock_mutex(mutex_id).map(|_| ())
+}
+
+/// System call to create a kernel counting semaphore with `count` permits, returns its id.
+pub fn create_semaphore(count: usize) -> Result<usize, SyscallError> {
+    raw::create_semaphore(count)
+}
+
+/// System call to take a permit of a semaphore, waits while none is available.
+/// A timeout of None waits until a permit is available.
+pub fn wait_semaphore(semaphore_id: usize, timeout: Option<usize>) -> Result<(), SyscallError> {
+    raw::wait_semaphore(semaphore_id, timeout.unwrap_or(NO_TIMEOUT)).map(|_| ())
+}
+
+/// System call to return a permit to a semaphore.
+pub fn signal_semaphore(semaphore_id: usize) -> Result<(), SyscallError> {
+    raw::signal_semaphore(semaphore_id).map(|_| ())
+}
+
+/// System call to create a kernel condition variable, returns its id.
+pub fn create_condvar() -> Result<usize, SyscallError> {
+    raw::create_condvar()
+}
+
+/// System call to unlock a mutex owned by the current thread and wait for a signal  
+/// of the condition variable. The mutex is locked again when the call returns,  
+/// also after the timeout elapsed.
+pub fn wait_condvar(
+    condvar_id: usize,
+    mutex_id: usize,
+    timeout: Option<usize>,
+) -> Result<(), SyscallError> {
+    raw::wait_condvar(condvar_id, mutex_id, timeout.unwrap_or(NO_TIMEOUT)).map(|_| ())
+}
+
+/// System call to wake one thread waiting for the condition variable.
+pub fn signal_condvar(condvar_id: usize) -> Result<(), SyscallError> {
+    raw::signal_condvar(condvar_id).map(|_| ())
+}
+
+/// System call to wake all threads waiting for the condition variable.
+pub fn broadcast_condvar(condvar_id: usize) -> Result<(), SyscallError> {
+    raw::broadcast_condvar(condvar_id).map(|_| ())
+}
+
+/// System call to remove a mutex, semaphore or condition variable created in the same process.
+/// Fails for locked mutexes and objects other threads wait for.
+pub fn destroy_sync_object(id: usize) -> Result<(), SyscallError> {
+    raw::destroy_sync_object(id).map(|_| ())
+}
+
+/// System call to put a copy of `message` into the mailbox of a thread.
+/// Does not block, a full mailbox is reported as `SyscallError::MailboxFull`.
+pub fn send_message(thread_id: usize, message: &[u8]) -> Result<(), SyscallError> {
+    raw::send_message(thread_id, message.as_ptr(), message.len()).map(|_| ())
+}
+
+/// System call to take the oldest message out of the mailbox of the current thread.
+/// Waits for a message if the mailbox is empty, a timeout of None waits without limit.
+/// Returns the length of the message copied into `buffer`.
+pub fn receive_message(buffer: &mut [u8], timeout: Option<usize>) -> Result<usize, SyscallError> {
+    raw::receive_message(
+        buffer.as_mut_ptr(),
+        buffer.len(),
+        timeout.unwrap_or(NO_TIMEOUT),
+    )
+}
+
+/// System call to create a named pipe, returns the id of the pipe opened for the current thread.
+pub fn create_pipe(name: &str) -> Result<usize, SyscallError> {
+    raw::create_pipe(name.as_ptr(), name.len())
+}
+
+/// System call to open a pipe created by any thread, returns the id of the pipe.
+pub fn open_pipe(name: &str) -> Result<usize, SyscallError> {
+    raw::open_pipe(name.as_ptr(), name.len())
+}
+
+/// System call to read from a pipe, waits while the pipe is empty.
+/// Returns the amount of bytes read, which can be less than the buffer size.  
+/// A timeout can be given in milliseconds, None waits until data is available.
+pub fn read_pipe(
+    pipe_id: usize,
+    buffer: &mut [u8],
+    timeout: Option<usize>,
+) -> Result<usize, SyscallError> {
+    let transfer = TransferParameters {
+        buffer: buffer.as_mut_ptr(),
+        length: buffer.len(),
+        timeout_ms: timeout.unwrap_or(NO_TIMEOUT),
+    };
+    raw::read_pipe(pipe_id, &transfer)
+}
+
+/// System call to write `data` into a pipe, waits while the pipe is full.
+/// Returns the amount of bytes written, which is less than the length of `data`  
+/// only if the timeout elapsed or the pipe got removed after a part was written.  
+/// A timeout can be given in milliseconds, None waits until everything is written.
+pub fn write_pipe(
+    pipe_id: usize,
+    data: &[u8],
+    timeout: Option<usize>,
+) -> Result<usize, SyscallError> {
+    // the kernel only reads the buffer of a write
+    let transfer = TransferParameters {
+        buffer: data.as_ptr() as *mut u8,
+        length: data.len(),
+        timeout_ms: timeout.unwrap_or(NO_TIMEOUT),
+    };
+    raw::write_pipe(pipe_id, &transfer)
+}
+
+/// System call to close a pipe opened by `create_pipe` or `open_pipe`.
+/// The pipe gets removed once every thread which opened it closed it.
+pub fn close_pipe(pipe_id: usize) -> Result<(), SyscallError> {
+    raw::close_pipe(pipe_id).map(|_| ())
+}
+
+/// System call to wait until the first of up to `MAX_WAIT_EVENTS` events occurs.  
+/// Returns the index of the event in `events` without consuming it, e.g. the  
+/// character still has to be received. A timeout of None waits without limit.
+pub fn wait_any(events: &[Event], timeout: Option<usize>) -> Result<usize, SyscallError> {
+    raw::wait_any(events.as_ptr(), events.len(), timeout.unwrap_or(NO_TIMEOUT))
+}
+
+/// System call to register the handler of a signal for the current thread,  
+/// None removes the handler and discards the signal if it is pending.
+///
+/// Signals sent to a thread without a handler are discarded. After a fault  
+/// in its code the thread exits with `EXIT_CODE_FAULT` once the handler returns.
+pub fn register_signal_handler(
+    signal: Signal,
+    handler: Option<SignalHandler>,
+) -> Result<(), SyscallError> {
+    raw::register_signal_handler(signal, handler).map(|_| ())
+}
+
+/// System call to send a signal to another thread, its handler runs the next time the  
+/// thread gets scheduled. Returns false if the thread has no handler for the signal,  
+/// fails if the thread does not exist or is not a descendant of the current thread.
+pub fn send_signal(thread_id: usize, signal: Signal) -> Result<bool, SyscallError> {
+    raw::send_signal(thread_id, signal).map(|delivered| delivered != 0)
+}
+
+/// System call to continue the code interrupted by a signal after its handler returned.  
+/// Called by the kernel's signal entry, only returns if no signal handler is running.
+#[doc(hidden)]
+pub fn signal_return() -> Result<(), SyscallError> {
+    raw::signal_return().map(|_| ())
 }
@@ -0,0 +1,473 @@
+//! Generates `usercode_c/include/rost.h` from the syscall definitions of `rost_api`.
+//!
+//! Build and run it on the host with plain `rustc`, the cargo config of the kernel  
+//! forces the ARM target:  
+//! `rustc --edition 2018 -o target/gen_c_header api/tools/gen_c_header.rs`  
+//! `target/gen_c_header api/src usercode_c/include/rost.h`
+//!
+//! The syscall ids, functions, enums, constants and `#[repr(C)]` structs are taken from  
+//! `syscalls.rs`, so the header never drifts from the `SyscallHandler` trait.
+
+use std::env;
+use std::fs;
+use std::process;
+
+/// Enums of `syscalls.rs` which end up in the header with the prefix of their constants.
+const ENUMS: [(&str, &str, &str); 6] = [
+    ("SyscallError", "rost_error", "ROST_ERROR_"),
+    ("ThreadServices", "rost_thread_service", "ROST_SERVICE_"),
+    ("ReadMode", "rost_read_mode", "ROST_READ_"),
+    ("KernelInfo", "rost_kernel_info", "ROST_KERNEL_INFO_"),
+    ("EventKind", "rost_event_kind", "ROST_EVENT_"),
+    ("Signal", "rost_signal", "ROST_SIGNAL_"),
+];
+
+struct Variant {
+    docs: Vec<String>,
+    name: String,
+    value: String,
+}
+
+struct Enum {
+    docs: Vec<String>,
+    name: String,
+    variants: Vec<Variant>,
+}
+
+struct Const {
+    docs: Vec<String>,
+    name: String,
+    ty: String,
+    value: String,
+}
+
+struct Field {
+    docs: Vec<String>,
+    name: String,
+    ty: String,
+}
+
+struct Struct {
+    docs: Vec<String>,
+    name: String,
+    fields: Vec<Field>,
+}
+
+struct Function {
+    docs: Vec<String>,
+    name: String,
+    params: Vec<(String, String)>,
+    syscall: String,
+}
+
+#[derive(Default)]
+struct Definitions {
+    enums: Vec<Enum>,
+    consts: Vec<Const>,
+    structs: Vec<Struct>,
+    functions: Vec<Function>,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 {
+        eprintln!("usage: {} <api/src> <rost.h>", args[0]);
+        process::exit(1);
+    }
+    let syscalls = read(&format!("{}/syscalls.rs", args[1]));
+
+    let mut definitions = Definitions::default();
+    parse_syscalls(&syscalls, &mut definitions);
+
+    if let Err(error) = fs::write(&args[2], render(&definitions)) {
+        eprintln!("cannot write {}: {}", args[2], error);
+        process::exit(1);
+    }
+}
+
+fn read(path: &str) -> String {
+    fs::read_to_string(path).unwrap_or_else(|error| {
+        eprintln!("cannot read {}: {}", path, error);
+        process::exit(1);
+    })
+}
+
+/// Collects the syscalls, enums, constants and `#[repr(C)]` structs of `syscalls.rs`.
+fn parse_syscalls(source: &str, definitions: &mut Definitions) {
+    let mut lines = source.lines().map(str::trim);
+    let mut docs = Vec::new();
+    let mut repr_c = false;
+    let mut syscall = None;
+    while let Some(line) = lines.next() {
+        if let Some(doc) = line.strip_prefix("///") {
+            docs.push(doc.trim().to_string());
+            continue;
+        }
+        if line == "#[repr(C)]" {
+            repr_c = true;
+        }
+        if let Some(rest) = line.strip_prefix("#[syscall(") {
+            syscall = Some(push_syscall(rest.trim_end_matches(")]"), definitions));
+        }
+        if line.starts_with("#[") {
+            continue;
+        }
+        if let (Some(rest), Some(syscall)) = (line.strip_prefix("fn "), syscall.take()) {
+            let mut signature = rest.to_string();
+            while !signature.ends_with(';') {
+                signature.push_str(lines.next().expect("unterminated signature"));
+            }
+            definitions
+                .functions
+                .push(parse_function(&signature, syscall, docs.split_off(0)));
+        } else if let Some(rest) = line.strip_prefix("pub enum ") {
+            let name = rest.trim_end_matches('{').trim().to_string();
+            let variants = parse_variants(&mut lines);
+            definitions.enums.push(Enum {
+                docs: docs.split_off(0),
+                name,
+                variants,
+            });
+        } else if let Some(rest) = line.strip_prefix("pub struct ") {
+            let name = rest.trim_end_matches('{').trim().to_string();
+            let fields = parse_fields(&mut lines);
+            if repr_c {
+                definitions.structs.push(Struct {
+                    docs: docs.split_off(0),
+                    name,
+                    fields,
+                });
+            }
+        } else if let Some(rest) = line.strip_prefix("pub const ") {
+            let (name, rest) = split_once(rest, ':');
+            let (ty, value) = split_once(&rest, '=');
+            definitions.consts.push(Const {
+                docs: docs.split_off(0),
+                name,
+                ty,
+                value: value.trim_end_matches(';').trim().to_string(),
+            });
+        }
+        docs.clear();
+        repr_c = false;
+    }
+}
+
+/// Adds `Variant = id` of a `#[syscall]` attribute to the `Syscalls` ids, returns the variant.
+fn push_syscall(attribute: &str, definitions: &mut Definitions) -> String {
+    let (name, value) = split_once(attribute, '=');
+    let variant = Variant {
+        docs: Vec::new(),
+        name: name.clone(),
+        value,
+    };
+    match definitions.enums.iter_mut().find(|e| e.name == "Syscalls") {
+        Some(syscalls) => syscalls.variants.push(variant),
+        None => definitions.enums.push(Enum {
+            docs: Vec::new(),
+            name: "Syscalls".to_string(),
+            variants: vec![variant],
+        }),
+    }
+    name
+}
+
+/// Reads `Name = value,` lines up to the closing brace of an enum.
+fn parse_variants<'a>(lines: &mut impl Iterator<Item = &'a str>) -> Vec<Variant> {
+    let mut variants = Vec::new();
+    let mut docs = Vec::new();
+    for line in lines {
+        if line.starts_with('}') {
+            break;
+        }
+        if let Some(doc) = line.strip_prefix("///") {
+            docs.push(doc.trim().to_string());
+        } else if line.contains('=') {
+            let (name, value) = split_once(line, '=');
+            variants.push(Variant {
+                docs: docs.split_off(0),
+                name,
+                value: value.trim_end_matches(',').trim().to_string(),
+            });
+        }
+    }
+    variants
+}
+
+/// Reads `pub name: Type,` lines up to the closing brace of a struct.
+fn parse_fields<'a>(lines: &mut impl Iterator<Item = &'a str>) -> Vec<Field> {
+    let mut fields = Vec::new();
+    let mut docs = Vec::new();
+    for line in lines {
+        if line.starts_with('}') {
+            break;
+        }
+        if let Some(doc) = line.strip_prefix("///") {
+            docs.push(doc.trim().to_string());
+        } else if let Some(rest) = line.strip_prefix("pub ") {
+            let (name, ty) = split_once(rest, ':');
+            fields.push(Field {
+                docs: docs.split_off(0),
+                name,
+                ty: ty.trim_end_matches(',').trim().to_string(),
+            });
+        }
+    }
+    fields
+}
+
+/// Splits the signature of a `SyscallHandler` method into name and parameters,  
+/// the C function is the method name with the `rost_` prefix.
+fn parse_function(signature: &str, syscall: String, docs: Vec<String>) -> Function {
+    let (name, rest) = split_once(signature, '(');
+    let params = rest[..rest.rfind(')').expect("unterminated parameter list")]
+        .split(',')
+        .map(str::trim)
+        .filter(|param| !param.is_empty())
+        .map(|param| split_once(param, ':'))
+        .collect();
+    Function {
+        docs,
+        name: format!("rost_{}", name),
+        params,
+        syscall,
+    }
+}
+
+fn split_once(text: &str, separator: char) -> (String, String) {
+    let index = text.find(separator).unwrap_or_else(|| {
+        eprintln!("expected '{}' in `{}`", separator, text);
+        process::exit(1);
+    });
+    (
+        text[..index].trim().to_string(),
+        text[index + 1..].trim().to_string(),
+    )
+}
+
+/// Converts `SendDBGU` to `SEND_DBGU`.
+fn screaming_snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut result = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        let after_lower = i > 0 && chars[i - 1].is_lowercase();
+        let before_lower = i > 0
+            && chars[i - 1].is_uppercase()
+            && chars.get(i + 1).map_or(false, |next| next.is_lowercase());
+        if c.is_uppercase() && (after_lower || before_lower) {
+            result.push('_');
+        }
+        result.extend(c.to_uppercase());
+    }
+    result
+}
+
+/// Maps a Rust type of the syscall ABI to its C counterpart.
+fn c_type(ty: &str, definitions: &Definitions) -> String {
+    if let Some(pointee) = ty.strip_prefix("*const ") {
+        return format!("const {} *", c_type(pointee, definitions));
+    }
+    if let Some(pointee) = ty.strip_prefix("*mut ") {
+        return format!("{} *", c_type(pointee, definitions));
+    }
+    match ty {
+        "usize" | "bool" => "uintptr_t".to_string(),
+        "u8" | "Priority" => "uint8_t".to_string(),
+        "u32" => "uint32_t".to_string(),
+        "c_void" => "void".to_string(),
+        "ThreadEntry" => "rost_thread_entry".to_string(),
+        "Option<SignalHandler>" => "rost_signal_handler".to_string(),
+        _ if definitions.structs.iter().any(|s| s.name == ty) => {
+            format!("struct rost_{}", screaming_snake_case(ty).to_lowercase())
+        }
+        _ if definitions.enums.iter().any(|e| e.name == ty) => "uint32_t".to_string(),
+        _ => {
+            eprintln!("no C type for `{}`", ty);
+            process::exit(1);
+        }
+    }
+}
+
+/// Translates the expression of a constant, other constants get the `ROST_` prefix.
+fn c_value(value: &str, definitions: &Definitions) -> String {
+    let translated: Vec<String> = value
+        .split_whitespace()
+        .map(|token| match token {
+            "usize::MAX" => "UINTPTR_MAX".to_string(),
+            "Priority::MAX" => "UINT8_MAX".to_string(),
+            _ if definitions.consts.iter().any(|c| c.name == token) => format!("ROST_{}", token),
+            _ => token.to_string(),
+        })
+        .collect();
+    if translated.len() == 1 {
+        translated[0].clone()
+    } else {
+        format!("({})", translated.join(" "))
+    }
+}
+
+fn render_docs(out: &mut String, docs: &[String], indent: &str) {
+    match docs.len() {
+        0 => {}
+        1 => out.push_str(&format!("{}/* {} */\n", indent, docs[0])),
+        _ => {
+            out.push_str(&format!("{}/*\n", indent));
+            for doc in docs {
+                out.push_str(format!("{} * {}", indent, doc).trim_end());
+                out.push('\n');
+            }
+            out.push_str(&format!("{} */\n", indent));
+        }
+    }
+}
+
+fn render(definitions: &Definitions) -> String {
+    let mut out = String::new();
+    out.push_str(concat!(
+        "/* rost.h - syscalls of rOSt for C programs.\n",
+        " *\n",
+        " * Generated by api/tools/gen_c_header.rs from api/src/syscalls.rs, do not edit.\n",
+        " *\n",
+        " * Every syscall returns a uintptr_t, values of at least ROST_SYSCALL_ERROR_MIN\n",
+        " * are errors which rost_error_code decodes.\n",
+        " */\n\n",
+        "#ifndef ROST_H\n",
+        "#define ROST_H\n\n",
+        "#include <stdint.h>\n\n",
+    ));
+
+    for e in definitions.enums.iter().filter(|e| e.name == "Syscalls") {
+        for variant in &e.variants {
+            out.push_str(&format!(
+                "#define ROST_SYSCALL_{} {}\n",
+                screaming_snake_case(&variant.name),
+                variant.value
+            ));
+        }
+        out.push('\n');
+    }
+
+    for (rust_name, c_name, prefix) in ENUMS.iter() {
+        let e = match definitions.enums.iter().find(|e| e.name == *rust_name) {
+            Some(e) => e,
+            None => {
+                eprintln!("enum {} not found", rust_name);
+                process::exit(1);
+            }
+        };
+        render_docs(&mut out, &e.docs, "");
+        out.push_str(&format!("enum {} {{\n", c_name));
+        for variant in &e.variants {
+            render_docs(&mut out, &variant.docs, "    ");
+            out.push_str(&format!(
+                "    {}{} = {},\n",
+                prefix,
+                screaming_snake_case(&variant.name),
+                variant.value
+            ));
+        }
+        out.push_str("};\n\n");
+    }
+
+    for c in &definitions.consts {
+        render_docs(&mut out, &c.docs, "");
+        out.push_str(&format!(
+            "#define ROST_{} (({}){})\n",
+            c.name,
+            c_type(&c.ty, definitions),
+            c_value(&c.value, definitions)
+        ));
+    }
+    out.push('\n');
+
+    out.push_str(concat!(
+        "/* Returns non-zero if the return value of a syscall encodes an error. */\n",
+        "static inline int rost_is_error(uintptr_t value)\n",
+        "{\n",
+        "    return value >= ROST_SYSCALL_ERROR_MIN;\n",
+        "}\n\n",
+        "/* Returns the error encoded in the return value of a syscall. */\n",
+        "static inline enum rost_error rost_error_code(uintptr_t value)\n",
+        "{\n",
+        "    return (enum rost_error)(UINTPTR_MAX - value);\n",
+        "}\n\n",
+        "/* Entry of a thread, the return value is its exit code. */\n",
+        "typedef uintptr_t (*rost_thread_entry)(void *arg);\n",
+        "/* Handler of a signal, runs on the stack of the interrupted thread. */\n",
+        "typedef void (*rost_signal_handler)(uint32_t signal);\n\n",
+    ));
+
+    for s in &definitions.structs {
+        render_docs(&mut out, &s.docs, "");
+        out.push_str(&format!(
+            "struct rost_{} {{\n",
+            screaming_snake_case(&s.name).to_lowercase()
+        ));
+        for field in &s.fields {
+            render_docs(&mut out, &field.docs, "    ");
+            out.push_str(&format!(
+                "    {} {};\n",
+                c_type(&field.ty, definitions),
+                field.name
+            ));
+        }
+        out.push_str("};\n\n");
+    }
+
+    out.push_str(concat!(
+        "/*\n",
+        " * Issues a syscall with the arguments in r0-r2, evaluates to r0.\n",
+        " * The kernel returns with r1-r3 overwritten.\n",
+        " */\n",
+        "#define ROST_SYSCALL(id, arg0, arg1, arg2) ({ \\\n",
+        "    register uintptr_t rost_r0 __asm__(\"r0\") = (uintptr_t)(arg0); \\\n",
+        "    register uintptr_t rost_r1 __asm__(\"r1\") = (uintptr_t)(arg1); \\\n",
+        "    register uintptr_t rost_r2 __asm__(\"r2\") = (uintptr_t)(arg2); \\\n",
+        "    __asm__ volatile(\"swi %[call_id]\" \\\n",
+        "                     : \"+r\"(rost_r0), \"+r\"(rost_r1), \"+r\"(rost_r2) \\\n",
+        "                     : [call_id] \"i\"(id) \\\n",
+        "                     : \"r3\", \"memory\"); \\\n",
+        "    rost_r0; \\\n",
+        "})\n\n",
+    ));
+
+    for function in &definitions.functions {
+        render_docs(&mut out, &function.docs, "");
+        let params: Vec<String> = function
+            .params
+            .iter()
+            .map(|(name, ty)| {
+                let ty = c_type(ty, definitions);
+                if ty.ends_with('*') {
+                    format!("{}{}", ty, name)
+                } else {
+                    format!("{} {}", ty, name)
+                }
+            })
+            .collect();
+        let mut args: Vec<String> = function
+            .params
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect();
+        if args.len() > 3 {
+            eprintln!("{} has more than three arguments", function.name);
+            process::exit(1);
+        }
+        args.resize(3, "0".to_string());
+        out.push_str(&format!(
+            "static inline uintptr_t {}({})\n{{\n    return ROST_SYSCALL(ROST_SYSCALL_{}, {});\n}}\n\n",
+            function.name,
+            if params.is_empty() {
+                "void".to_string()
+            } else {
+                params.join(", ")
+            },
+            screaming_snake_case(&function.syscall),
+            args.join(", ")
+        ));
+    }
+
+    out.push_str("#endif /* ROST_H */\n");
+    out
+}
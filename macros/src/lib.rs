@@ -4,7 +4,8 @@ use proc_macro::TokenStream;
 use proc_macro2::Span;
 use quote::quote;
 use syn::{
-    parse, parse_macro_input, spanned::Spanned, Ident, ItemFn, ReturnType, Type, Visibility,
+    parse, parse_macro_input, spanned::Spanned, FnArg, Ident, ItemFn, ItemTrait, LitInt, Pat,
+    PatType, ReturnType, Token, TraitItem, TraitItemMethod, Type, Visibility,
 };
 
 #[derive(Debug, PartialEq)]
@@ -184,3 +185,291 @@ pub fn interrupt(args: TokenStream, input: TokenStream) -> TokenStream {
     )
     .into()
 }
+
+/// Marks a method of a `#[syscalls]` trait with its `Syscalls` variant and id,  
+/// e.g. `#[syscall(SendDBGU = 10)]`.
+#[proc_macro_attribute]
+pub fn syscall(_args: TokenStream, input: TokenStream) -> TokenStream {
+    let mut output: TokenStream = parse::Error::new(
+        Span::call_site(),
+        "`#[syscall]` is only valid inside a `#[syscalls]` trait",
+    )
+    .to_compile_error()
+    .into();
+    output.extend(input);
+    output
+}
+
+struct SyscallDefinition {
+    variant: Ident,
+    id: u32,
+    method: TraitItemMethod,
+    arguments: Vec<(Ident, Type)>,
+}
+
+/// Reads `#[syscall(Variant = id)]` and the arguments of a method of a `#[syscalls]` trait.
+fn parse_syscall_definition(method: &mut TraitItemMethod) -> parse::Result<SyscallDefinition> {
+    let position = method
+        .attrs
+        .iter()
+        .position(|attr| attr.path.is_ident("syscall"))
+        .ok_or_else(|| {
+            parse::Error::new(method.sig.span(), "missing `#[syscall(Variant = id)]`")
+        })?;
+    let attr = method.attrs.remove(position);
+    let (variant, id) = attr.parse_args_with(|input: parse::ParseStream| {
+        let variant: Ident = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let id: LitInt = input.parse()?;
+        Ok((variant, id))
+    })?;
+    let id_span = id.span();
+    let id: u32 = id.base10_parse()?;
+    // the id is the immediate of the swi instruction, which has 24 bits
+    if id >= 1 << 24 {
+        return Err(parse::Error::new(
+            id_span,
+            "syscall ids have to fit into 24 bits",
+        ));
+    }
+
+    let sig = &method.sig;
+    if sig.constness.is_some()
+        || sig.asyncness.is_some()
+        || sig.abi.is_some()
+        || !sig.generics.params.is_empty()
+        || sig.variadic.is_some()
+        || method.default.is_some()
+    {
+        return Err(parse::Error::new(
+            sig.span(),
+            "syscalls must have signature `fn(arguments) -> Result<usize, SyscallError>;`",
+        ));
+    }
+    if sig.inputs.len() > 3 {
+        return Err(parse::Error::new(
+            sig.inputs.span(),
+            "syscalls take at most three arguments, passed in r0-r2",
+        ));
+    }
+    let mut arguments = Vec::new();
+    for input in &sig.inputs {
+        match input {
+            FnArg::Typed(PatType { pat, ty, .. }) => match &**pat {
+                Pat::Ident(pat) => arguments.push((pat.ident.clone(), (**ty).clone())),
+                _ => return Err(parse::Error::new(pat.span(), "expected an argument name")),
+            },
+            FnArg::Receiver(receiver) => {
+                return Err(parse::Error::new(
+                    receiver.span(),
+                    "syscalls are associated functions without `self`",
+                ))
+            }
+        }
+    }
+
+    Ok(SyscallDefinition {
+        variant,
+        id,
+        method: method.clone(),
+        arguments,
+    })
+}
+
+/// Generates the syscalls from the signatures in a trait.
+///
+/// Every method of the trait is one syscall marked with `#[syscall(Variant = id)]`,  
+/// ids and variants must be unique. Generated next to the trait are  
+/// - the `Syscalls` enum of all ids,  
+/// - the module `raw` with a user stub per syscall, which passes the arguments in  
+///   r0-r2 and decodes the result in r0,  
+/// - `dispatch`, which decodes the arguments and calls the kernel's implementation  
+///   of the trait,  
+/// - `format_syscall`, which writes a syscall with its decoded arguments for tracing.
+///
+/// Arguments are converted by `SyscallArgument` and printed with `Debug`, the trait has  
+/// to be in scope together with `SyscallError` and `decode_result`.
+#[proc_macro_attribute]
+pub fn syscalls(args: TokenStream, input: TokenStream) -> TokenStream {
+    let mut item = parse_macro_input!(input as ItemTrait);
+
+    if !args.is_empty() {
+        return parse::Error::new(Span::call_site(), "This attribute accepts no arguments")
+            .to_compile_error()
+            .into();
+    }
+
+    let mut definitions: Vec<SyscallDefinition> = Vec::new();
+    let mut errors = proc_macro2::TokenStream::new();
+    for trait_item in item.items.iter_mut() {
+        let method = match trait_item {
+            TraitItem::Method(method) => method,
+            other => {
+                errors.extend(
+                    parse::Error::new(other.span(), "`#[syscalls]` traits only contain syscalls")
+                        .to_compile_error(),
+                );
+                continue;
+            }
+        };
+        match parse_syscall_definition(method) {
+            Ok(definition) => {
+                let duplicate = definitions
+                    .iter()
+                    .find(|other| other.id == definition.id || other.variant == definition.variant);
+                if let Some(other) = duplicate {
+                    let message = format!(
+                        "syscall `{}` = {} collides with `{}` = {}",
+                        definition.variant, definition.id, other.variant, other.id
+                    );
+                    errors.extend(
+                        parse::Error::new(definition.variant.span(), message).to_compile_error(),
+                    );
+                }
+                definitions.push(definition);
+            }
+            Err(error) => errors.extend(error.to_compile_error()),
+        }
+    }
+    if !errors.is_empty() {
+        return errors.into();
+    }
+
+    let trait_ident = &item.ident;
+    let variants: Vec<&Ident> = definitions.iter().map(|d| &d.variant).collect();
+    let ids = definitions.iter().map(|d| d.id);
+
+    let stubs = definitions.iter().map(|definition| {
+        let variant = &definition.variant;
+        let docs = definition
+            .method
+            .attrs
+            .iter()
+            .filter(|attr| attr.path.is_ident("doc"));
+        let ident = &definition.method.sig.ident;
+        let inputs = &definition.method.sig.inputs;
+        let output = &definition.method.sig.output;
+        // the kernel returns with r1-r3 overwritten, whether they held arguments or not
+        let registers = ["r1", "r2"];
+        let mut operands = Vec::new();
+        for (index, (argument, _)) in definition.arguments.iter().enumerate() {
+            operands.push(if index == 0 {
+                quote!(inlateout("r0") SyscallArgument::into_register(#argument) => result)
+            } else {
+                let register = registers[index - 1];
+                quote!(inout(#register) SyscallArgument::into_register(#argument) => _)
+            });
+        }
+        if operands.is_empty() {
+            operands.push(quote!(lateout("r0") result));
+        }
+        for register in registers.iter().skip(definition.arguments.len().max(1) - 1) {
+            operands.push(quote!(lateout(#register) _));
+        }
+        operands.push(quote!(lateout("r3") _));
+        quote!(
+            #(#docs)*
+            #[inline(never)]
+            pub fn #ident(#inputs) #output {
+                let result: usize;
+                unsafe {
+                    asm!("swi #{call_id}", call_id = const Syscalls::#variant as u32, #(#operands),*);
+                }
+                decode_result(result)
+            }
+        )
+    });
+
+    let dispatch_arms = definitions.iter().map(|definition| {
+        let variant = &definition.variant;
+        let ident = &definition.method.sig.ident;
+        let arguments = (0..definition.arguments.len())
+            .map(|index| quote!(SyscallArgument::from_register(arguments[#index])?));
+        quote!(Syscalls::#variant => H::#ident(#(#arguments),*))
+    });
+
+    let format_arms = definitions.iter().map(|definition| {
+        let variant = &definition.variant;
+        let call = format!("{}(", definition.method.sig.ident);
+        let arguments = definition
+            .arguments
+            .iter()
+            .enumerate()
+            .map(|(index, (argument, ty))| {
+                let separator = if index == 0 { "" } else { ", " };
+                let name = format!("{}{}: ", separator, argument);
+                quote!(
+                    core::fmt::Write::write_str(out, #name)?;
+                    match <#ty as SyscallArgument>::from_register(arguments[#index]) {
+                        Ok(value) => core::fmt::Write::write_fmt(out, format_args!("{:?}", value))?,
+                        Err(_) => core::fmt::Write::write_fmt(
+                            out,
+                            format_args!("<invalid {:#x}>", arguments[#index]),
+                        )?,
+                    }
+                )
+            });
+        quote!(Syscalls::#variant => {
+            core::fmt::Write::write_str(out, #call)?;
+            #(#arguments)*
+        })
+    });
+
+    quote!(
+        #item
+
+        /// Ids of the syscalls, passed as immediate of the `swi` instruction.
+        #[derive(Debug, Eq, PartialEq, TryFromPrimitive)]
+        #[repr(u32)]
+        pub enum Syscalls {
+            #(#variants = #ids,)*
+        }
+
+        /// User side stubs of the syscalls, generated by `#[syscalls]`.
+        pub mod raw {
+            use super::*;
+
+            #(#stubs)*
+        }
+
+        /// Decodes the arguments of the syscall `id` passed in r0-r2 and calls its handler.
+        /// Fails with `SyscallError::UnknownSyscall` for ids without a syscall.
+        pub fn dispatch<H: #trait_ident>(
+            id: usize,
+            arguments: [usize; 3],
+        ) -> Result<usize, SyscallError> {
+            let syscall = <u32 as core::convert::TryFrom<usize>>::try_from(id)
+                .ok()
+                .and_then(|id| <Syscalls as core::convert::TryFrom<u32>>::try_from(id).ok())
+                .ok_or(SyscallError::UnknownSyscall)?;
+            match syscall {
+                #(#dispatch_arms,)*
+            }
+        }
+
+        /// Writes the syscall `id` with its decoded arguments, e.g. `send_dbgu(character: 65)`.
+        pub fn format_syscall(
+            out: &mut dyn core::fmt::Write,
+            id: usize,
+            arguments: [usize; 3],
+        ) -> core::fmt::Result {
+            let syscall = <u32 as core::convert::TryFrom<usize>>::try_from(id)
+                .ok()
+                .and_then(|id| <Syscalls as core::convert::TryFrom<u32>>::try_from(id).ok());
+            let syscall = match syscall {
+                Some(syscall) => syscall,
+                None => {
+                    return core::fmt::Write::write_fmt(
+                        out,
+                        format_args!("unknown_syscall_{}({:#x}, {:#x}, {:#x})", id, arguments[0], arguments[1], arguments[2]),
+                    )
+                }
+            };
+            match syscall {
+                #(#format_arms)*
+            }
+            core::fmt::Write::write_str(out, ")")
+        }
+    )
+    .into()
+}
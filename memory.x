@@ -17,29 +17,39 @@ SECTIONS
     *(.ExceptionsJump);
   } > SRAM
 
+  /* text and rodata are readable by user threads, the MMU maps whole megabytes */
   .text : {
     *(.text*);
     _end_text = .;
   } > SDRAM
 
-  .data : {
-    *(.data*);
-    _end_data = .;
-  } > SDRAM
-
   .rodata : {
     *(.rodata*);
     _end_rodata = .;
   } > SDRAM
 
+  /* data and bss are only accessible by the kernel */
+  .data : ALIGN(0x100000) {
+    _start_kernel_data = .;
+    *(.data*);
+    _end_data = .;
+  } > SDRAM
+
   .bss : {
     *(.bss*);
     _end_bss = .;
   } > SDRAM
 
+  /* statics of the user tasks built into the kernel, readable and writable by user threads */
+  .user_data : ALIGN(0x100000) {
+    _start_user_data = .;
+    *(.user_data*);
+    _end_user_data = .;
+  } > SDRAM
+
 
   ASSERT(
-     (_end_bss < 0X21000000),
+     (_end_user_data < 0X21000000),
      "kernel program data overflows custom code entry")
 
   
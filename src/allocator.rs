@@ -1,28 +1,40 @@
-use crate::println_with_stack;
+use crate::processor::{self, ProcessorMode};
+use crate::{println_with_stack, user_heap};
 use core::alloc::{GlobalAlloc, Layout};
-use core::ptr::NonNull;
+use rost_allocator::{Heap, HeapStats};
 
 const HEAP_START: usize = 0x2300_0000;
 const HEAP_END: usize = 0x2400_0000;
 
 struct UnsafeHeap;
 
-static mut HEAP: linked_list_allocator::Heap = linked_list_allocator::Heap::empty();
+static mut HEAP: Heap = Heap::empty();
 
 #[global_allocator]
 static GLOBAL_ALLOCATOR: UnsafeHeap = UnsafeHeap {};
 
+/// Serves the kernel from the kernel heap and user threads from the user heap.
+///
+/// User threads cannot access the kernel heap, their allocations go through  
+/// the `Allocate` syscall and are charged to their quota.
 unsafe impl GlobalAlloc for UnsafeHeap {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        HEAP.allocate_first_fit(layout)
-            .ok()
-            .map_or(core::ptr::null_mut::<u8>(), |allocation| {
-                allocation.as_ptr()
-            })
+        if processor::get_processor_mode() == ProcessorMode::User {
+            rost_api::syscalls::allocate(layout.size(), layout.align())
+                .unwrap_or(core::ptr::null_mut())
+        } else {
+            HEAP.allocate(layout)
+        }
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
-        HEAP.deallocate(NonNull::new_unchecked(ptr), layout)
+        if !user_heap::contains(ptr) {
+            HEAP.deallocate(ptr, layout)
+        } else if processor::get_processor_mode() == ProcessorMode::User {
+            let _ = rost_api::syscalls::deallocate(ptr, layout.size(), layout.align());
+        } else {
+            let _ = user_heap::deallocate(ptr, layout);
+        }
     }
 }
 
@@ -33,11 +45,30 @@ pub fn init_allocator() {
 }
 
 pub fn get_current_heap_size() -> usize {
-    unsafe { HEAP.used() }
+    get_heap_stats().used
 }
 
 pub fn get_heap_size_left() -> usize {
-    unsafe { HEAP.free() }
+    get_heap_stats().free
+}
+
+pub fn get_heap_stats() -> HeapStats {
+    unsafe { HEAP.stats() }
+}
+
+/// Prints the heap statistics and the usage of every slab cache.
+pub fn print_heap_stats() {
+    crate::println!("heap: {}", get_heap_stats());
+    for cache in unsafe { HEAP.caches() } {
+        crate::println!(
+            "  cache {:>5}: objects: {} free: {:#X} slabs: {} of {:#X}",
+            cache.object_size(),
+            cache.objects_in_use(),
+            cache.free_bytes(),
+            cache.slabs(),
+            cache.slab_size()
+        );
+    }
 }
 
 // https://rust-embedded.github.io/book/collections/index.html?using-alloc
@@ -104,6 +104,15 @@ pub fn read_char() -> Option<u32> {
     }
 }
 
+/// Writes all bytes to the DBGU Transmit Holding Register, waits for the DBGU to be ready  
+/// before each one
+pub fn write_bytes(bytes: &[u8]) {
+    for &byte in bytes {
+        while helpers::read_register_bit(DBGU::BASE_ADDRESS, DBGU::SR, DBGU::TXRDY) == 0 {}
+        helpers::write_register(DBGU::BASE_ADDRESS, DBGU::THR, byte as u32);
+    }
+}
+
 /// Writes a character to the DBGU Transmit Holding Register when the DBGU is ready
 /// TODO: Loop or return error if not ready?
 pub fn write_char(character: char) {
@@ -1,7 +1,14 @@
-use crate::memory;
+use crate::mmu;
 use crate::processor;
+use crate::threads;
 use log::{error, trace};
 use processor::ProcessorMode;
+use rost_api::syscalls::Signal;
+
+/// Returns true if the exception was raised by code running in `ProcessorMode::User`.
+fn is_user_fault(spsr: usize) -> bool {
+    spsr & 0x1F == ProcessorMode::User as usize
+}
 
 #[rost_macros::exception]
 unsafe extern "C" fn Reset() {
@@ -9,10 +16,14 @@ unsafe extern "C" fn Reset() {
 }
 
 #[rost_macros::exception]
-unsafe extern "C" fn UndefinedInstruction(lr: usize) {
+unsafe extern "C" fn UndefinedInstruction(lr: usize, spsr: usize) {
     trace!("undefined instruction handler");
     assert!(processor::get_processor_mode() == ProcessorMode::System);
 
+    if is_user_fault(spsr) {
+        error!("undefined instruction at {:#X}", lr - 4);
+        threads::handle_user_fault(Signal::IllegalInstruction);
+    }
     panic!("undefined instruction at {:#X}", lr - 4);
 }
 
@@ -42,21 +53,38 @@ unsafe extern "C" fn SoftwareInterrupt(
 }
 
 #[rost_macros::exception]
-unsafe extern "C" fn PrefetchAbort(lr: usize) {
+unsafe extern "C" fn PrefetchAbort(lr: usize, spsr: usize) {
     error!("prefetch abort handler");
     assert!(processor::get_processor_mode() == ProcessorMode::System);
 
-    panic!("prefetch abort at {:#X}", lr - 4);
+    let status = mmu::get_prefetch_fault_status();
+    if is_user_fault(spsr) {
+        error!("prefetch abort at {:#X}: {}", lr - 4, status);
+        threads::handle_user_fault(Signal::SegmentationFault);
+    }
+    panic!("prefetch abort at {:#X}: {}", lr - 4, status);
 }
 
 #[rost_macros::exception]
-unsafe extern "C" fn DataAbort(lr: usize) {
+unsafe extern "C" fn DataAbort(lr: usize, spsr: usize) {
     error!("data abort handler");
     assert!(processor::get_processor_mode() == ProcessorMode::System);
 
+    let address = mmu::get_data_fault_address();
+    let status = mmu::get_data_fault_status();
+    if is_user_fault(spsr) {
+        error!(
+            "data abort at {:#X} for address {:#X}: {}",
+            lr - 4,
+            address,
+            status
+        );
+        threads::handle_user_fault(Signal::SegmentationFault);
+    }
     panic!(
-        "data abort at {:#X} for address {:#X}",
+        "data abort at {:#X} for address {:#X}: {}",
         lr - 4,
-        memory::mc_get_abort_address() // doesn't work in the emulator
+        address,
+        status
     );
 }
@@ -1,6 +1,7 @@
 // print utilities
 
 use crate::dbgu;
+use crate::processor::{self, ProcessorMode};
 
 #[macro_export]
 macro_rules! print {
@@ -40,7 +41,12 @@ macro_rules! println_with_stack {
     }
 }
 
+/// Writes to the DBGU, user threads cannot access it and use the `WriteDBGU` syscall.
 pub fn send_str(chars: &str) {
+    if processor::get_processor_mode() == ProcessorMode::User {
+        let _ = rost_api::syscalls::write_dbgu(chars.as_bytes());
+        return;
+    }
     for character in chars.chars() {
         dbgu::write_char(character);
     }
@@ -2,6 +2,7 @@ use crate::dbgu;
 use crate::interrupt_controller;
 use crate::processor;
 use crate::threads;
+use crate::timer_service;
 use crate::user_tasks;
 use crate::{print, println};
 
@@ -21,14 +22,8 @@ pub fn system_timer_period_interval_timer_elapsed() {
     interrupt_controller::mark_end_of_interrupt!();
 
     threads::wakeup_elapsed_threads();
-
-    unsafe {
-        if threads::SCHEDULER_INTERVAL_COUNTER == 0 {
-            threads::schedule(None);
-        } else {
-            threads::SCHEDULER_INTERVAL_COUNTER -= 1;
-        }
-    }
+    timer_service::deliver_elapsed_timers();
+    threads::scheduler_tick();
 }
 
 pub fn dbgu_character_received() {
@@ -39,12 +34,12 @@ pub fn dbgu_character_received() {
     let last_char =
         dbgu::read_char().expect("there should be a char available in dbgu interrupt") as u8;
 
-    threads::handle_dbgu_new_character_event(last_char as char);
-
-    // TODO: do not forget to remove
-    if unsafe { user_tasks::TASK4_ACTIVE } {
-        user_tasks::task4_dbgu(last_char as char);
-    }
+    let preempt = threads::handle_dbgu_new_character_event(last_char as char);
 
     interrupt_controller::mark_end_of_interrupt!();
+
+    // switch right away to a woken thread with a higher priority
+    if preempt {
+        threads::schedule(None);
+    }
 }
@@ -7,7 +7,6 @@
 extern crate alloc;
 
 use core::panic::PanicInfo;
-use log::error;
 
 mod allocator;
 mod dbgu;
@@ -18,10 +17,20 @@ mod interrupt_controller;
 mod interrupt_handlers;
 mod logger;
 mod memory;
+mod mmu;
+mod pipe;
+mod process;
 mod processor;
+mod scheduler;
+mod strace;
+mod sync;
 mod syscall_handlers;
 mod system_timer;
 mod threads;
+mod timer_queue;
+mod timer_service;
+mod user_heap;
+mod user_ptr;
 mod user_tasks;
 
 /// Initial OS entry point: Sets stack pointers and calls boot function
@@ -101,6 +110,7 @@ pub unsafe extern "C" fn _start() -> ! {
 pub fn boot() {
     memory::toggle_memory_remap(); // blend sram to 0x0 for IVT
     allocator::init_allocator(); // init allocator before print allocations
+    mmu::init_mmu();
 
     assert!(processor::ProcessorMode::System == processor::get_processor_mode());
     assert!(!processor::interrupts_enabled());
@@ -123,12 +133,14 @@ pub fn boot() {
 
     processor::set_interrupts_enabled!(true);
 
-    fn start_thread() {
+    extern "C" fn start_thread(_: *mut core::ffi::c_void) -> usize {
         assert!(processor::ProcessorMode::User == processor::get_processor_mode());
         assert!(processor::interrupts_enabled());
 
-        rost_api::syscalls::create_thread(user_tasks::read_eval_print_loop);
+        rost_api::syscalls::create_thread(user_tasks::read_eval_print_loop)
+            .expect("no memory for the REPL thread");
         // syscalls::create_thread(custom_user_code_thread);
+        0
     }
 
     // noreturn
@@ -136,18 +148,18 @@ pub fn boot() {
 }
 
 fn custom_user_code_thread() {
-    const CUSTOM_CODE_ADDRESS: usize = 0x2100_0000;
     // check for custom user code
-    if unsafe { core::ptr::read(CUSTOM_CODE_ADDRESS as *const u32) > 0 } {
+    if unsafe { core::ptr::read(memory::USER_CODE_START as *const u32) > 0 } {
         unsafe {
             asm!("
             mov lr,  r1
-            mov pc, r0", in("r1") rost_api::syscalls::exit_thread, in("r0") CUSTOM_CODE_ADDRESS);
+            mov pc, r0", in("r1") rost_api::syscalls::exit_thread, in("r0") memory::USER_CODE_START);
         }
     } else {
-        error!(
+        // user threads cannot access the logger
+        println!(
             "no custom user code loaded into qemu at {:#X}",
-            CUSTOM_CODE_ADDRESS
+            memory::USER_CODE_START
         );
     }
 }
@@ -1,5 +1,13 @@
 use crate::helpers;
 
+/// Start of the SDRAM, which holds the kernel and all memory of the user threads.
+pub const SDRAM_START: usize = 0x2000_0000;
+/// End of the 64 MiB SDRAM.
+pub const SDRAM_END: usize = 0x2400_0000;
+/// Custom user code is loaded to this megabyte by the debugger, the kernel has to end below.
+pub const USER_CODE_START: usize = 0x2100_0000;
+pub const USER_CODE_END: usize = 0x2200_0000;
+
 const SRAM_END: usize = 0x2300_0000;
 const STACK_SIZE: usize = 1024 * 4;
 
@@ -27,7 +35,3 @@ impl MemoryController {
 pub fn toggle_memory_remap() {
     helpers::write_register(MemoryController::BASE_ADDRESS, MemoryController::RCR, 1);
 }
-
-pub fn mc_get_abort_address() -> u32 {
-    helpers::read_register(MemoryController::BASE_ADDRESS, MemoryController::AASR)
-}
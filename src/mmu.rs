@@ -0,0 +1,374 @@
+use crate::memory;
+use crate::user_heap;
+use alloc::boxed::Box;
+use core::alloc::Layout;
+use core::fmt;
+
+/// Size of the memory mapped by one first-level section descriptor.
+pub(crate) const SECTION_SIZE: usize = 1 << SECTION_SHIFT;
+const SECTION_SHIFT: usize = 20;
+const TRANSLATION_TABLE_ENTRIES: usize = 4096;
+
+/// Descriptor type of a section, bit 4 has to be set on the ARM920T.
+const DESCRIPTOR_SECTION: u32 = 0b1_0010;
+const DESCRIPTOR_TYPE_MASK: u32 = 0b11;
+const DESCRIPTOR_AP_SHIFT: u32 = 10;
+/// All sections are in domain 0, which is a client domain so the access permissions are checked.
+const DOMAIN_ACCESS_CLIENT: u32 = 0b01;
+
+/// First section of the peripherals, which end at the top of the address space.
+const PERIPHERALS_START: usize = 0xFFF0_0000;
+
+/// Access of user threads to a section, the kernel may always read and write mapped memory.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum UserAccess {
+    None,
+    ReadOnly,
+    ReadWrite,
+}
+
+impl UserAccess {
+    /// AP bits of a section descriptor with system and rom protection disabled.
+    fn access_permissions(self) -> u32 {
+        match self {
+            UserAccess::None => 0b01,
+            UserAccess::ReadOnly => 0b10,
+            UserAccess::ReadWrite => 0b11,
+        }
+    }
+
+    fn from_access_permissions(access_permissions: u32) -> UserAccess {
+        match access_permissions {
+            0b10 => UserAccess::ReadOnly,
+            0b11 => UserAccess::ReadWrite,
+            _ => UserAccess::None,
+        }
+    }
+}
+
+const TRANSLATION_TABLE_ALIGN: usize = 16 * 1024;
+
+/// First-level translation table, it has to be aligned to 16 KiB.
+#[repr(C, align(16384))]
+struct TranslationTable([u32; TRANSLATION_TABLE_ENTRIES]);
+
+static mut TRANSLATION_TABLE: TranslationTable = TranslationTable([0; TRANSLATION_TABLE_ENTRIES]);
+
+extern "C" {
+    static _start_kernel_data: u8;
+    static _start_user_data: u8;
+    static _end_user_data: u8;
+}
+
+/// Returns the translation table of the kernel, which is used by all threads outside of processes.
+fn get_translation_table<'a>() -> &'a mut [u32; TRANSLATION_TABLE_ENTRIES] {
+    unsafe { &mut TRANSLATION_TABLE.0 }
+}
+
+/// Returns the translation table the MMU currently uses.
+fn get_active_translation_table<'a>() -> &'a [u32; TRANSLATION_TABLE_ENTRIES] {
+    let table: usize;
+    unsafe {
+        asm!("mrc p15, 0, {0}, c2, c0, 0", out(reg) table);
+        &*((table & !(TRANSLATION_TABLE_ALIGN - 1)) as *const [u32; TRANSLATION_TABLE_ENTRIES])
+    }
+}
+
+/// Maps the section at `virtual_address` to the section at `physical_address`.
+fn map_section(
+    table: &mut [u32; TRANSLATION_TABLE_ENTRIES],
+    virtual_address: usize,
+    physical_address: usize,
+    access: UserAccess,
+) {
+    table[virtual_address >> SECTION_SHIFT] = (physical_address & !(SECTION_SIZE - 1)) as u32
+        | access.access_permissions() << DESCRIPTOR_AP_SHIFT
+        | DESCRIPTOR_SECTION;
+}
+
+/// Maps the sections containing the addresses from `first` to `last` to themselves.
+fn map_sections(
+    table: &mut [u32; TRANSLATION_TABLE_ENTRIES],
+    first: usize,
+    last: usize,
+    access: UserAccess,
+) {
+    for section in first >> SECTION_SHIFT..=last >> SECTION_SHIFT {
+        map_section(
+            table,
+            section << SECTION_SHIFT,
+            section << SECTION_SHIFT,
+            access,
+        );
+    }
+}
+
+/// Bounds of the statics of the user tasks built into the kernel.
+fn get_user_data() -> (usize, usize) {
+    unsafe {
+        (
+            &_start_user_data as *const u8 as usize,
+            &_end_user_data as *const u8 as usize,
+        )
+    }
+}
+
+/// Builds the translation table and enables the MMU.
+///
+/// All memory is mapped to the same addresses with sections of 1 MiB:
+/// - kernel text and rodata are readable by user threads, which run the user tasks
+///   built into the kernel. The ARM920T cannot map memory executable but unreadable.
+/// - kernel data, bss, the exception mode stacks, the kernel heap and the
+///   peripherals are only accessible by the kernel. The kernel heap holds the
+///   bookkeeping of the user heap and the process heaps and the kernel stacks
+///   with the saved contexts of the threads.
+/// - statics of the user tasks, the custom user code and the user heap are
+///   readable and writable by user threads. The user heap holds nothing but
+///   user allocations, user stacks and the sections of processes.
+///
+/// Everything else is unmapped, accesses raise a translation fault.
+pub fn init_mmu() {
+    let kernel_data_start = unsafe { &_start_kernel_data as *const u8 as usize };
+    let (user_data_start, user_data_end) = get_user_data();
+    let table = get_translation_table();
+
+    // sram, remapped to 0x0 for the vector table, rom and flash
+    map_sections(table, 0x0000_0000, 0x002F_FFFF, UserAccess::None);
+    map_sections(table, 0x1000_0000, 0x10FF_FFFF, UserAccess::None);
+
+    map_sections(
+        table,
+        memory::SDRAM_START,
+        kernel_data_start - 1,
+        UserAccess::ReadOnly,
+    );
+    map_sections(
+        table,
+        kernel_data_start,
+        user_data_start - 1,
+        UserAccess::None,
+    );
+    map_sections(
+        table,
+        user_data_start,
+        user_data_end - 1,
+        UserAccess::ReadWrite,
+    );
+    map_sections(
+        table,
+        (user_data_end + SECTION_SIZE - 1) & !(SECTION_SIZE - 1),
+        memory::USER_CODE_START - 1,
+        UserAccess::None,
+    );
+    map_sections(
+        table,
+        memory::USER_CODE_START,
+        memory::USER_CODE_END - 1,
+        UserAccess::ReadWrite,
+    );
+    map_sections(
+        table,
+        user_heap::USER_HEAP_START,
+        user_heap::USER_HEAP_END - 1,
+        UserAccess::ReadWrite,
+    );
+    // exception mode stacks and kernel heap
+    map_sections(
+        table,
+        user_heap::USER_HEAP_END,
+        memory::SDRAM_END - 1,
+        UserAccess::None,
+    );
+    map_sections(table, PERIPHERALS_START, usize::MAX, UserAccess::None);
+
+    unsafe {
+        asm!(
+            "
+            mcr p15, 0, {table}, c2, c0, 0
+            mcr p15, 0, {domains}, c3, c0, 0
+            mov {tmp}, #0
+            mcr p15, 0, {tmp}, c8, c7, 0
+            mrc p15, 0, {tmp}, c1, c0, 0
+            orr {tmp}, {tmp}, #1
+            mcr p15, 0, {tmp}, c1, c0, 0
+            ",
+            table = in(reg) table.as_ptr(),
+            domains = in(reg) DOMAIN_ACCESS_CLIENT,
+            tmp = out(reg) _,
+        );
+    }
+}
+
+/// Uses the translation table of a process or, for None, the one of the kernel.
+///
+/// The ARM920T does not tag TLB entries with an address space, so the whole  
+/// TLB is invalidated. The caches are never enabled and need no cleaning.
+pub(crate) fn switch_address_space(address_space: Option<&AddressSpace>) {
+    let table = match address_space {
+        Some(address_space) => address_space.table.0.as_ptr(),
+        None => get_translation_table().as_ptr(),
+    };
+    unsafe {
+        asm!(
+            "
+            mcr p15, 0, {table}, c2, c0, 0
+            mov {tmp}, #0
+            mcr p15, 0, {tmp}, c8, c7, 0
+            ",
+            table = in(reg) table,
+            tmp = out(reg) _,
+        );
+    }
+}
+
+fn invalidate_tlb() {
+    unsafe {
+        asm!(
+            "
+            mov {tmp}, #0
+            mcr p15, 0, {tmp}, c8, c7, 0
+            ",
+            tmp = out(reg) _,
+        );
+    }
+}
+
+/// Changes the access of user threads outside of processes to the sections from `first` to `last`.
+///
+/// Used for memory of the user heap which is handed to a process and given back afterwards.
+pub(crate) fn set_user_access(first: usize, last: usize, access: UserAccess) {
+    map_sections(get_translation_table(), first, last, access);
+    invalidate_tlb();
+}
+
+/// Translation table of a process.
+///
+/// A copy of the kernel's table in which user threads cannot access the user heap,  
+/// the custom user code and the statics of the built-in user tasks. Instead  
+/// the image of the process is mapped to `USER_CODE_START` and its heap is  
+/// accessible, so several processes can run the same binary.
+pub(crate) struct AddressSpace {
+    table: Box<TranslationTable>,
+}
+
+impl AddressSpace {
+    /// Creates the address space of a process with the image at the section `image`  
+    /// and the heap from `heap` to `heap + heap_size`, both outside of the image.
+    ///
+    /// Returns None if the kernel heap has no memory for the table.
+    pub(crate) fn new(image: usize, heap: usize, heap_size: usize) -> Option<AddressSpace> {
+        // copied in place, the table does not fit on the stack
+        let mut table = unsafe {
+            let table =
+                alloc::alloc::alloc(Layout::new::<TranslationTable>()) as *mut TranslationTable;
+            if table.is_null() {
+                return None;
+            }
+            table.copy_from_nonoverlapping(&TRANSLATION_TABLE, 1);
+            Box::from_raw(table)
+        };
+
+        let (user_data_start, user_data_end) = get_user_data();
+        let entries = &mut table.0;
+        map_sections(
+            entries,
+            user_data_start,
+            user_data_end - 1,
+            UserAccess::None,
+        );
+        map_sections(
+            entries,
+            memory::USER_CODE_START,
+            memory::USER_CODE_END - 1,
+            UserAccess::None,
+        );
+        map_sections(
+            entries,
+            user_heap::USER_HEAP_START,
+            user_heap::USER_HEAP_END - 1,
+            UserAccess::None,
+        );
+        map_section(
+            entries,
+            memory::USER_CODE_START,
+            image,
+            UserAccess::ReadWrite,
+        );
+        map_sections(entries, heap, heap + heap_size - 1, UserAccess::ReadWrite);
+        Some(AddressSpace { table })
+    }
+}
+
+/// Checks that user threads may access `length` bytes from `start` with `access`  
+/// in the address space the MMU currently uses, which is the one of the running thread.
+pub fn is_user_accessible(start: usize, length: usize, access: UserAccess) -> bool {
+    let end = match start.checked_add(length) {
+        Some(end) if length > 0 => end,
+        _ => return length == 0,
+    };
+    let table = get_active_translation_table();
+    (start >> SECTION_SHIFT..=(end - 1) >> SECTION_SHIFT).all(|section| {
+        let descriptor = table[section];
+        descriptor & DESCRIPTOR_TYPE_MASK == DESCRIPTOR_SECTION & DESCRIPTOR_TYPE_MASK
+            && UserAccess::from_access_permissions(descriptor >> DESCRIPTOR_AP_SHIFT & 0b11)
+                >= access
+    })
+}
+
+/// Content of a fault status register of the MMU.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct FaultStatus(u32);
+
+impl FaultStatus {
+    pub fn domain(&self) -> u32 {
+        self.0 >> 4 & 0xF
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self.0 & 0xF {
+            0b0001 | 0b0011 => "alignment fault",
+            0b0101 => "translation fault (section)",
+            0b0111 => "translation fault (page)",
+            0b1001 => "domain fault (section)",
+            0b1011 => "domain fault (page)",
+            0b1101 => "permission fault (section)",
+            0b1111 => "permission fault (page)",
+            0b1100 | 0b1110 => "external abort on translation",
+            0b0100 | 0b0110 | 0b1000 | 0b1010 => "external abort",
+            _ => "unknown fault",
+        }
+    }
+}
+
+impl fmt::Display for FaultStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} in domain {}", self.description(), self.domain())
+    }
+}
+
+/// Returns the virtual address accessed by the last aborted load or store.
+pub fn get_data_fault_address() -> usize {
+    let address: usize;
+    unsafe {
+        asm!("mrc p15, 0, {0}, c6, c0, 0", out(reg) address);
+    }
+    address
+}
+
+/// Returns the cause of the last data abort.
+pub fn get_data_fault_status() -> FaultStatus {
+    let status: u32;
+    unsafe {
+        asm!("mrc p15, 0, {0}, c5, c0, 0", out(reg) status);
+    }
+    FaultStatus(status)
+}
+
+/// Returns the cause of the last prefetch abort, the faulting address is the aborted instruction.
+pub fn get_prefetch_fault_status() -> FaultStatus {
+    let status: u32;
+    unsafe {
+        asm!("mrc p15, 0, {0}, c5, c0, 1", out(reg) status);
+    }
+    FaultStatus(status)
+}
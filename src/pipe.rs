@@ -0,0 +1,208 @@
+use crate::process::ProcessId;
+use crate::threads::{self, TimeoutValue, WaitResult, WaitingReason};
+use alloc::collections::{btree_map::BTreeMap, vec_deque::VecDeque};
+use alloc::string::String;
+use alloc::vec::Vec;
+use rost_api::syscalls::{SyscallError, MAX_PIPE_NAME_LENGTH, PIPE_CAPACITY};
+
+pub(crate) type PipeId = usize;
+
+/// Named byte stream between threads.
+///
+/// The ring buffer holds at most `PIPE_CAPACITY` bytes, readers wait  
+/// while it is empty and writers while it is full.
+struct Pipe {
+    name: String,
+    buffer: VecDeque<u8>,
+    /// amount of `create_pipe` and `open_pipe` calls not closed yet per process
+    open_counts: BTreeMap<ProcessId, usize>,
+}
+
+static mut PIPES: Option<BTreeMap<PipeId, Pipe>> = None;
+static mut LAST_PIPE_ID: PipeId = 0;
+
+fn get_pipes<'a>() -> &'a mut BTreeMap<PipeId, Pipe> {
+    unsafe { PIPES.get_or_insert_with(BTreeMap::new) }
+}
+
+fn get_pipe<'a>(pipe_id: PipeId) -> Result<&'a mut Pipe, SyscallError> {
+    get_pipes()
+        .get_mut(&pipe_id)
+        .ok_or(SyscallError::PipeNotFound)
+}
+
+/// Returns a pipe the process of the running thread opened,  
+/// pipes of other processes are reported as `SyscallError::PipeNotFound`.
+fn get_open_pipe<'a>(pipe_id: PipeId) -> Result<&'a mut Pipe, SyscallError> {
+    let pipe = get_pipe(pipe_id)?;
+    if !pipe.open_counts.contains_key(&current_process_id()) {
+        return Err(SyscallError::PipeNotFound);
+    }
+    Ok(pipe)
+}
+
+fn find_pipe(name: &str) -> Option<(PipeId, &'static mut Pipe)> {
+    get_pipes()
+        .iter_mut()
+        .find(|(_, pipe)| pipe.name == name)
+        .map(|(&pipe_id, pipe)| (pipe_id, pipe))
+}
+
+/// Creates a pipe with a name unique among all open pipes and opens it.
+pub(crate) fn create_pipe(name: &str) -> Result<PipeId, SyscallError> {
+    if name.is_empty() || name.len() > MAX_PIPE_NAME_LENGTH {
+        return Err(SyscallError::InvalidName);
+    }
+    if find_pipe(name).is_some() {
+        return Err(SyscallError::PipeAlreadyExists);
+    }
+    unsafe {
+        LAST_PIPE_ID += 1;
+        get_pipes().insert(
+            LAST_PIPE_ID,
+            Pipe {
+                name: String::from(name),
+                buffer: VecDeque::with_capacity(PIPE_CAPACITY),
+                open_counts: core::iter::once((current_process_id(), 1)).collect(),
+            },
+        );
+        Ok(LAST_PIPE_ID)
+    }
+}
+
+/// Opens an existing pipe by its name.
+pub(crate) fn open_pipe(name: &str) -> Result<PipeId, SyscallError> {
+    let (pipe_id, pipe) = find_pipe(name).ok_or(SyscallError::PipeNotFound)?;
+    *pipe.open_counts.entry(current_process_id()).or_insert(0) += 1;
+    Ok(pipe_id)
+}
+
+/// Closes the pipe once for the process of the running thread, the last close removes it.
+///
+/// Threads still waiting for the removed pipe get woken and fail  
+/// with `SyscallError::PipeNotFound`.
+pub(crate) fn close_pipe(pipe_id: PipeId) -> Result<(), SyscallError> {
+    let pipe = get_pipe(pipe_id)?;
+    let process_id = current_process_id();
+    let open_count = pipe
+        .open_counts
+        .get_mut(&process_id)
+        .ok_or(SyscallError::PipeNotFound)?;
+    *open_count -= 1;
+    if *open_count == 0 {
+        pipe.open_counts.remove(&process_id);
+    }
+    if remove_if_closed(pipe_id) {
+        threads::schedule(None);
+    }
+    Ok(())
+}
+
+/// Closes every pipe a finished process still has open.
+///
+/// Called by the scheduler, woken threads get scheduled by the caller.
+pub(crate) fn release_process_pipes(process_id: ProcessId) {
+    let pipe_ids: Vec<PipeId> = get_pipes()
+        .iter_mut()
+        .filter_map(|(&pipe_id, pipe)| pipe.open_counts.remove(&process_id).map(|_| pipe_id))
+        .collect();
+    for pipe_id in pipe_ids {
+        remove_if_closed(pipe_id);
+    }
+}
+
+/// Removes a pipe no process has open anymore and wakes the threads waiting for it.  
+/// Returns true if one of them should preempt the running thread.
+fn remove_if_closed(pipe_id: PipeId) -> bool {
+    if !get_pipes()[&pipe_id].open_counts.is_empty() {
+        return false;
+    }
+    get_pipes().remove(&pipe_id);
+    threads::wake_waiting_threads(&WaitingReason::PipeRead(pipe_id))
+        | threads::wake_waiting_threads(&WaitingReason::PipeWrite(pipe_id))
+}
+
+fn current_process_id() -> ProcessId {
+    threads::get_current_thread().process_id
+}
+
+/// Returns true if reading the pipe does not block, a removed pipe  
+/// or one not opened by the process is readable as reading fails immediately.
+pub(crate) fn is_readable(pipe_id: PipeId) -> bool {
+    get_open_pipe(pipe_id).map_or(true, |pipe| !pipe.buffer.is_empty())
+}
+
+/// Reads the available bytes up to the size of `buffer`, waits while the pipe is empty.
+///
+/// Returns the amount of bytes read and wakes writers waiting for space.  
+/// Fails if the process of the running thread did not open the pipe  
+/// and with `SyscallError::TimedOut` if the pipe stayed empty until `timeout`.
+pub(crate) fn read_pipe(
+    pipe_id: PipeId,
+    buffer: &mut [u8],
+    timeout: Option<TimeoutValue>,
+) -> Result<usize, SyscallError> {
+    if buffer.is_empty() {
+        return get_open_pipe(pipe_id).map(|_| 0);
+    }
+    loop {
+        // looked up again after waiting, the pipe may have been closed in the meantime
+        let pipe = get_open_pipe(pipe_id)?;
+        if !pipe.buffer.is_empty() {
+            let length = buffer.len().min(pipe.buffer.len());
+            for (byte, value) in buffer.iter_mut().zip(pipe.buffer.drain(..length)) {
+                *byte = value;
+            }
+            if threads::wake_waiting_threads(&WaitingReason::PipeWrite(pipe_id)) {
+                threads::schedule(None);
+            }
+            return Ok(length);
+        }
+        if threads::wait(WaitingReason::PipeRead(pipe_id), timeout) == WaitResult::TimedOut {
+            return Err(SyscallError::TimedOut);
+        }
+    }
+}
+
+/// Writes all of `data` into the pipe, waits whenever the pipe is full.
+///
+/// Readers get woken as soon as a part of the data is written. Returns the amount  
+/// of bytes written, which is less than the length of `data` if the pipe got  
+/// removed or `timeout` elapsed after a part was written. Otherwise fails if the  
+/// process of the running thread did not open the pipe or with `SyscallError::TimedOut`.
+pub(crate) fn write_pipe(
+    pipe_id: PipeId,
+    data: &[u8],
+    timeout: Option<TimeoutValue>,
+) -> Result<usize, SyscallError> {
+    if data.is_empty() {
+        return get_open_pipe(pipe_id).map(|_| 0);
+    }
+    let mut written = 0;
+    while written < data.len() {
+        // a part already in the pipe counts as success, the reader may consume it
+        let pipe = match get_open_pipe(pipe_id) {
+            Ok(pipe) => pipe,
+            Err(_) if written > 0 => return Ok(written),
+            Err(error) => return Err(error),
+        };
+        let free = PIPE_CAPACITY - pipe.buffer.len();
+        if free == 0 {
+            if threads::wait(WaitingReason::PipeWrite(pipe_id), timeout) == WaitResult::TimedOut {
+                if written > 0 {
+                    return Ok(written);
+                }
+                return Err(SyscallError::TimedOut);
+            }
+            continue;
+        }
+
+        let length = free.min(data.len() - written);
+        pipe.buffer.extend(&data[written..written + length]);
+        written += length;
+        if threads::wake_waiting_threads(&WaitingReason::PipeRead(pipe_id)) {
+            threads::schedule(None);
+        }
+    }
+    Ok(written)
+}
@@ -0,0 +1,194 @@
+use crate::mmu::{self, AddressSpace, UserAccess};
+use crate::threads::{self, ThreadId};
+use crate::{memory, pipe, sync, user_heap};
+use alloc::boxed::Box;
+use alloc::collections::btree_map::BTreeMap;
+use alloc::vec::Vec;
+use rost_allocator::Heap;
+use rost_api::syscalls::{SyscallError, MAX_PROCESS_IMAGE_SIZE};
+
+pub(crate) type ProcessId = usize;
+
+/// Process of the idle thread and the user tasks built into the kernel.  
+/// Its threads use the kernel's address space and the shared user heap.
+pub(crate) const KERNEL_PROCESS_ID: ProcessId = 0;
+/// Memory for the stacks and allocations of the threads of a process.
+const PROCESS_HEAP_SIZE: usize = mmu::SECTION_SIZE;
+
+/// A user binary running in its own address space.
+///
+/// The image and the heap of a process are sections of the user heap,  
+/// which only the threads of the process can access.
+pub(crate) struct Process {
+    /// thread running the entry of the image, the process ends when it stops
+    main_thread_id: ThreadId,
+    /// section with the copy of the image, mapped to `USER_CODE_START`
+    image: usize,
+    heap_memory: usize,
+    heap: Heap,
+    /// bookkeeping of `heap`, on the kernel heap out of reach of the process
+    heap_metadata: Box<[u32]>,
+    address_space: AddressSpace,
+}
+
+static mut PROCESSES: Option<BTreeMap<ProcessId, Process>> = None;
+static mut LAST_PROCESS_ID: ProcessId = KERNEL_PROCESS_ID;
+
+fn get_processes<'a>() -> &'a mut BTreeMap<ProcessId, Process> {
+    unsafe { PROCESSES.get_or_insert_with(BTreeMap::new) }
+}
+
+/// Takes the sections of a new process from the user heap and copies `image` into them.
+///
+/// The rest of the image section is zeroed for the statics of the binary.  
+/// The sections are taken away from the threads of the kernel process.
+fn create_process(image: &[u8]) -> Result<ProcessId, SyscallError> {
+    if image.len() > MAX_PROCESS_IMAGE_SIZE {
+        return Err(SyscallError::InvalidArgument);
+    }
+    let image_memory = user_heap::allocate_section().ok_or(SyscallError::OutOfMemory)?;
+    let heap_memory = match user_heap::allocate_section() {
+        Some(heap_memory) => heap_memory,
+        None => {
+            user_heap::deallocate_section(image_memory);
+            return Err(SyscallError::OutOfMemory);
+        }
+    };
+    let address_space = match AddressSpace::new(image_memory, heap_memory, PROCESS_HEAP_SIZE) {
+        Some(address_space) => address_space,
+        None => {
+            user_heap::deallocate_section(image_memory);
+            user_heap::deallocate_section(heap_memory);
+            return Err(SyscallError::OutOfMemory);
+        }
+    };
+    mmu::set_user_access(
+        image_memory,
+        image_memory + mmu::SECTION_SIZE - 1,
+        UserAccess::None,
+    );
+    mmu::set_user_access(
+        heap_memory,
+        heap_memory + PROCESS_HEAP_SIZE - 1,
+        UserAccess::None,
+    );
+
+    let mut heap = Heap::empty();
+    unsafe {
+        let memory = core::slice::from_raw_parts_mut(image_memory as *mut u8, mmu::SECTION_SIZE);
+        memory[..image.len()].copy_from_slice(image);
+        memory[image.len()..].fill(0);
+        let heap_metadata = user_heap::init_heap(&mut heap, heap_memory, PROCESS_HEAP_SIZE);
+
+        LAST_PROCESS_ID += 1;
+        get_processes().insert(
+            LAST_PROCESS_ID,
+            Process {
+                // set by `spawn_process` once the thread exists
+                main_thread_id: threads::IDLE_THREAD_ID,
+                image: image_memory,
+                heap_memory,
+                heap,
+                heap_metadata,
+                address_space,
+            },
+        );
+        Ok(LAST_PROCESS_ID)
+    }
+}
+
+/// Starts a process running a copy of `image` and returns the id of its main thread.
+///
+/// The image has to be linked to `USER_CODE_START`, its first instruction is the entry.  
+/// The main thread is a child of the running thread.
+pub(crate) fn spawn_process(image: &[u8]) -> Result<ThreadId, SyscallError> {
+    let process_id = create_process(image)?;
+    let entry = threads::ThreadEntry {
+        function: unsafe {
+            core::mem::transmute::<usize, rost_api::syscalls::ThreadEntry>(memory::USER_CODE_START)
+        },
+        arg: core::ptr::null_mut(),
+    };
+    match threads::create_process_thread(process_id, entry) {
+        Ok(thread_id) => {
+            get_processes().get_mut(&process_id).unwrap().main_thread_id = thread_id;
+            Ok(thread_id)
+        }
+        Err(error) => {
+            release_process(process_id);
+            Err(error)
+        }
+    }
+}
+
+/// Returns the main thread of a process, None for the kernel process.
+pub(crate) fn get_main_thread_id(process_id: ProcessId) -> Option<ThreadId> {
+    get_processes()
+        .get(&process_id)
+        .map(|process| process.main_thread_id)
+}
+
+/// Returns the heap of a process, None for the kernel process which uses the user heap.
+pub(crate) fn get_heap<'a>(process_id: ProcessId) -> Option<&'a mut Heap> {
+    get_processes()
+        .get_mut(&process_id)
+        .map(|process| &mut process.heap)
+}
+
+/// Switches the MMU to the address space of a process.
+pub(crate) fn switch_address_space(process_id: ProcessId) {
+    mmu::switch_address_space(
+        get_processes()
+            .get(&process_id)
+            .map(|process| &process.address_space),
+    );
+}
+
+/// Frees a process and gives its sections back to the user heap.
+///
+/// The pipes the process still has open get closed and its synchronization  
+/// objects removed, so threads of other processes waiting for them get woken.
+fn release_process(process_id: ProcessId) {
+    let process = get_processes().remove(&process_id).unwrap();
+    pipe::release_process_pipes(process_id);
+    sync::release_process_objects(process_id);
+    user_heap::release_process(process_id);
+    for &(memory, size) in &[
+        (process.image, mmu::SECTION_SIZE),
+        (process.heap_memory, PROCESS_HEAP_SIZE),
+    ] {
+        mmu::set_user_access(memory, memory + size - 1, UserAccess::ReadWrite);
+        user_heap::deallocate_section(memory);
+    }
+    // the bookkeeping has to outlive the heap
+    drop(process.heap_metadata);
+}
+
+/// Frees every process for which `in_use` returns false.
+///
+/// Called by the scheduler with the processes which still have threads with  
+/// a stack, all threads of the other processes stopped and none of them runs.
+pub(crate) fn release_processes(in_use: impl Fn(ProcessId) -> bool) {
+    let finished_process_ids: Vec<ProcessId> = get_processes()
+        .keys()
+        .copied()
+        .filter(|&process_id| !in_use(process_id))
+        .collect();
+    for process_id in finished_process_ids {
+        release_process(process_id);
+    }
+}
+
+/// Prints every process with its main thread and heap.
+pub fn print_processes() {
+    crate::println!("processes:");
+    for (process_id, process) in get_processes().iter() {
+        crate::println!(
+            "  process {}: main thread: {} image: {:#X} heap: {}",
+            process_id,
+            process.main_thread_id,
+            process.image,
+            process.heap.stats()
+        );
+    }
+}
@@ -13,8 +13,7 @@ pub enum ProcessorMode {
     System = 0x1F,
 }
 
-/// Returns the current processor mode.
-/// Requires the caller to be in a priviliged mode.
+/// Returns the current processor mode, user threads may call it, too.
 pub fn get_processor_mode() -> ProcessorMode {
     let mut cpsr: u32;
 
@@ -25,27 +24,6 @@ pub fn get_processor_mode() -> ProcessorMode {
     ProcessorMode::try_from((cpsr & 0x1F) as u8).unwrap()
 }
 
-/// Switches the processor to the specified mode.  
-/// Requires the caller to be in a priviliged mode.  
-/// Clobbers r0.
-macro_rules! _switch_processor_mode {
-    ($mode:expr) => {
-        #[allow(unused_unsafe)]
-        unsafe {
-            asm!(
-                "
-            MRS {tmp}, cpsr
-            BIC {tmp}, {tmp}, #0x1F
-            ORR {tmp}, {tmp}, {mode}
-            MSR cpsr_c, {tmp}
-            ", mode = const $mode as i32, tmp = out(reg) _
-            );
-        }
-   };
-}
-
-pub(crate) use _switch_processor_mode as switch_processor_mode;
-
 pub fn interrupts_enabled() -> bool {
     let mut cpsr: u32;
 
@@ -131,36 +109,52 @@ pub(crate) use _set_interrupts_enabled as set_interrupts_enabled;
 /// System Mode instead of the exception mode that was entered by the  
 /// exception. The benefit is that it allows the interrupt routine to   
 /// enable nested interrupts while using function calls, without the risk  
-/// of corrupting the link register by a second exception to the same exception mode.
+/// of corrupting the link register by a second exception to the same exception mode.  
+/// The context of the interrupted code is saved below the stack pointer of the  
+/// interrupted mode, along with that stack pointer. If a thread was interrupted  
+/// in user mode, the context goes on the kernel stack of the thread starting  
+/// at `threads::KERNEL_STACK_TOP` instead, so user threads cannot tamper with it.
 macro_rules! _exception_routine {
     (subroutine=$subcall:ident, lr_size=$lr_size:expr, nested_interrupt=true) => {
             asm!(
-                // save two work registers (r0, r1), get sp_user and push spsr and context to userstack
-                "push {{r0, r1}}",  // r1, r0   |
+                // save three work registers (r0-r2), get spsr and sp_user
+                "push {{r0-r2}}",   // r2, r1, r0   |
                 "mrs r0, spsr",
-                "sub sp, sp, #4",   // r1, r0, x    |
-                "stm sp, {{sp}}^",  // r1, r0, sp_user  |
+                "sub sp, sp, #4",   // r2, r1, r0, x    |
+                "stm sp, {{sp}}^",  // r2, r1, r0, sp_user  |
                 "nop",
-                "pop {{r1}}",       // r1, r0   |
+                "pop {{r1}}",       // r2, r1, r0   |
+
+                // user mode was interrupted: continue on the kernel stack of the thread
+                "and r2, r0, #0x1F",
+                "cmp r2, #0x10",
+                "mov r2, r1",
+                "ldreq r1, ={kernel_stack_top}",
+                "ldreq r1, [r1]",
+
+                // push sp_user, spsr and context to the stack of the interrupted code
+                "stmfd r1!, {{r2}}",    // r2, r1, r0   |   sp_user
+                "ldr r2, [sp, #8]",
                 "sub lr, lr, #{lr_size}",
-                "stmfd r1!, {{r0, r2-r12,r14}}",  // r1, r0   |   spsr, r2-12, r14_irq
+                "stmfd r1!, {{r0, r2-r12,r14}}",  // r2, r1, r0   |   sp_user, spsr, r2-12, r14_irq
 
-                // save original r0 and cpsr on userstack
-                "pop {{r0}}",               // r1   |   spsr, r2-12, r14_irq
-                "stmfd r1!, {{r0}}",          // r1   |  spsr, r2-12, r14_irq, r0
+                // save original r0 and cpsr
+                "pop {{r0}}",               // r2, r1   |   sp_user, spsr, r2-12, r14_irq
+                "stmfd r1!, {{r0}}",          // r2, r1   |  sp_user, spsr, r2-12, r14_irq, r0
                 "mrs r0, cpsr",
-                "stmfd r1!, {{r0}}",          // r1   |  spsr, r2-12, r14_irq, r0, cpsr
-                "pop {{r1}}",               //      |  spsr, r2-12, r14_irq, r0, cpsr
+                "stmfd r1!, {{r0}}",          // r2, r1   |  sp_user, spsr, r2-12, r14_irq, r0, cpsr
+                "mov r0, r1",
+                "pop {{r1, r2}}",               //      |  sp_user, spsr, r2-12, r14_irq, r0, cpsr
 
                 // switch to system mode
-                "MRS r0, cpsr",
-                "BIC r0, r0, #0x1F",
-                "ORR r0, r0, #0x1F",
-                "MSR cpsr_c, r0",
+                "MRS r2, cpsr",
+                "BIC r2, r2, #0x1F",
+                "ORR r2, r2, #0x1F",
+                "MSR cpsr_c, r2",
 
-                // increment sp to actual stacksize and save rest of thread context plus user lr
-                "sub sp, sp, #(15*4)",
-                "push {{r1, r14}}",         // spsr, r2-12, r14_irq, r0, cpsr, r14_user, r0
+                // continue below the saved context and save user r1 and lr
+                "mov sp, r0",
+                "push {{r1, r14}}",         // sp_user, spsr, r2-12, r14_irq, r0, cpsr, r14_user, r0
 
                 // enable interrupts
                 "MRS r0, CPSR",
@@ -176,21 +170,22 @@ macro_rules! _exception_routine {
                 "MSR    CPSR_c, r0",
 
                 // restore user r1 and lr, switch back to former exception mode
-                "pop {{r1,r14}}",       // spsr, r2-12, r14_irq, r0, cpsr
-                "pop {{r0}}",           // spsr, r2-12, r14_irq, r0
+                "pop {{r1,r14}}",       // sp_user, spsr, r2-12, r14_irq, r0, cpsr
+                "pop {{r0}}",           // sp_user, spsr, r2-12, r14_irq, r0
                 "msr CPSR, r0", // switch to former exception mode
 
-                // get sp_user, get user/thread context back
-                "push {{r1}}",      // r1   |   spsr, r2-r14, r0
-                "sub sp, sp, #4",   // r1, x   |   spsr, r2-r14, r0
-                "stm sp, {{sp}}^", // r1, sp_user | spsr, r2-r14, r0
+                // get the saved context back
+                "push {{r1}}",      // r1   |   sp_user, spsr, r2-r14, r0
+                "sub sp, sp, #4",   // r1, x   |   sp_user, spsr, r2-r14, r0
+                "stm sp, {{sp}}^", // r1, sp_sys | sp_user, spsr, r2-r14, r0
                 "nop",
-                "pop {{r1}}",   // r1   | spsr, r2-r14, r0
-                "ldmfd r1!, {{r0}}",  // r1   | spsr, r2-r14
-                "push {{r0}}",  // r1, r0   | spsr, r2-r14
-                "ldmfd r1!, {{r0, r2-r12,r14}}",  // r1, r0
+                "pop {{r1}}",   // r1   | sp_user, spsr, r2-r14, r0
+                "ldmfd r1!, {{r0}}",  // r1   | sp_user, spsr, r2-r14
+                "push {{r0}}",  // r1, r0   | sp_user, spsr, r2-r14
+                "ldmfd r1!, {{r0, r2-r12,r14}}",  // r1, r0   | sp_user
 
-                // write updated sp_user back to orig. register
+                // write the saved sp_user back to orig. register
+                "ldr r1, [r1]",
                 "push {{r1}}",  // r1, r0, sp_user
                 "ldm sp, {{sp}}^",  // r1, r0, x
                 "nop",
@@ -201,60 +196,77 @@ macro_rules! _exception_routine {
                 "pop {{r0, r1}}", // r1, r0
 
                 // return to user mode
-                "subs pc, lr, #0"
+                "subs pc, lr, #0",
+                ".ltorg"
             , subcall = sym $subcall, lr_size = const $lr_size,
+            kernel_stack_top = sym $crate::threads::KERNEL_STACK_TOP,
              options(noreturn));
     };
     (subroutine=$subcall:ident, lr_size=$lr_size:expr, nested_interrupt=false) => {
             asm!(
-                // save two work registers (r0, r1), get sp_user and push spsr and context to userstack
-                "push {{r0, r1}}",  // r1, r0   |
+                // save three work registers (r0-r2), get spsr and sp_user
+                "push {{r0-r2}}",   // r2, r1, r0   |
                 "mrs r0, spsr",
-                "sub sp, sp, #4",   // r1, r0, x    |
-                "stm sp, {{sp}}^",  // r1, r0, sp_user  |
+                "sub sp, sp, #4",   // r2, r1, r0, x    |
+                "stm sp, {{sp}}^",  // r2, r1, r0, sp_user  |
                 "nop",
-                "pop {{r1}}",       // r1, r0   |
+                "pop {{r1}}",       // r2, r1, r0   |
+
+                // user mode was interrupted: continue on the kernel stack of the thread
+                "and r2, r0, #0x1F",
+                "cmp r2, #0x10",
+                "mov r2, r1",
+                "ldreq r1, ={kernel_stack_top}",
+                "ldreq r1, [r1]",
+
+                // push sp_user, spsr and context to the stack of the interrupted code
+                "stmfd r1!, {{r2}}",    // r2, r1, r0   |   sp_user
+                "ldr r2, [sp, #8]",
                 "sub lr, lr, #{lr_size}",
-                "stmfd r1!, {{r0, r2-r12,r14}}",  // r1, r0   |   spsr, r2-12, r14_irq
+                "stmfd r1!, {{r0, r2-r12,r14}}",  // r2, r1, r0   |   sp_user, spsr, r2-12, r14_irq
                 "mov r12, lr",
+                "mov r11, r0",              // keep spsr for the subcall
 
-                // save original r0 and cpsr on userstack
-                "pop {{r0}}",               // r1   |   spsr, r2-12, r14_irq
-                "stmfd r1!, {{r0}}",          // r1   |  spsr, r2-12, r14_irq, r0
+                // save original r0 and cpsr
+                "pop {{r0}}",               // r2, r1   |   sp_user, spsr, r2-12, r14_irq
+                "stmfd r1!, {{r0}}",          // r2, r1   |  sp_user, spsr, r2-12, r14_irq, r0
                 "mrs r0, cpsr",
-                "stmfd r1!, {{r0}}",          // r1   |  spsr, r2-12, r14_irq, r0, cpsr
-                "pop {{r1}}",               //      |  spsr, r2-12, r14_irq, r0, cpsr
+                "stmfd r1!, {{r0}}",          // r2, r1   |  sp_user, spsr, r2-12, r14_irq, r0, cpsr
+                "mov r0, r1",
+                "pop {{r1, r2}}",               //      |  sp_user, spsr, r2-12, r14_irq, r0, cpsr
 
                 // switch to system mode
-                "MRS r0, cpsr",
-                "BIC r0, r0, #0x1F",
-                "ORR r0, r0, #0x1F",
-                "MSR cpsr_c, r0",
+                "MRS r2, cpsr",
+                "BIC r2, r2, #0x1F",
+                "ORR r2, r2, #0x1F",
+                "MSR cpsr_c, r2",
 
-                // increment sp to actual stacksize and save rest of thread context plus user lr
-                "sub sp, sp, #(15*4)",
-                "push {{r1, r14}}",         // spsr, r2-12, r14_irq, r0, cpsr, r1, r14_user
+                // continue below the saved context and save user r1 and lr
+                "mov sp, r0",
+                "push {{r1, r14}}",         // sp_user, spsr, r2-12, r14_irq, r0, cpsr, r1, r14_user
 
                 // jump to subcall
                 "mov r0, r12",
+                "mov r1, r11",
                 "bl {subcall}",
 
                 // restore user r1 and lr, switch back to former exception mode
-                "pop {{r1,r14}}",       // spsr, r2-12, r14_irq, r0, cpsr, r1, lr_user, r1
-                "pop {{r0}}",           // spsr, r2-12, r14_irq, r0
+                "pop {{r1,r14}}",       // sp_user, spsr, r2-12, r14_irq, r0, cpsr
+                "pop {{r0}}",           // sp_user, spsr, r2-12, r14_irq, r0
                 "msr CPSR, r0", // switch to former exception mode
 
-                // get sp_user, get user/thread context back
-                "push {{r1}}",      // r1   |   spsr, r2-r14, r0
-                "sub sp, sp, #4",   // r1, x   |   spsr, r2-r14, r0
-                "stm sp, {{sp}}^", // r1, sp_user | spsr, r2-r14, r0
+                // get the saved context back
+                "push {{r1}}",      // r1   |   sp_user, spsr, r2-r14, r0
+                "sub sp, sp, #4",   // r1, x   |   sp_user, spsr, r2-r14, r0
+                "stm sp, {{sp}}^", // r1, sp_sys | sp_user, spsr, r2-r14, r0
                 "nop",
-                "pop {{r1}}",   // r1   | spsr, r2-r14, r0
-                "ldmfd r1!, {{r0}}",  // r1   | spsr, r2-r14
-                "push {{r0}}",  // r1, r0   | spsr, r2-r14
-                "ldmfd r1!, {{r0, r2-r12,r14}}",  // r1, r0
+                "pop {{r1}}",   // r1   | sp_user, spsr, r2-r14, r0
+                "ldmfd r1!, {{r0}}",  // r1   | sp_user, spsr, r2-r14
+                "push {{r0}}",  // r1, r0   | sp_user, spsr, r2-r14
+                "ldmfd r1!, {{r0, r2-r12,r14}}",  // r1, r0   | sp_user
 
-                // write updated sp_user back to orig. register
+                // write the saved sp_user back to orig. register
+                "ldr r1, [r1]",
                 "push {{r1}}",  // r1, r0, sp_user
                 "ldm sp, {{sp}}^",  // r1, r0, x
                 "nop",
@@ -265,67 +277,81 @@ macro_rules! _exception_routine {
                 "pop {{r0, r1}}", // r1, r0
 
                 // return to user mode
-                "subs pc, lr, #0"
+                "subs pc, lr, #0",
+                ".ltorg"
             , subcall = sym $subcall,
-            lr_size = const $lr_size, options(noreturn));
+            lr_size = const $lr_size,
+            kernel_stack_top = sym $crate::threads::KERNEL_STACK_TOP,
+            options(noreturn));
     };
     (subroutine=$subcall:ident, software_interrupt=true) => {
         asm!(
-            // save two work registers (r0, r1), get sp_user and push spsr and context to userstack
-            "push {{r5, r6}}",  // r6, r5   |
+            // save three work registers (r5-r7), get spsr and sp_user
+            "push {{r5-r7}}",  // r7, r6, r5   |
 
             "mrs r5, spsr",
 
             // get sp pointer from user mode
-            "sub sp, sp, #4",   // r6, r5, x    |
-            "stm sp, {{sp}}^",  // r6, r5, sp_user  |
+            "sub sp, sp, #4",   // r7, r6, r5, x    |
+            "stm sp, {{sp}}^",  // r7, r6, r5, sp_user  |
             "nop",
-            "pop {{r6}}",       // r6, r5   |
+            "pop {{r6}}",       // r7, r6, r5   |
+
+            // syscalls from user mode run on the kernel stack of the thread
+            "and r7, r5, #0x1F",
+            "cmp r7, #0x10",
+            "mov r7, r6",
+            "ldreq r6, ={kernel_stack_top}",
+            "ldreq r6, [r6]",
 
             // correct lr
             "sub lr, lr, #{lr_size}",
-            // save non-volatile registers on user stack
-            "stmfd r6!, {{r5, r7-r12, r14}}",  // r6, r5   |   spsr, r7-r12, r14_irq
-
-            // save original r0 and cpsr on userstack
-            "pop {{r5}}",               // r6   |   spsr, r7-r12, r14_irq
-            "stmfd r6!, {{r5}}",          // r6   |  spsr, r7-r12, r14_irq, r5
+            // save sp_user and non-volatile registers on the stack of the caller
+            "stmfd r6!, {{r7}}",    // r7, r6, r5   |   sp_user
+            "ldr r7, [sp, #8]",
+            "stmfd r6!, {{r5, r7-r12, r14}}",  // r7, r6, r5   |   sp_user, spsr, r7-r12, r14_irq
+
+            // save original r5 and cpsr
+            "pop {{r5}}",               // r7, r6   |   sp_user, spsr, r7-r12, r14_irq
+            "stmfd r6!, {{r5}}",          // r7, r6   |  sp_user, spsr, r7-r12, r14_irq, r5
             "mrs r5, cpsr",
-            "stmfd r6!, {{r5}}",          // r6   |  spsr, r7-r12, r14_irq, r5, cpsr
-            "pop {{r6}}",               //      |  spsr, r7-r12, r14_irq, r5, cpsr
+            "stmfd r6!, {{r5}}",          // r7, r6   |  sp_user, spsr, r7-r12, r14_irq, r5, cpsr
+            "mov r5, r6",
+            "pop {{r6, r7}}",               //      |  sp_user, spsr, r7-r12, r14_irq, r5, cpsr
 
             // get service_id of software_interrupt
             "LDR r3, [lr, #-4]",
             "BIC r3,r3,#0xff000000",
 
             // switch to system mode
-            "MRS r5, cpsr",
-            "BIC r5, r5, #0x1F",
-            "ORR r5, r5, #0x1F",
-            "MSR cpsr_c, r5",
+            "MRS r7, cpsr",
+            "BIC r7, r7, #0x1F",
+            "ORR r7, r7, #0x1F",
+            "MSR cpsr_c, r7",
 
-            // increment sp to actual stacksize and save rest of thread context plus user lr
-            "sub sp, sp, #(10*4)",
-            "push {{r6, r14}}",         // spsr, r7-r12, r14, r14_irq, r5, cpsr, r6, r14_user
+            // continue below the saved context and save user r6 and lr
+            "mov sp, r5",
+            "push {{r6, r14}}",         // sp_user, spsr, r7-r12, r14_irq, r5, cpsr, r6, r14_user
 
             // jump to subcall
             "bl {subcall}",
 
-            // restore user r1 and lr, switch back to former exception mode
-            "pop {{r6,r14}}",       // spsr, r7-r12, r14, r14_irq, r5, cpsr
-            "pop {{r1}}",           // spsr, r7-r12, r14, r14_irq, r5
+            // restore user r6 and lr, switch back to former exception mode
+            "pop {{r6,r14}}",       // sp_user, spsr, r7-r12, r14_irq, r5, cpsr
+            "pop {{r1}}",           // sp_user, spsr, r7-r12, r14_irq, r5
             "msr CPSR, r1", // switch to former exception mode
 
-            // get sp_user, get user/thread context back
-            "sub sp, sp, #4",   // x   |   spsr, r7-r12, r14, r5
-            "stm sp, {{sp}}^", // sp_user | spsr, r7-r12, r14, r5
+            // get the saved context back
+            "sub sp, sp, #4",   // x   |   sp_user, spsr, r7-r12, r14, r5
+            "stm sp, {{sp}}^", // sp_sys | sp_user, spsr, r7-r12, r14, r5
             "nop",
-            "pop {{r1}}",   //   | spsr, r7-r12, r14, r5
+            "pop {{r1}}",   //   | sp_user, spsr, r7-r12, r14, r5
 
-            "ldmfd r1!, {{r5}}",  //    | spsr, r7-r12, r14
-            "ldmfd r1!, {{r2, r7-r12, r14}}",  //
+            "ldmfd r1!, {{r5}}",  //    | sp_user, spsr, r7-r12, r14
+            "ldmfd r1!, {{r2, r7-r12, r14}}",  //    | sp_user
 
-            // write updated sp_user back to orig. register
+            // write the saved sp_user back to orig. register
+            "ldr r1, [r1]",
             "push {{r1}}",  // sp_user
             "ldm sp, {{sp}}^",  // sp_user
             "nop",
@@ -333,9 +359,12 @@ macro_rules! _exception_routine {
 
             // write back orig. exception spsr and return from exception
             "msr SPSR, r2",
-            "subs pc, lr, #0"
+            "subs pc, lr, #0",
+            ".ltorg"
         , subcall = sym $subcall,
-        lr_size = const 0, options(noreturn));
+        lr_size = const 0,
+        kernel_stack_top = sym $crate::threads::KERNEL_STACK_TOP,
+        options(noreturn));
 };
 }
 
@@ -0,0 +1,113 @@
+//! Scheduling policies deciding which ready thread runs next.
+//!
+//! The policy is selected at build time through one of the cargo features
+//! `scheduler-round-robin`, `scheduler-priority` or `scheduler-mlfq`.
+//! Periodic real-time threads are scheduled earliest deadline first on top
+//! of the selected policy.
+//! `threads::schedule` only talks to the policy through the `Scheduler` trait.
+//! The idle thread is never handed to a policy, it runs whenever a policy
+//! has nothing left to pick.
+
+use crate::threads::{ThreadId, TCB};
+
+mod edf;
+#[cfg(feature = "scheduler-priority")]
+mod fixed_priority;
+#[cfg(feature = "scheduler-mlfq")]
+mod mlfq;
+#[cfg(feature = "scheduler-round-robin")]
+mod round_robin;
+
+#[cfg(not(any(
+    feature = "scheduler-round-robin",
+    feature = "scheduler-priority",
+    feature = "scheduler-mlfq"
+)))]
+compile_error!("one scheduler feature has to be enabled");
+
+#[cfg(any(
+    all(feature = "scheduler-round-robin", feature = "scheduler-priority"),
+    all(feature = "scheduler-round-robin", feature = "scheduler-mlfq"),
+    all(feature = "scheduler-priority", feature = "scheduler-mlfq")
+))]
+compile_error!("only one scheduler feature can be enabled at a time");
+
+#[cfg(feature = "scheduler-round-robin")]
+type Policy = round_robin::RoundRobinScheduler;
+#[cfg(feature = "scheduler-priority")]
+type Policy = fixed_priority::FixedPriorityScheduler;
+#[cfg(feature = "scheduler-mlfq")]
+type Policy = mlfq::MultilevelFeedbackQueueScheduler;
+
+pub(crate) type ActiveScheduler = edf::EarliestDeadlineFirst<Policy>;
+
+/// Creates the scheduler selected at build time.
+pub(crate) fn create_scheduler() -> ActiveScheduler {
+    edf::EarliestDeadlineFirst::new(Policy::new())
+}
+
+/// The amount of SysTicks a thread may run before the scheduler gets called.
+pub(crate) const SCHEDULER_INTERVAL: u32 = 5;
+
+/// Ready queue management of a scheduling policy.
+pub(crate) trait Scheduler {
+    /// Adds a thread which became `ThreadState::Ready` to the ready queue.
+    fn enqueue(&mut self, thread: &TCB);
+
+    /// Removes a thread from the ready queue, scheduling state kept  
+    /// about it stays until the thread gets removed.
+    fn dequeue(&mut self, thread_id: ThreadId);
+
+    /// Removes a stopped thread from the ready queue and forgets all  
+    /// scheduling state kept about it.
+    fn remove(&mut self, thread_id: ThreadId) {
+        self.dequeue(thread_id);
+    }
+
+    /// Removes and returns the thread which should run next.  
+    ///
+    /// `running` is the running thread if it could continue to run.  
+    /// Returns None if the running thread should keep the processor  
+    /// or, without a running thread, if no thread is ready.
+    fn pick_next(&mut self, running: Option<&TCB>) -> Option<ThreadId>;
+
+    /// Gets called on every system timer tick for the running thread.  
+    /// Returns true if the scheduler should be called.
+    fn tick(&mut self, running: &TCB) -> bool;
+
+    /// Returns true if the `woken` thread, which just got enqueued,  
+    /// should immediately take the processor from the `running` thread.
+    fn should_preempt(&self, _woken: &TCB, _running: &TCB) -> bool {
+        false
+    }
+}
+
+/// Counts the SysTicks of the running thread's time slice.
+struct TimeSlice {
+    thread_id: Option<ThreadId>,
+    ticks_used: u32,
+}
+
+impl TimeSlice {
+    const fn new() -> Self {
+        TimeSlice {
+            thread_id: None,
+            ticks_used: 0,
+        }
+    }
+
+    /// Counts a tick for the given thread, a new slice starts whenever  
+    /// another thread is running. Returns true once `length` ticks elapsed.
+    fn tick(&mut self, thread_id: ThreadId, length: u32) -> bool {
+        if self.thread_id != Some(thread_id) {
+            self.thread_id = Some(thread_id);
+            self.ticks_used = 0;
+        }
+        self.ticks_used += 1;
+        if self.ticks_used >= length {
+            self.thread_id = None;
+            return true;
+        }
+        false
+    }
+}
@@ -0,0 +1,81 @@
+use super::Scheduler;
+use crate::threads::{ThreadId, TimeoutValue, TCB};
+use alloc::collections::btree_set::BTreeSet;
+
+/// Schedules periodic threads earliest deadline first.  
+///
+/// Periodic threads always run before the threads of the wrapped policy  
+/// and are not preempted by time slices, only by a periodic thread with  
+/// an earlier deadline. All other threads are passed to the wrapped policy.
+pub(crate) struct EarliestDeadlineFirst<S: Scheduler> {
+    /// ready periodic threads ordered by absolute deadline
+    ready: BTreeSet<(TimeoutValue, ThreadId)>,
+    policy: S,
+}
+
+impl<S: Scheduler> EarliestDeadlineFirst<S> {
+    pub(crate) fn new(policy: S) -> Self {
+        EarliestDeadlineFirst {
+            ready: BTreeSet::new(),
+            policy,
+        }
+    }
+
+    fn deadline(thread: &TCB) -> Option<TimeoutValue> {
+        thread.periodic.as_ref().map(|periodic| periodic.deadline())
+    }
+}
+
+impl<S: Scheduler> Scheduler for EarliestDeadlineFirst<S> {
+    fn enqueue(&mut self, thread: &TCB) {
+        match Self::deadline(thread) {
+            Some(deadline) => {
+                self.ready.insert((deadline, thread.id));
+            }
+            None => self.policy.enqueue(thread),
+        }
+    }
+
+    fn dequeue(&mut self, thread_id: ThreadId) {
+        self.ready.retain(|&(_, id)| id != thread_id);
+        self.policy.dequeue(thread_id);
+    }
+
+    fn remove(&mut self, thread_id: ThreadId) {
+        self.ready.retain(|&(_, id)| id != thread_id);
+        self.policy.remove(thread_id);
+    }
+
+    fn pick_next(&mut self, running: Option<&TCB>) -> Option<ThreadId> {
+        let running_deadline = running.and_then(Self::deadline);
+
+        match self.ready.iter().next().copied() {
+            Some((deadline, thread_id)) => {
+                if running_deadline.map_or(false, |running_deadline| running_deadline <= deadline) {
+                    return None;
+                }
+                self.ready.remove(&(deadline, thread_id));
+                Some(thread_id)
+            }
+            // a running periodic thread continues until it waits for its next period
+            None if running_deadline.is_some() => None,
+            None => self.policy.pick_next(running),
+        }
+    }
+
+    fn tick(&mut self, running: &TCB) -> bool {
+        if running.periodic.is_some() {
+            return false;
+        }
+        self.policy.tick(running)
+    }
+
+    fn should_preempt(&self, woken: &TCB, running: &TCB) -> bool {
+        match (Self::deadline(woken), Self::deadline(running)) {
+            (Some(woken_deadline), Some(running_deadline)) => woken_deadline < running_deadline,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => self.policy.should_preempt(woken, running),
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use super::{Scheduler, TimeSlice, SCHEDULER_INTERVAL};
+use crate::threads::{ThreadId, TCB};
+use alloc::collections::{btree_map::BTreeMap, vec_deque::VecDeque};
+use rost_api::syscalls::Priority;
+
+/// Always runs the ready thread with the highest priority,  
+/// threads of equal priority are scheduled round-robin.
+pub(crate) struct FixedPriorityScheduler {
+    ready: BTreeMap<Priority, VecDeque<ThreadId>>,
+    time_slice: TimeSlice,
+}
+
+impl FixedPriorityScheduler {
+    pub(crate) fn new() -> Self {
+        FixedPriorityScheduler {
+            ready: BTreeMap::new(),
+            time_slice: TimeSlice::new(),
+        }
+    }
+
+    fn highest_ready_priority(&self) -> Option<Priority> {
+        self.ready.keys().next_back().copied()
+    }
+}
+
+impl Scheduler for FixedPriorityScheduler {
+    fn enqueue(&mut self, thread: &TCB) {
+        self.ready
+            .entry(thread.priority)
+            .or_insert_with(VecDeque::new)
+            .push_back(thread.id);
+    }
+
+    fn dequeue(&mut self, thread_id: ThreadId) {
+        for queue in self.ready.values_mut() {
+            queue.retain(|&id| id != thread_id);
+        }
+        self.ready.retain(|_, queue| !queue.is_empty());
+    }
+
+    fn pick_next(&mut self, running: Option<&TCB>) -> Option<ThreadId> {
+        let priority = self.highest_ready_priority()?;
+        if let Some(running) = running {
+            if running.priority > priority {
+                return None;
+            }
+        }
+
+        let queue = self.ready.get_mut(&priority).unwrap();
+        let next = queue.pop_front();
+        if queue.is_empty() {
+            self.ready.remove(&priority);
+        }
+        next
+    }
+
+    fn tick(&mut self, running: &TCB) -> bool {
+        // only worth calling the scheduler if a thread of equal priority waits
+        self.time_slice.tick(running.id, SCHEDULER_INTERVAL)
+            && self.highest_ready_priority() >= Some(running.priority)
+    }
+
+    fn should_preempt(&self, woken: &TCB, running: &TCB) -> bool {
+        woken.priority > running.priority
+    }
+}
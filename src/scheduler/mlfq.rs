@@ -0,0 +1,103 @@
+use super::{Scheduler, TimeSlice, SCHEDULER_INTERVAL};
+use crate::threads::{ThreadId, TCB};
+use alloc::collections::{btree_map::BTreeMap, vec_deque::VecDeque};
+
+/// Amount of queues, level 0 is scheduled first.
+const LEVELS: usize = 4;
+/// The amount of SysTicks after which all threads are moved back to level 0.
+const BOOST_INTERVAL: u32 = 100;
+
+/// Multilevel feedback queue scheduler.  
+///
+/// New threads start in the highest level. A thread using up its whole  
+/// time slice is moved down one level, where slices are twice as long.  
+/// Threads which block or yield early keep their level, so interactive  
+/// threads stay above busy ones. All threads get boosted back to the  
+/// highest level periodically to prevent starvation.  
+/// Thread priorities are ignored.
+pub(crate) struct MultilevelFeedbackQueueScheduler {
+    ready: [VecDeque<ThreadId>; LEVELS],
+    levels: BTreeMap<ThreadId, usize>,
+    time_slice: TimeSlice,
+    ticks_until_boost: u32,
+}
+
+impl MultilevelFeedbackQueueScheduler {
+    pub(crate) fn new() -> Self {
+        MultilevelFeedbackQueueScheduler {
+            ready: Default::default(),
+            levels: BTreeMap::new(),
+            time_slice: TimeSlice::new(),
+            ticks_until_boost: BOOST_INTERVAL,
+        }
+    }
+
+    fn level_of(&self, thread_id: ThreadId) -> usize {
+        self.levels.get(&thread_id).copied().unwrap_or(0)
+    }
+
+    fn highest_ready_level(&self) -> Option<usize> {
+        self.ready.iter().position(|queue| !queue.is_empty())
+    }
+
+    fn boost(&mut self) {
+        for level in 1..LEVELS {
+            while let Some(thread_id) = self.ready[level].pop_front() {
+                self.ready[0].push_back(thread_id);
+            }
+        }
+        self.levels.values_mut().for_each(|level| *level = 0);
+    }
+}
+
+impl Scheduler for MultilevelFeedbackQueueScheduler {
+    fn enqueue(&mut self, thread: &TCB) {
+        let level = *self.levels.entry(thread.id).or_insert(0);
+        self.ready[level].push_back(thread.id);
+    }
+
+    fn dequeue(&mut self, thread_id: ThreadId) {
+        // the level stays, a thread cannot escape its demotion by blocking
+        let level = self.level_of(thread_id);
+        self.ready[level].retain(|&id| id != thread_id);
+    }
+
+    fn remove(&mut self, thread_id: ThreadId) {
+        self.dequeue(thread_id);
+        self.levels.remove(&thread_id);
+    }
+
+    fn pick_next(&mut self, running: Option<&TCB>) -> Option<ThreadId> {
+        let level = self.highest_ready_level()?;
+        if let Some(running) = running {
+            if self.level_of(running.id) < level {
+                return None;
+            }
+        }
+        self.ready[level].pop_front()
+    }
+
+    fn tick(&mut self, running: &TCB) -> bool {
+        self.ticks_until_boost -= 1;
+        if self.ticks_until_boost == 0 {
+            self.ticks_until_boost = BOOST_INTERVAL;
+            self.boost();
+        }
+
+        let level = self.level_of(running.id);
+        if !self
+            .time_slice
+            .tick(running.id, SCHEDULER_INTERVAL << level)
+        {
+            return false;
+        }
+
+        // used up the whole time slice, demote one level
+        self.levels.insert(running.id, (level + 1).min(LEVELS - 1));
+        true
+    }
+
+    fn should_preempt(&self, woken: &TCB, running: &TCB) -> bool {
+        self.level_of(woken.id) < self.level_of(running.id)
+    }
+}
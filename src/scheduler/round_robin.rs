@@ -0,0 +1,38 @@
+use super::{Scheduler, TimeSlice, SCHEDULER_INTERVAL};
+use crate::threads::{ThreadId, TCB};
+use alloc::collections::vec_deque::VecDeque;
+
+/// Runs all ready threads one after another for a fixed time slice,  
+/// thread priorities are ignored.
+pub(crate) struct RoundRobinScheduler {
+    ready: VecDeque<ThreadId>,
+    time_slice: TimeSlice,
+}
+
+impl RoundRobinScheduler {
+    pub(crate) fn new() -> Self {
+        RoundRobinScheduler {
+            ready: VecDeque::new(),
+            time_slice: TimeSlice::new(),
+        }
+    }
+}
+
+impl Scheduler for RoundRobinScheduler {
+    fn enqueue(&mut self, thread: &TCB) {
+        debug_assert!(!self.ready.contains(&thread.id));
+        self.ready.push_back(thread.id);
+    }
+
+    fn dequeue(&mut self, thread_id: ThreadId) {
+        self.ready.retain(|&id| id != thread_id);
+    }
+
+    fn pick_next(&mut self, _running: Option<&TCB>) -> Option<ThreadId> {
+        self.ready.pop_front()
+    }
+
+    fn tick(&mut self, running: &TCB) -> bool {
+        self.time_slice.tick(running.id, SCHEDULER_INTERVAL)
+    }
+}
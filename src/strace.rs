@@ -0,0 +1,123 @@
+use crate::system_timer;
+use crate::threads::{self, ThreadId};
+use crate::{print, println};
+use alloc::collections::{btree_map::BTreeMap, vec_deque::VecDeque};
+use core::fmt;
+use rost_api::syscalls::{self, SyscallError};
+
+/// Number of records kept per traced thread, older records are dropped first.
+const MAX_RECORDS: usize = 64;
+
+/// A syscall made by a traced thread.
+struct TraceRecord {
+    /// number of the syscall among all traced syscalls of the thread
+    sequence: usize,
+    service_id: usize,
+    arguments: [usize; 3],
+    /// real time at which the syscall entered the kernel
+    start: u32,
+    /// None until the syscall returns, syscalls like `exit_thread` never do
+    result: Option<Result<usize, SyscallError>>,
+    /// real time units spent in the kernel, including waiting
+    duration: u32,
+}
+
+/// The buffered syscalls of one traced thread.
+#[derive(Default)]
+struct ThreadTrace {
+    records: VecDeque<TraceRecord>,
+    dropped: usize,
+    next_sequence: usize,
+}
+
+static mut TRACES: Option<BTreeMap<ThreadId, ThreadTrace>> = None;
+
+fn get_traces<'a>() -> &'a mut BTreeMap<ThreadId, ThreadTrace> {
+    unsafe { TRACES.get_or_insert_with(BTreeMap::new) }
+}
+
+/// Buffers a syscall of a traced thread before it gets dispatched.
+///
+/// Called by `syscall_handler`, so syscalls which never return there are  
+/// recorded as well. Printing from there would slow the traced thread down  
+/// and interleave with its output. Returns the sequence number to pass to `exit()`.
+pub(crate) fn enter(thread_id: ThreadId, service_id: usize, arguments: [usize; 3]) -> usize {
+    let trace = get_traces().entry(thread_id).or_default();
+    if trace.records.len() == MAX_RECORDS {
+        trace.records.pop_front();
+        trace.dropped += 1;
+    }
+    let sequence = trace.next_sequence;
+    trace.next_sequence += 1;
+    trace.records.push_back(TraceRecord {
+        sequence,
+        service_id,
+        arguments,
+        start: system_timer::get_current_real_time(),
+        result: None,
+        duration: 0,
+    });
+    sequence
+}
+
+/// Completes the record of a syscall which returned with `result`.
+///
+/// The record may already be dropped or printed, then the result is discarded.
+pub(crate) fn exit(thread_id: ThreadId, sequence: usize, result: Result<usize, SyscallError>) {
+    if let Some(record) = get_traces().get_mut(&thread_id).and_then(|trace| {
+        trace
+            .records
+            .iter_mut()
+            .rev()
+            .find(|record| record.sequence == sequence)
+    }) {
+        record.result = Some(result);
+        record.duration = system_timer::get_current_real_time().wrapping_sub(record.start);
+    }
+}
+
+/// Decodes the name and arguments of a syscall.
+struct SyscallCall<'a>(&'a TraceRecord);
+
+impl fmt::Display for SyscallCall<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        syscalls::format_syscall(f, self.0.service_id, self.0.arguments)
+    }
+}
+
+/// Prints and clears the buffered syscall records of every thread.
+///
+/// Syscalls which did not return yet are printed without a result.
+pub fn print_records() {
+    let unit = system_timer::get_real_time_unit_interval();
+    let traces = get_traces();
+    for (thread_id, trace) in traces.iter_mut() {
+        let dropped = core::mem::replace(&mut trace.dropped, 0);
+        if dropped > 0 {
+            println!(
+                "-> {} older syscall records of thread {} dropped",
+                dropped, thread_id
+            );
+        }
+        for record in trace.records.drain(..) {
+            print!("  thread {}: {} = ", thread_id, SyscallCall(&record));
+            match record.result {
+                Some(Ok(value)) => {
+                    println!("{} <{}ms>", value, (unit * record.duration).as_millis());
+                }
+                Some(Err(error)) => {
+                    println!(
+                        "Err({:?}) <{}ms>",
+                        error,
+                        (unit * record.duration).as_millis()
+                    );
+                }
+                None => {
+                    println!("? <unfinished>");
+                }
+            }
+        }
+    }
+    // the sequence numbers of running threads stay, they may still be in a syscall
+    traces.retain(|&thread_id, _| threads::get_thread_by_id(thread_id).is_some());
+}
@@ -0,0 +1,448 @@
+use crate::process::ProcessId;
+use crate::threads::{self, ThreadId, ThreadState, TimeoutValue, WaitResult, WaitingReason};
+use alloc::collections::{btree_map::BTreeMap, vec_deque::VecDeque};
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+use rost_api::syscalls::{Priority, SyscallError};
+
+pub(crate) type SyncObjectId = usize;
+
+/// Mutex owned by at most one thread, unlocking hands it directly to the next waiter.
+///
+/// The owner inherits the priority of its highest priority waiter.
+struct Mutex {
+    owner: Option<ThreadId>,
+    waiters: VecDeque<ThreadId>,
+}
+
+/// Counting semaphore, a signal hands the permit directly to the next waiter.
+struct Semaphore {
+    count: usize,
+    waiters: VecDeque<ThreadId>,
+}
+
+/// Condition variable used together with a `Mutex`.
+struct Condvar {
+    waiters: VecDeque<ThreadId>,
+}
+
+enum SyncObject {
+    Mutex(Mutex),
+    Semaphore(Semaphore),
+    Condvar(Condvar),
+}
+
+/// Synchronization object with the process of the thread which created it,  
+/// objects get removed together with their process.
+struct OwnedSyncObject {
+    process_id: ProcessId,
+    object: SyncObject,
+}
+
+static mut SYNC_OBJECTS: Option<BTreeMap<SyncObjectId, OwnedSyncObject>> = None;
+static mut LAST_SYNC_OBJECT_ID: SyncObjectId = 0;
+
+fn get_sync_objects<'a>() -> &'a mut BTreeMap<SyncObjectId, OwnedSyncObject> {
+    unsafe { SYNC_OBJECTS.get_or_insert_with(BTreeMap::new) }
+}
+
+fn get_sync_object<'a>(id: SyncObjectId) -> Option<&'a mut SyncObject> {
+    get_sync_objects()
+        .get_mut(&id)
+        .map(|owned| &mut owned.object)
+}
+
+fn create_sync_object(object: SyncObject) -> SyncObjectId {
+    let process_id = threads::get_current_thread().process_id;
+    unsafe {
+        LAST_SYNC_OBJECT_ID += 1;
+        get_sync_objects().insert(LAST_SYNC_OBJECT_ID, OwnedSyncObject { process_id, object });
+        LAST_SYNC_OBJECT_ID
+    }
+}
+
+fn get_mutex<'a>(id: SyncObjectId) -> Result<&'a mut Mutex, SyscallError> {
+    match get_sync_object(id) {
+        Some(SyncObject::Mutex(mutex)) => Ok(mutex),
+        _ => Err(SyscallError::InvalidSyncObject),
+    }
+}
+
+fn get_semaphore<'a>(id: SyncObjectId) -> Result<&'a mut Semaphore, SyscallError> {
+    match get_sync_object(id) {
+        Some(SyncObject::Semaphore(semaphore)) => Ok(semaphore),
+        _ => Err(SyscallError::InvalidSyncObject),
+    }
+}
+
+fn get_condvar<'a>(id: SyncObjectId) -> Result<&'a mut Condvar, SyscallError> {
+    match get_sync_object(id) {
+        Some(SyncObject::Condvar(condvar)) => Ok(condvar),
+        _ => Err(SyscallError::InvalidSyncObject),
+    }
+}
+
+/// Removes the next thread to wake from `waiters`.
+///
+/// Threads which stopped or timed out in the meantime are skipped.  
+/// Waiters are served by effective priority and in order of arrival  
+/// within the same priority.
+fn pop_next_waiter(waiters: &mut VecDeque<ThreadId>, reason: WaitingReason) -> Option<ThreadId> {
+    let waiting_state = ThreadState::Waiting(reason);
+    waiters.retain(|&thread_id| {
+        threads::get_thread_by_id(thread_id).map_or(false, |thread| thread.state == waiting_state)
+    });
+    let (position, _) = waiters
+        .iter()
+        .enumerate()
+        .max_by_key(|(position, &thread_id)| {
+            (
+                threads::get_thread_by_id(thread_id).unwrap().priority,
+                Reverse(*position),
+            )
+        })?;
+    waiters.remove(position)
+}
+
+/// Wakes the next thread of `waiters` still waiting for the given reason.
+///
+/// Returns the id of the woken thread and whether it should preempt  
+/// the running thread.
+fn wake_next_waiter(
+    waiters: &mut VecDeque<ThreadId>,
+    reason: WaitingReason,
+) -> Option<(ThreadId, bool)> {
+    let thread_id = pop_next_waiter(waiters, reason)?;
+    let thread = threads::get_thread_by_id(thread_id).unwrap();
+    Some((thread_id, threads::wake_thread(thread)))
+}
+
+/// Returns true if one of the `waiters` can still be woken.
+fn has_waiters(waiters: &VecDeque<ThreadId>) -> bool {
+    waiters.iter().any(|&thread_id| {
+        threads::get_thread_by_id(thread_id).map_or(false, |thread| {
+            matches!(thread.state, ThreadState::Waiting(_))
+        })
+    })
+}
+
+/// Returns the waiting threads of any synchronization object.
+fn get_waiters<'a>(id: SyncObjectId) -> Option<&'a mut VecDeque<ThreadId>> {
+    match get_sync_object(id)? {
+        SyncObject::Mutex(mutex) => Some(&mut mutex.waiters),
+        SyncObject::Semaphore(semaphore) => Some(&mut semaphore.waiters),
+        SyncObject::Condvar(condvar) => Some(&mut condvar.waiters),
+    }
+}
+
+/// Blocks the running thread in the queue of a synchronization object.
+///
+/// A thread whose timeout elapsed removes itself from the queue again.  
+/// The object is looked up again after waiting, as other threads may  
+/// create or destroy objects in the meantime. Fails with  
+/// `SyscallError::InvalidSyncObject` if the object got removed.
+fn wait_in_queue(
+    id: SyncObjectId,
+    reason: WaitingReason,
+    timeout: Option<TimeoutValue>,
+) -> Result<(), SyscallError> {
+    let current_thread_id = threads::get_current_thread().id;
+    get_waiters(id)
+        .ok_or(SyscallError::InvalidSyncObject)?
+        .push_back(current_thread_id);
+    match threads::wait(reason, timeout) {
+        WaitResult::Woken => get_waiters(id)
+            .map(|_| ())
+            .ok_or(SyscallError::InvalidSyncObject),
+        WaitResult::TimedOut => {
+            if let Some(waiters) = get_waiters(id) {
+                waiters.retain(|&thread_id| thread_id != current_thread_id);
+            }
+            Err(SyscallError::TimedOut)
+        }
+    }
+}
+
+/// Returns the highest effective priority of the threads waiting for mutexes  
+/// owned by the given thread.
+fn inherited_priority(thread_id: ThreadId) -> Option<Priority> {
+    get_sync_objects()
+        .values()
+        .filter_map(|owned| match &owned.object {
+            SyncObject::Mutex(mutex) if mutex.owner == Some(thread_id) => Some(&mutex.waiters),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|&waiter_id| threads::get_thread_by_id(waiter_id))
+        .map(|waiter| waiter.priority)
+        .max()
+}
+
+/// Recomputes the effective priority of a thread from its base priority  
+/// and the waiters of the mutexes it owns.
+///
+/// A changed priority is passed on transitively to the owner of the mutex  
+/// the thread waits for. Returns true if a thread whose priority changed  
+/// should preempt the running thread.
+pub(crate) fn update_inherited_priority(thread_id: ThreadId) -> bool {
+    let mut preempt = false;
+    let mut thread_id = thread_id;
+    while let Some(thread) = threads::get_thread_by_id(thread_id) {
+        let priority = inherited_priority(thread_id).map_or(thread.base_priority, |priority| {
+            priority.max(thread.base_priority)
+        });
+        if priority == thread.priority {
+            break;
+        }
+        preempt |= threads::set_effective_priority(thread, priority);
+
+        match &thread.state {
+            ThreadState::Waiting(WaitingReason::Mutex(mutex_id)) => {
+                match get_mutex(*mutex_id).ok().and_then(|mutex| mutex.owner) {
+                    Some(owner) => thread_id = owner,
+                    None => break,
+                }
+            }
+            _ => break,
+        }
+    }
+    preempt
+}
+
+/// Removes a thread which stopped waiting for a mutex without getting it  
+/// and undoes the priority its owner inherited from the thread.  
+/// Returns true if the owner should preempt the running thread.
+pub(crate) fn remove_mutex_waiter(mutex_id: SyncObjectId, thread_id: ThreadId) -> bool {
+    match get_mutex(mutex_id) {
+        Ok(mutex) => {
+            mutex.waiters.retain(|&waiter_id| waiter_id != thread_id);
+            mutex.owner.map_or(false, update_inherited_priority)
+        }
+        Err(_) => false,
+    }
+}
+
+/// Hands the mutex to the next waiter or frees it.
+///
+/// The previous owner drops the priority inherited from the waiters of  
+/// the mutex before the new owner gets woken, so the new owner preempts  
+/// it if necessary. Returns true if the new owner should preempt the  
+/// running thread.
+fn release_mutex(mutex_id: SyncObjectId, mutex: &mut Mutex) -> bool {
+    let previous_owner = mutex.owner;
+    mutex.owner = pop_next_waiter(&mut mutex.waiters, WaitingReason::Mutex(mutex_id));
+
+    let mut preempt = previous_owner.map_or(false, update_inherited_priority);
+    if let Some(owner) = mutex.owner {
+        preempt |= threads::wake_thread(threads::get_thread_by_id(owner).unwrap());
+        // the remaining waiters now boost the new owner
+        preempt |= update_inherited_priority(owner);
+    }
+    preempt
+}
+
+pub(crate) fn create_mutex() -> SyncObjectId {
+    create_sync_object(SyncObject::Mutex(Mutex {
+        owner: None,
+        waiters: VecDeque::new(),
+    }))
+}
+
+/// Locks the mutex for the running thread, waits if another thread owns it.
+///
+/// Locking a mutex the running thread already owns is an error  
+/// instead of a deadlock. While waiting, the owner and the owners  
+/// it waits for inherit the priority of the running thread.
+pub(crate) fn lock_mutex(
+    mutex_id: SyncObjectId,
+    timeout: Option<TimeoutValue>,
+) -> Result<(), SyscallError> {
+    let current_thread_id = threads::get_current_thread().id;
+    let mutex = get_mutex(mutex_id)?;
+    match mutex.owner {
+        None => {
+            mutex.owner = Some(current_thread_id);
+            Ok(())
+        }
+        Some(owner) if owner == current_thread_id => Err(SyscallError::InvalidSyncObject),
+        Some(owner) => {
+            mutex.waiters.push_back(current_thread_id);
+            // the owner runs with at least the priority of the running thread
+            update_inherited_priority(owner);
+
+            // the unlocking thread makes the running thread the owner before waking it
+            match threads::wait(WaitingReason::Mutex(mutex_id), timeout) {
+                WaitResult::Woken => get_mutex(mutex_id).map(|_| ()),
+                WaitResult::TimedOut => {
+                    if remove_mutex_waiter(mutex_id, current_thread_id) {
+                        threads::schedule(None);
+                    }
+                    Err(SyscallError::TimedOut)
+                }
+            }
+        }
+    }
+}
+
+/// Unlocks a mutex owned by the running thread.
+pub(crate) fn unlock_mutex(mutex_id: SyncObjectId) -> Result<(), SyscallError> {
+    let mutex = get_mutex(mutex_id)?;
+    if mutex.owner != Some(threads::get_current_thread().id) {
+        return Err(SyscallError::InvalidSyncObject);
+    }
+    if release_mutex(mutex_id, mutex) {
+        threads::schedule(None);
+    }
+    Ok(())
+}
+
+/// Releases every mutex owned by a stopped thread, so waiters do not block forever.  
+/// Returns true if a new owner should preempt the running thread.
+pub(crate) fn release_owned_mutexes(thread_id: ThreadId) -> bool {
+    let mut preempt = false;
+    for (&mutex_id, owned) in get_sync_objects().iter_mut() {
+        if let SyncObject::Mutex(mutex) = &mut owned.object {
+            if mutex.owner == Some(thread_id) {
+                preempt |= release_mutex(mutex_id, mutex);
+            }
+        }
+    }
+    preempt
+}
+
+pub(crate) fn create_semaphore(count: usize) -> SyncObjectId {
+    create_sync_object(SyncObject::Semaphore(Semaphore {
+        count,
+        waiters: VecDeque::new(),
+    }))
+}
+
+/// Takes a permit of the semaphore, waits until one gets available if there is none.
+pub(crate) fn wait_semaphore(
+    semaphore_id: SyncObjectId,
+    timeout: Option<TimeoutValue>,
+) -> Result<(), SyscallError> {
+    let semaphore = get_semaphore(semaphore_id)?;
+    if semaphore.count > 0 {
+        semaphore.count -= 1;
+        return Ok(());
+    }
+    wait_in_queue(
+        semaphore_id,
+        WaitingReason::Semaphore(semaphore_id),
+        timeout,
+    )
+}
+
+/// Returns a permit to the semaphore, which gets handed to the first waiter.
+pub(crate) fn signal_semaphore(semaphore_id: SyncObjectId) -> Result<(), SyscallError> {
+    let semaphore = get_semaphore(semaphore_id)?;
+    match wake_next_waiter(
+        &mut semaphore.waiters,
+        WaitingReason::Semaphore(semaphore_id),
+    ) {
+        Some((_, preempt)) => {
+            if preempt {
+                threads::schedule(None);
+            }
+        }
+        None => semaphore.count = semaphore.count.saturating_add(1),
+    }
+    Ok(())
+}
+
+pub(crate) fn create_condvar() -> SyncObjectId {
+    create_sync_object(SyncObject::Condvar(Condvar {
+        waiters: VecDeque::new(),
+    }))
+}
+
+/// Unlocks the mutex and waits for a signal of the condition variable.
+///
+/// The mutex has to be owned by the running thread and gets locked  
+/// again before returning, also if the timeout elapsed.
+pub(crate) fn wait_condvar(
+    condvar_id: SyncObjectId,
+    mutex_id: SyncObjectId,
+    timeout: Option<TimeoutValue>,
+) -> Result<(), SyscallError> {
+    get_condvar(condvar_id)?;
+    let mutex = get_mutex(mutex_id)?;
+    if mutex.owner != Some(threads::get_current_thread().id) {
+        return Err(SyscallError::InvalidSyncObject);
+    }
+
+    // the new owner runs once this thread waits
+    release_mutex(mutex_id, mutex);
+    let result = wait_in_queue(condvar_id, WaitingReason::Condvar(condvar_id), timeout);
+    lock_mutex(mutex_id, None)?;
+    result
+}
+
+/// Wakes one thread waiting for the condition variable, or all if `broadcast` is set.
+pub(crate) fn signal_condvar(
+    condvar_id: SyncObjectId,
+    broadcast: bool,
+) -> Result<(), SyscallError> {
+    let condvar = get_condvar(condvar_id)?;
+    let mut preempt = false;
+    while let Some((_, woken_preempt)) =
+        wake_next_waiter(&mut condvar.waiters, WaitingReason::Condvar(condvar_id))
+    {
+        preempt |= woken_preempt;
+        if !broadcast {
+            break;
+        }
+    }
+    if preempt {
+        threads::schedule(None);
+    }
+    Ok(())
+}
+
+/// Removes a synchronization object nobody waits for anymore.
+///
+/// Only threads of the process which created the object can destroy it.  
+/// Locked mutexes and objects with waiting threads cannot be destroyed.
+pub(crate) fn destroy_sync_object(id: SyncObjectId) -> Result<(), SyscallError> {
+    let process_id = threads::get_current_thread().process_id;
+    let owned = get_sync_objects()
+        .get(&id)
+        .filter(|owned| owned.process_id == process_id)
+        .ok_or(SyscallError::InvalidSyncObject)?;
+    let in_use = match &owned.object {
+        SyncObject::Mutex(mutex) => mutex.owner.is_some() || has_waiters(&mutex.waiters),
+        SyncObject::Semaphore(semaphore) => has_waiters(&semaphore.waiters),
+        SyncObject::Condvar(condvar) => has_waiters(&condvar.waiters),
+    };
+    if in_use {
+        return Err(SyscallError::InvalidSyncObject);
+    }
+    get_sync_objects().remove(&id);
+    Ok(())
+}
+
+/// Removes every synchronization object created by the threads of a finished process.
+///
+/// Threads of other processes still waiting for one of them get woken and fail  
+/// with `SyscallError::InvalidSyncObject`, owners of removed mutexes drop the  
+/// priority inherited from their waiters. Called by the scheduler, woken  
+/// threads get scheduled by the caller.
+pub(crate) fn release_process_objects(process_id: ProcessId) {
+    let ids: Vec<SyncObjectId> = get_sync_objects()
+        .iter()
+        .filter(|(_, owned)| owned.process_id == process_id)
+        .map(|(&id, _)| id)
+        .collect();
+    for id in ids {
+        let (reason, owner) = match get_sync_objects().remove(&id).unwrap().object {
+            SyncObject::Mutex(mutex) => (WaitingReason::Mutex(id), mutex.owner),
+            SyncObject::Semaphore(_) => (WaitingReason::Semaphore(id), None),
+            SyncObject::Condvar(_) => (WaitingReason::Condvar(id), None),
+        };
+        threads::wake_waiting_threads(&reason);
+        if let Some(owner) = owner {
+            update_inherited_priority(owner);
+        }
+    }
+}
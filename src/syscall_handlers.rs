@@ -1,214 +1,554 @@
-use crate::{system_timer, threads};
-use alloc::boxed::Box;
-use core::{alloc::Layout, convert::TryFrom};
+use crate::user_ptr::{UserData, UserPtr, UserSlice};
+use crate::{
+    allocator, pipe, process, strace, sync, system_timer, threads, timer_service, user_heap,
+};
+use core::alloc::Layout;
+use core::convert::TryFrom;
+use core::ffi::c_void;
 use log::trace;
 use rost_api::syscalls;
-use rost_api::syscalls::Syscalls;
+use rost_api::syscalls::{SyscallError, SyscallHandler};
 use threads::ThreadState;
 
-fn yield_thread() -> usize {
-    trace!("syscall: YieldThread");
-    super::threads::schedule(None);
-    0
+/// Result of a syscall handler, encoded into the return register by `syscall_handler`.
+type SyscallResult = Result<usize, SyscallError>;
+
+/// Wraps the entry function and argument passed by a user thread into a thread entry.
+fn thread_entry(entry: syscalls::ThreadEntry, arg: *mut c_void) -> threads::ThreadEntry {
+    threads::ThreadEntry {
+        function: entry,
+        arg,
+    }
 }
 
-fn create_thread(fat_pointer_part1: usize, fat_pointer_part2: usize) -> usize {
-    trace!("syscall: CreateThread");
-    unsafe {
-        let raw_entry: *mut dyn FnMut() =
-            core::mem::transmute((fat_pointer_part1, fat_pointer_part2));
-        let entry = Box::<dyn FnMut() + 'static>::from_raw(raw_entry);
-        super::threads::create_thread_internal(entry)
+/// Converts a relative time in milliseconds into a real time timestamp which is  
+/// at least `time_ms` away. The current unit may be almost over, so it does not count.  
+/// A time of zero gives the current timestamp, which is over already.
+fn timestamp_from_ms(time_ms: usize) -> threads::TimeoutValue {
+    let current_time = system_timer::get_current_real_time() as usize;
+    if time_ms == 0 {
+        return current_time;
     }
+    let unit = system_timer::get_real_time_unit_interval().as_millis() as usize;
+    let units = time_ms / unit + (time_ms % unit != 0) as usize;
+    current_time.saturating_add(units + 1)
 }
 
-fn exit_thread() -> usize {
-    trace!("syscall: ExitThread");
-    super::threads::exit_internal();
-    0
+/// Converts a relative timeout in milliseconds into a real time timestamp,  
+/// `syscalls::NO_TIMEOUT` means waiting without a timeout.
+fn timeout_from_ms(timeout_ms: usize) -> Option<threads::TimeoutValue> {
+    if timeout_ms == syscalls::NO_TIMEOUT {
+        return None;
+    }
+    Some(timestamp_from_ms(timeout_ms))
 }
 
-fn receive_dbgu(blocking: bool) -> usize {
-    trace!("syscall: ReceiveDBGU");
-    let current_tcb = threads::get_current_thread();
-    match current_tcb
+/// Takes the next message of a service subscribed by the current thread.
+///
+/// A blocking receive waits for the next message, at most until the timeout  
+/// elapsed. Fails with `SyscallError::WouldBlock` if a non-blocking receive  
+/// finds no message.
+fn receive_service_message(
+    service: syscalls::ThreadServices,
+    reason: threads::WaitingReason,
+    blocking: bool,
+    timeout: Option<threads::TimeoutValue>,
+) -> Result<threads::ThreadMessage, SyscallError> {
+    let messages = threads::get_current_thread()
         .subscribed_services
-        .get_mut(&syscalls::ThreadServices::DBGU)
-    {
-        Some(messages) => {
-            if let Some(threads::ThreadMessage::DBGU(character)) = messages.pop_front() {
-                return character as usize;
+        .get_mut(&service)
+        .ok_or(SyscallError::NotSubscribed)?;
+    if let Some(message) = messages.pop_front() {
+        return Ok(message);
+    }
+
+    if !blocking {
+        return Err(SyscallError::WouldBlock);
+    }
+    if threads::wait(reason, timeout) == threads::WaitResult::TimedOut {
+        return Err(SyscallError::TimedOut);
+    }
+
+    let message = threads::get_current_thread()
+        .subscribed_services
+        .get_mut(&service)
+        .and_then(|messages| messages.pop_front());
+    message.ok_or(SyscallError::WouldBlock)
+}
+
+/// Reads a pipe name passed by a user thread.
+fn pipe_name<'a>(name: *const u8, length: usize) -> Result<&'a str, SyscallError> {
+    core::str::from_utf8(UserSlice::new(name, length).as_slice()?)
+        .map_err(|_| SyscallError::InvalidName)
+}
+
+/// `syscalls::Event` as passed by a user thread, whose kind is checked before use.
+#[repr(C)]
+struct RawEvent {
+    kind: u32,
+    id: usize,
+}
+
+unsafe impl UserData for RawEvent {}
+
+/// The kernel side of the syscalls, called by `syscalls::dispatch`.
+struct KernelSyscalls;
+
+impl SyscallHandler for KernelSyscalls {
+    fn yield_thread() -> SyscallResult {
+        trace!("syscall: YieldThread");
+        super::threads::schedule(None);
+        Ok(0)
+    }
+
+    fn create_thread(
+        entry: syscalls::ThreadEntry,
+        arg: *mut c_void,
+        stack_size: usize,
+    ) -> SyscallResult {
+        trace!("syscall: CreateThread");
+        super::threads::create_thread_internal(thread_entry(entry, arg), None, stack_size)
+    }
+
+    fn create_periodic_thread(
+        entry: syscalls::ThreadEntry,
+        arg: *mut c_void,
+        parameters: *const syscalls::PeriodicParameters,
+    ) -> SyscallResult {
+        trace!("syscall: CreatePeriodicThread");
+        let parameters = UserPtr::new(parameters).read()?;
+        let unit_ms = system_timer::get_real_time_unit_interval().as_millis() as usize;
+        if parameters.deadline_ms < unit_ms || parameters.deadline_ms > parameters.period_ms {
+            return Err(SyscallError::InvalidArgument);
+        }
+        let periodic = threads::Periodic {
+            period: parameters.period_ms / unit_ms,
+            relative_deadline: parameters.deadline_ms / unit_ms,
+            release: system_timer::get_current_real_time() as usize,
+            deadline_misses: 0,
+        };
+        super::threads::create_thread_internal(thread_entry(entry, arg), Some(periodic), 0)
+    }
+
+    fn wait_next_period() -> SyscallResult {
+        trace!("syscall: WaitNextPeriod");
+        threads::wait_next_period()
+    }
+
+    fn exit_thread(exit_code: usize) -> SyscallResult {
+        trace!("syscall: ExitThread");
+        super::threads::exit_internal(exit_code.min(syscalls::MAX_EXIT_CODE));
+        Ok(0)
+    }
+
+    fn receive_dbgu(blocking: bool, timeout_ms: usize) -> SyscallResult {
+        trace!("syscall: ReceiveDBGU");
+        match receive_service_message(
+            syscalls::ThreadServices::DBGU,
+            threads::WaitingReason::DBGU,
+            blocking,
+            timeout_from_ms(timeout_ms),
+        )? {
+            threads::ThreadMessage::DBGU(character) => Ok(character as usize),
+            message => panic!("syscall ReceiveDBGU: unexpected message {:?}", message),
+        }
+    }
+
+    fn send_dbgu(character: u8) -> SyscallResult {
+        trace!("syscall: SendDBGU");
+        super::dbgu::write_char(character as char);
+        Ok(0)
+    }
+
+    fn write_dbgu(data: *const u8, length: usize) -> SyscallResult {
+        trace!("syscall: WriteDBGU");
+        let data = UserSlice::new(data, length).as_slice()?;
+        let data = &data[..length.min(syscalls::MAX_DBGU_WRITE_SIZE)];
+        // interrupts stay disabled during a syscall, so no other thread can print in between
+        super::dbgu::write_bytes(data);
+        Ok(data.len())
+    }
+
+    fn read_dbgu(
+        transfer: *const syscalls::TransferParameters,
+        mode: syscalls::ReadMode,
+    ) -> SyscallResult {
+        trace!("syscall: ReadDBGU");
+        let transfer = UserPtr::new(transfer).read()?;
+        let buffer = UserSlice::new_mut(transfer.buffer, transfer.length).as_mut_slice()?;
+        // one deadline for the whole read, not one per character
+        let timeout = timeout_from_ms(transfer.timeout_ms);
+        let mut read = 0;
+        while read < buffer.len() {
+            let blocking = match mode {
+                syscalls::ReadMode::NonBlocking => false,
+                syscalls::ReadMode::Available => read == 0,
+                syscalls::ReadMode::Full => true,
+            };
+            match receive_service_message(
+                syscalls::ThreadServices::DBGU,
+                threads::WaitingReason::DBGU,
+                blocking,
+                timeout,
+            ) {
+                Ok(threads::ThreadMessage::DBGU(character)) => {
+                    buffer[read] = character as u8;
+                    read += 1;
+                }
+                Ok(message) => panic!("syscall ReadDBGU: unexpected message {:?}", message),
+                Err(SyscallError::WouldBlock) | Err(SyscallError::TimedOut) if read > 0 => break,
+                Err(error) => return Err(error),
             }
         }
-        _ => {
-            panic!(
-                "syscall DBGU: Service {:?} not subscribed",
-                syscalls::ThreadServices::DBGU
-            );
+        Ok(read)
+    }
+
+    fn print_kernel_info(info: syscalls::KernelInfo) -> SyscallResult {
+        trace!("syscall: PrintKernelInfo");
+        match info {
+            syscalls::KernelInfo::Threads => threads::print_threads(),
+            syscalls::KernelInfo::HeapSize => {
+                crate::println!(
+                    "current heap size: {:#X}, left: {:#X}",
+                    allocator::get_current_heap_size(),
+                    allocator::get_heap_size_left()
+                );
+                user_heap::print_user_heap_usage();
+            }
+            syscalls::KernelInfo::HeapStats => allocator::print_heap_stats(),
+            syscalls::KernelInfo::SyscallTrace => strace::print_records(),
+            syscalls::KernelInfo::Processes => process::print_processes(),
         }
+        Ok(0)
+    }
+
+    fn allocate(size: usize, align: usize) -> SyscallResult {
+        trace!("syscall: Allocate");
+        let layout =
+            Layout::from_size_align(size, align).map_err(|_| SyscallError::InvalidArgument)?;
+        user_heap::allocate(layout).map(|ptr| ptr as usize)
     }
 
-    if blocking {
-        current_tcb.state = threads::ThreadState::Waiting(threads::WaitingReason::DBGU);
-        threads::schedule(None);
+    fn deallocate(ptr: *mut u8, size: usize, align: usize) -> SyscallResult {
+        trace!("syscall: Deallocate");
+        let layout =
+            Layout::from_size_align(size, align).map_err(|_| SyscallError::InvalidArgument)?;
+        user_heap::deallocate(ptr, layout).map(|()| 0)
+    }
+
+    fn set_heap_quota(thread_id: usize, quota: usize) -> SyscallResult {
+        trace!("syscall: SetHeapQuota");
+        threads::set_heap_quota(thread_id, quota).map(|()| 0)
+    }
+
+    fn get_current_real_time() -> SyscallResult {
+        trace!("syscall: GetCurrentRealTime");
+        Ok(system_timer::get_current_real_time() as usize)
+    }
+
+    fn subscribe(service: syscalls::ThreadServices) -> SyscallResult {
+        trace!("syscall: Subscribe");
+        let current_tcb = threads::get_current_thread();
+
+        if current_tcb.subscribed_services.contains_key(&service) {
+            return Err(SyscallError::AlreadySubscribed);
+        }
+        current_tcb
+            .subscribed_services
+            .insert(service, alloc::collections::VecDeque::new());
+        Ok(0)
+    }
 
+    fn unsubscribe(service: syscalls::ThreadServices) -> SyscallResult {
+        trace!("syscall: Unsubscribe");
         let current_tcb = threads::get_current_thread();
-        if let Some(messages) = current_tcb
+
+        if current_tcb.subscribed_services.remove(&service) == None {
+            return Err(SyscallError::NotSubscribed);
+        }
+        if service == syscalls::ThreadServices::Timer {
+            timer_service::cancel_thread_timers(current_tcb.id);
+        }
+        Ok(0)
+    }
+
+    fn create_timer(interval_ms: usize, periodic: bool) -> SyscallResult {
+        trace!("syscall: CreateTimer");
+        if !threads::get_current_thread()
             .subscribed_services
-            .get_mut(&syscalls::ThreadServices::DBGU)
+            .contains_key(&syscalls::ThreadServices::Timer)
         {
-            if let Some(threads::ThreadMessage::DBGU(character)) = messages.pop_front() {
-                return character as usize;
+            return Err(SyscallError::NotSubscribed);
+        }
+        if interval_ms == 0 {
+            return Err(SyscallError::InvalidArgument);
+        }
+        // round up, the timer never expires earlier than requested
+        let unit_ms = system_timer::get_real_time_unit_interval().as_millis() as usize;
+        let interval = interval_ms / unit_ms + (interval_ms % unit_ms != 0) as usize;
+        Ok(timer_service::create_timer(interval, periodic))
+    }
+
+    fn cancel_timer(timer_id: usize) -> SyscallResult {
+        trace!("syscall: CancelTimer");
+        if !timer_service::cancel_timer(timer_id) {
+            return Err(SyscallError::UnknownTimer);
+        }
+        Ok(0)
+    }
+
+    fn receive_timer(blocking: bool, timeout_ms: usize) -> SyscallResult {
+        trace!("syscall: ReceiveTimer");
+        match receive_service_message(
+            syscalls::ThreadServices::Timer,
+            threads::WaitingReason::Timer,
+            blocking,
+            timeout_from_ms(timeout_ms),
+        )? {
+            threads::ThreadMessage::Timer(timer_id) => Ok(timer_id),
+            message => panic!("syscall ReceiveTimer: unexpected message {:?}", message),
+        }
+    }
+
+    fn get_uptime() -> SyscallResult {
+        trace!("syscall: GetUptime");
+        Ok(system_timer::get_current_real_time_as_duration().as_millis() as usize)
+    }
+
+    fn sleep(time_ms: usize) -> SyscallResult {
+        trace!("syscall: Sleep");
+        let current_time = system_timer::get_current_real_time() as usize;
+
+        threads::wait(
+            threads::WaitingReason::Sleep,
+            Some(timestamp_from_ms(time_ms)),
+        );
+
+        Ok(
+            system_timer::get_real_time_unit_interval().as_millis() as usize
+                * (system_timer::get_current_real_time() as usize - current_time as usize),
+        )
+    }
+
+    fn join_thread(thread_id: usize, timeout_ms: usize) -> SyscallResult {
+        trace!("syscall: JoinThread");
+        let join_thread = threads::get_joinable_thread(thread_id)?;
+
+        if join_thread.state != ThreadState::Stopped {
+            let mut joined_thread_ids = alloc::collections::btree_set::BTreeSet::new();
+            joined_thread_ids.insert(thread_id);
+
+            if threads::wait(
+                threads::WaitingReason::Join(joined_thread_ids),
+                timeout_from_ms(timeout_ms),
+            ) == threads::WaitResult::TimedOut
+            {
+                return Err(SyscallError::TimedOut);
             }
         }
-        panic!("should always be a character saved from the interrupt");
-    } else {
-        0xFFFF
+
+        Ok(threads::reap_thread(thread_id))
     }
-}
 
-fn send_dbgu(character: char) -> usize {
-    trace!("syscall: SendDBGU");
-    super::dbgu::write_char(character);
-    0
-}
+    fn kill_thread(thread_id: usize) -> SyscallResult {
+        trace!("syscall: KillThread");
+        threads::kill_thread(thread_id).map(|()| 0)
+    }
 
-fn allocate(size: usize, align: usize) -> usize {
-    trace!("syscall: Allocate");
-    let layout = Layout::from_size_align(size, align).expect("Bad layout");
-    unsafe { alloc::alloc::alloc(layout) as usize }
-}
+    fn suspend_thread(thread_id: usize) -> SyscallResult {
+        trace!("syscall: SuspendThread");
+        threads::suspend_thread(thread_id).map(|()| 0)
+    }
 
-fn deallocate(ptr: *mut u8, size: usize, align: usize) -> usize {
-    trace!("syscall: Deallocate");
-    let layout = Layout::from_size_align(size, align).expect("Bad layout");
+    fn resume_thread(thread_id: usize) -> SyscallResult {
+        trace!("syscall: ResumeThread");
+        threads::resume_thread(thread_id).map(|()| 0)
+    }
 
-    unsafe {
-        alloc::alloc::dealloc(ptr, layout);
+    fn trace_thread(thread_id: usize, enabled: bool) -> SyscallResult {
+        trace!("syscall: TraceThread");
+        threads::trace_thread(thread_id, enabled).map(|()| 0)
     }
-    0
-}
 
-fn get_current_real_time() -> usize {
-    trace!("syscall: GetCurrentRealTime");
-    system_timer::get_current_real_time() as usize
-}
+    fn spawn_process(image: *const u8, length: usize) -> SyscallResult {
+        trace!("syscall: SpawnProcess");
+        let image = UserSlice::new(image, length).as_slice()?;
+        process::spawn_process(image)
+    }
 
-fn subscribe_thread_service(service_id: usize) -> usize {
-    trace!("syscall: Subscribe");
-    let current_tcb = threads::get_current_thread();
-    let service = rost_api::syscalls::ThreadServices::try_from(service_id as u32)
-        .expect("invalid service given");
+    fn set_priority(thread_id: usize, priority: syscalls::Priority) -> SyscallResult {
+        trace!("syscall: SetPriority");
+        threads::set_priority(thread_id, priority).map(|()| 0)
+    }
 
-    if current_tcb
-        .subscribed_services
-        .insert(service, alloc::collections::VecDeque::new())
-        != None
-    {
-        panic!(
-            "syscall Subscribe: Service {:?} already subscribed",
-            service
-        );
+    fn get_priority(thread_id: usize) -> SyscallResult {
+        trace!("syscall: GetPriority");
+        threads::get_priority(thread_id).map(|priority| priority as usize)
     }
-    0
-}
 
-fn unsubscribe_thread_service(service_id: usize) -> usize {
-    trace!("syscall: Unsubscribe");
-    let current_tcb = threads::get_current_thread();
-    let service = rost_api::syscalls::ThreadServices::try_from(service_id as u32)
-        .expect("invalid service given");
+    fn create_mutex() -> SyscallResult {
+        trace!("syscall: CreateMutex");
+        Ok(sync::create_mutex())
+    }
 
-    if current_tcb.subscribed_services.remove(&service) == None {
-        panic!(
-            "syscall Unsubscribe: Service {:?} was not subscribed",
-            service
-        );
+    fn lock_mutex(mutex_id: usize, timeout_ms: usize) -> SyscallResult {
+        trace!("syscall: LockMutex");
+        sync::lock_mutex(mutex_id, timeout_from_ms(timeout_ms)).map(|()| 0)
     }
-    0
-}
 
-fn sleep(time_ms: usize) -> usize {
-    trace!("syscall: Sleep");
-    let current_time = system_timer::get_current_real_time() as usize;
-    let current_tcb = threads::get_current_thread();
+    fn unlock_mutex(mutex_id: usize) -> SyscallResult {
+        trace!("syscall: UnlockMutex");
+        sync::unlock_mutex(mutex_id).map(|()| 0)
+    }
 
-    let time_in_realtime_units: usize =
-        time_ms / system_timer::get_real_time_unit_interval().as_millis() as usize;
+    fn create_semaphore(count: usize) -> SyscallResult {
+        trace!("syscall: CreateSemaphore");
+        Ok(sync::create_semaphore(count))
+    }
 
-    current_tcb.state = threads::ThreadState::Waiting(threads::WaitingReason::Sleep(
-        current_time + time_in_realtime_units,
-    ));
+    fn wait_semaphore(semaphore_id: usize, timeout_ms: usize) -> SyscallResult {
+        trace!("syscall: WaitSemaphore");
+        sync::wait_semaphore(semaphore_id, timeout_from_ms(timeout_ms)).map(|()| 0)
+    }
 
-    threads::schedule(None);
+    fn signal_semaphore(semaphore_id: usize) -> SyscallResult {
+        trace!("syscall: SignalSemaphore");
+        sync::signal_semaphore(semaphore_id).map(|()| 0)
+    }
 
-    system_timer::get_real_time_unit_interval().as_millis() as usize
-        * (system_timer::get_current_real_time() as usize - current_time as usize)
-}
+    fn create_condvar() -> SyscallResult {
+        trace!("syscall: CreateCondvar");
+        Ok(sync::create_condvar())
+    }
 
-fn join_thread(thread_id: usize, timeout_ms: usize) -> usize {
-    trace!("syscall: JoinThread");
-    let join_thread = {
-        let thread = threads::get_thread_by_id(thread_id);
-        if threads::get_thread_by_id(thread_id).is_none() {
-            return 0;
-        }
-        thread.unwrap()
-    };
+    fn wait_condvar(condvar_id: usize, mutex_id: usize, timeout_ms: usize) -> SyscallResult {
+        trace!("syscall: WaitCondvar");
+        sync::wait_condvar(condvar_id, mutex_id, timeout_from_ms(timeout_ms)).map(|()| 0)
+    }
 
-    if join_thread.state == ThreadState::Stopped {
-        return 0;
+    fn signal_condvar(condvar_id: usize) -> SyscallResult {
+        trace!("syscall: SignalCondvar");
+        sync::signal_condvar(condvar_id, false).map(|()| 0)
     }
 
-    let current_tcb = threads::get_current_thread();
+    fn broadcast_condvar(condvar_id: usize) -> SyscallResult {
+        trace!("syscall: BroadcastCondvar");
+        sync::signal_condvar(condvar_id, true).map(|()| 0)
+    }
 
-    if join_thread.parent_thread_id != current_tcb.id {
-        panic!(
-            "JoinThread: you cannot join a thread which is not your parent thread: p: {} != {}",
-            join_thread.parent_thread_id, current_tcb.id
-        );
+    fn destroy_sync_object(id: usize) -> SyscallResult {
+        trace!("syscall: DestroySyncObject");
+        sync::destroy_sync_object(id).map(|()| 0)
     }
 
-    if let threads::ThreadState::Waiting(threads::WaitingReason::Join(joined_thread_ids, _)) =
-        &mut current_tcb.state
-    {
-        joined_thread_ids.insert(thread_id);
-    } else {
-        let timeout = {
-            if timeout_ms > 0 {
-                let current_time = system_timer::get_current_real_time() as usize;
-                Some(current_time + timeout_ms)
-            } else {
-                None
-            }
-        };
-        let mut joined_thread_ids = alloc::collections::btree_set::BTreeSet::new();
-        joined_thread_ids.insert(thread_id);
-        current_tcb.state =
-            threads::ThreadState::Waiting(threads::WaitingReason::Join(joined_thread_ids, timeout));
+    fn send_message(thread_id: usize, data: *const u8, length: usize) -> SyscallResult {
+        trace!("syscall: SendMessage");
+        let data = UserSlice::new(data, length).as_slice()?;
+        threads::send_message(thread_id, data).map(|()| 0)
     }
 
-    threads::schedule(None);
-    0
+    fn receive_message(buffer: *mut u8, length: usize, timeout_ms: usize) -> SyscallResult {
+        trace!("syscall: ReceiveMessage");
+        let buffer = UserSlice::new_mut(buffer, length).as_mut_slice()?;
+        threads::receive_message(buffer, timeout_from_ms(timeout_ms))
+    }
+
+    fn create_pipe(name: *const u8, length: usize) -> SyscallResult {
+        trace!("syscall: CreatePipe");
+        pipe_name(name, length).and_then(pipe::create_pipe)
+    }
+
+    fn open_pipe(name: *const u8, length: usize) -> SyscallResult {
+        trace!("syscall: OpenPipe");
+        pipe_name(name, length).and_then(pipe::open_pipe)
+    }
+
+    fn read_pipe(pipe_id: usize, transfer: *const syscalls::TransferParameters) -> SyscallResult {
+        trace!("syscall: ReadPipe");
+        let transfer = UserPtr::new(transfer).read()?;
+        let buffer = UserSlice::new_mut(transfer.buffer, transfer.length).as_mut_slice()?;
+        pipe::read_pipe(pipe_id, buffer, timeout_from_ms(transfer.timeout_ms))
+    }
+
+    fn write_pipe(pipe_id: usize, transfer: *const syscalls::TransferParameters) -> SyscallResult {
+        trace!("syscall: WritePipe");
+        let transfer = UserPtr::new(transfer).read()?;
+        let data = UserSlice::new(transfer.buffer, transfer.length).as_slice()?;
+        pipe::write_pipe(pipe_id, data, timeout_from_ms(transfer.timeout_ms))
+    }
+
+    fn close_pipe(pipe_id: usize) -> SyscallResult {
+        trace!("syscall: ClosePipe");
+        pipe::close_pipe(pipe_id).map(|()| 0)
+    }
+
+    fn wait_any(events: *const syscalls::Event, count: usize, timeout_ms: usize) -> SyscallResult {
+        trace!("syscall: WaitAny");
+        if count == 0 || count > syscalls::MAX_WAIT_EVENTS {
+            return Err(SyscallError::InvalidArgument);
+        }
+        let events = UserSlice::new(events as *const RawEvent, count).as_slice()?;
+        let reasons = events
+            .iter()
+            .map(|event| {
+                let kind = syscalls::EventKind::try_from(event.kind)
+                    .map_err(|_| SyscallError::InvalidArgument)?;
+                Ok(match kind {
+                    syscalls::EventKind::DBGU => threads::WaitingReason::DBGU,
+                    syscalls::EventKind::ThreadExit => {
+                        let mut joined_thread_ids = alloc::collections::btree_set::BTreeSet::new();
+                        joined_thread_ids.insert(event.id);
+                        threads::WaitingReason::Join(joined_thread_ids)
+                    }
+                    syscalls::EventKind::Message => threads::WaitingReason::Message,
+                    syscalls::EventKind::PipeReadable => threads::WaitingReason::PipeRead(event.id),
+                    syscalls::EventKind::Timer => threads::WaitingReason::Timer,
+                })
+            })
+            .collect::<Result<_, SyscallError>>()?;
+        threads::wait_any(reasons, timeout_from_ms(timeout_ms))
+    }
+
+    fn register_signal_handler(
+        signal: syscalls::Signal,
+        handler: Option<syscalls::SignalHandler>,
+    ) -> SyscallResult {
+        trace!("syscall: RegisterSignalHandler");
+        threads::register_signal_handler(signal, handler);
+        Ok(0)
+    }
+
+    fn send_signal(thread_id: usize, signal: syscalls::Signal) -> SyscallResult {
+        trace!("syscall: SendSignal");
+        threads::send_signal(thread_id, signal).map(|delivered| delivered as usize)
+    }
+
+    fn signal_return() -> SyscallResult {
+        trace!("syscall: SignalReturn");
+        threads::signal_return().map(|()| 0)
+    }
 }
 
+/// Dispatches a syscall and encodes its result for the return register.
+///
+/// Errors are returned to the calling thread as `SyscallError`, the kernel  
+/// only panics for broken invariants of its own.
 pub fn syscall_handler(arg0: usize, arg1: usize, arg2: usize, service_id: usize) -> usize {
-    match Syscalls::try_from(service_id as u32) {
-        Ok(Syscalls::YieldThread) => yield_thread(),
-        Ok(Syscalls::CreateThread) => create_thread(arg0, arg1),
-        Ok(Syscalls::ExitThread) => exit_thread(),
-        Ok(Syscalls::ReceiveDBGU) => receive_dbgu(arg0 != 0),
-        Ok(Syscalls::SendDBGU) => send_dbgu(arg0 as u8 as char),
-        Ok(Syscalls::Allocate) => allocate(arg0, arg1),
-        Ok(Syscalls::Deallocate) => deallocate(arg0 as *mut u8, arg1, arg2),
-        Ok(Syscalls::GetCurrentRealTime) => get_current_real_time(),
-        Ok(Syscalls::Subscribe) => subscribe_thread_service(arg0),
-        Ok(Syscalls::Unsubscribe) => unsubscribe_thread_service(arg0),
-        Ok(Syscalls::Sleep) => sleep(arg0),
-        Ok(Syscalls::JoinThread) => join_thread(arg0, arg1),
-        _ => {
-            log::error!("unknown syscall id {}", service_id);
-            panic!()
-        }
+    let current_thread = threads::get_current_thread();
+    let thread_id = current_thread.id;
+    // recorded before the dispatch, syscalls like `exit_thread` never return here
+    let trace_sequence = if current_thread.traced {
+        Some(strace::enter(thread_id, service_id, [arg0, arg1, arg2]))
+    } else {
+        None
+    };
+
+    let result = syscalls::dispatch::<KernelSyscalls>(service_id, [arg0, arg1, arg2]);
+    if result == Err(SyscallError::UnknownSyscall) {
+        log::warn!("unknown syscall id {}", service_id);
+    }
+    if let Some(sequence) = trace_sequence {
+        strace::exit(thread_id, sequence, result);
     }
+    syscalls::encode_result(result)
 }
@@ -1,63 +1,272 @@
+use crate::pipe::PipeId;
+use crate::process::{self, ProcessId};
+use crate::scheduler::{self, ActiveScheduler, Scheduler};
+use crate::sync::{self, SyncObjectId};
 use crate::system_timer;
+use crate::timer_queue::TimerQueue;
+use crate::timer_service::{self, TimerId};
+use crate::user_heap;
 
 use super::processor;
 use crate::alloc::borrow::ToOwned;
-use alloc::{alloc::alloc, alloc::dealloc, boxed::Box};
 use alloc::{
-    collections::btree_map::BTreeMap, collections::btree_set::BTreeSet,
-    collections::vec_deque::VecDeque, vec::Vec,
+    boxed::Box, collections::btree_map::BTreeMap, collections::btree_set::BTreeSet,
+    collections::vec_deque::VecDeque, vec, vec::Vec,
 };
-use core::{alloc::Layout, panic};
+use core::{alloc::Layout, ffi::c_void, panic};
 use log::trace;
+use rost_api::syscalls::{Signal, SignalHandler, SyscallError};
 
+/// Stack size of threads created without a specific stack size.
 const THREAD_STACK_SIZE: usize = 1024 * 8;
-const IDLE_THREAD_ID: ThreadId = 0;
-/// The amount of SysTicks before the scheduler gets called.
-pub(crate) static SCHEDULER_INTERVAL: u32 = 5;
-pub(crate) static mut SCHEDULER_INTERVAL_COUNTER: u32 = 0;
+const MIN_THREAD_STACK_SIZE: usize = 1024;
+const MAX_THREAD_STACK_SIZE: usize = 1024 * 1024;
+/// Unused stack memory is filled with this pattern to find the high-water mark.
+const STACK_PAINT: u32 = 0xDEAD_BEEF;
+const KERNEL_STACK_PAINT: u64 = (STACK_PAINT as u64) << 32 | STACK_PAINT as u64;
+/// Size of the guard region at the bottom of each stack, which has to keep the  
+/// `STACK_PAINT` pattern. Checked on every context switch to detect overflows.
+const STACK_GUARD_SIZE: usize = 64;
+/// Size of the context popped by `switch_thread()`, CPSR, LR and r0-r12.
+const CONTEXT_SIZE: usize = 15 * 4;
+/// Size of the stack the kernel runs on for a thread, see `TCB::kernel_stack`.
+const KERNEL_STACK_SIZE: usize = 1024 * 8;
+pub(crate) const IDLE_THREAD_ID: ThreadId = 0;
+/// The priority of the idle thread, below every priority a user thread can request.
+const IDLE_THREAD_PRIORITY: Priority = 0;
 
 #[repr(C, align(4))]
 pub struct TCB {
     pub id: ThreadId,
+    /// process owning the thread, its stack and its allocations
+    pub(crate) process_id: ProcessId,
     pub(crate) state: ThreadState,
-    entry: Box<dyn FnMut() + 'static>,
+    entry: Option<ThreadEntry>,
+    /// stack pointer saved by `switch_thread()`, points into the kernel stack
     stack_current: *mut u8,
+    /// user stack on the heap of the process
     stack_start: *mut u8,
+    stack_size: usize,
+    /// stack on the kernel heap for the exceptions of the thread, the saved  
+    /// contexts stay out of reach of the other threads of the process
+    kernel_stack: Option<Box<[u64]>>,
     pub(crate) parent_thread_id: ThreadId,
+    /// set when the thread stopped, kept until the parent joins
+    pub(crate) exit_code: Option<usize>,
+    /// set for threads adopted after their parent stopped, they cannot be joined
+    pub(crate) detached: bool,
+    /// suspended threads are not scheduled even if they are ready
+    pub(crate) suspended: bool,
+    /// syscalls of traced threads are recorded by `strace`
+    pub(crate) traced: bool,
+    /// bytes of the user heap allocated by the thread and not freed yet
+    pub(crate) heap_used: usize,
+    /// limit of `heap_used`, set by the parent with `set_heap_quota`
+    pub(crate) heap_quota: usize,
+    /// effective priority used for scheduling, raised above `base_priority` by  
+    /// priority inheritance while a higher priority thread waits for a mutex of this thread
+    pub(crate) priority: Priority,
+    /// priority set by `set_priority`
+    pub(crate) base_priority: Priority,
+    pub(crate) periodic: Option<Periodic>,
+    /// timestamp at which a waiting thread gets woken regardless of its `WaitingReason`
+    pub(crate) wakeup_timestamp: Option<TimeoutValue>,
+    /// set if the last wait ended because `wakeup_timestamp` elapsed
+    pub(crate) wait_timed_out: bool,
     pub(crate) subscribed_services:
         BTreeMap<rost_api::syscalls::ThreadServices, VecDeque<ThreadMessage>>,
+    /// messages of other threads, at most `MAILBOX_CAPACITY`
+    pub(crate) mailbox: VecDeque<ThreadMessage>,
+    signal_handlers: BTreeMap<Signal, SignalHandler>,
+    /// signals sent to the thread, delivered the next time it gets scheduled
+    pending_signals: BTreeSet<Signal>,
+    /// context interrupted by the running signal handler, continued by `signal_return()`
+    signal_context: Option<*mut u8>,
 }
 
-impl Drop for TCB {
-    fn drop(&mut self) {
-        let layout = Layout::from_size_align(THREAD_STACK_SIZE, core::mem::align_of::<u64>())
+impl TCB {
+    /// Frees the stack of a stopped thread which is not running anymore.
+    fn release_stack(&mut self) {
+        if self.stack_start.is_null() {
+            return;
+        }
+        let layout = Layout::from_size_align(self.stack_size, core::mem::align_of::<u64>())
             .expect("Bad layout");
+        user_heap::deallocate_stack(self.process_id, self.stack_bottom(), layout);
+        self.stack_start = core::ptr::null_mut();
+        self.stack_current = core::ptr::null_mut();
+        self.kernel_stack = None;
+    }
+
+    /// Returns true if the thread waits for `reason`, alone or as part of `WaitingReason::Any`.
+    pub(crate) fn is_waiting_for(&self, reason: &WaitingReason) -> bool {
+        match &self.state {
+            ThreadState::Waiting(WaitingReason::Any(reasons)) => reasons.contains(reason),
+            ThreadState::Waiting(waiting_reason) => waiting_reason == reason,
+            _ => false,
+        }
+    }
+
+    /// Returns the lowest address of the stack, stacks grow downwards.
+    fn stack_bottom(&self) -> *mut u8 {
+        unsafe { self.stack_start.sub(self.stack_size) }
+    }
+
+    /// Returns the address above the kernel stack.
+    fn kernel_stack_start(&self) -> *mut u8 {
+        let kernel_stack = self.kernel_stack.as_ref().expect("kernel stack released");
+        unsafe { kernel_stack.as_ptr().add(kernel_stack.len()) as *mut u8 }
+    }
+
+    /// Returns the kernel stack in use when the thread was switched out the last time.
+    fn kernel_stack_used(&self) -> usize {
+        match self.kernel_stack {
+            Some(_) => self.kernel_stack_start() as usize - self.stack_current as usize,
+            None => 0,
+        }
+    }
+
+    /// Returns the address below which the next exception from user mode saves  
+    /// its context, above it lies the context interrupted by a running signal handler.
+    fn kernel_stack_top(&self) -> *mut u8 {
+        self.signal_context
+            .unwrap_or_else(|| self.kernel_stack_start())
+    }
+
+    /// Returns the stack pointer of the thread in user mode, saved on top of  
+    /// the kernel stack by the last exception from user mode.
+    fn saved_user_stack(&self) -> *mut u8 {
+        if self.entry.is_some() {
+            // not started yet
+            return self.stack_start;
+        }
+        unsafe { *(self.kernel_stack_top() as *const *mut u8).sub(1) }
+    }
+
+    /// Returns the stack as words, starting at the bottom.
+    fn stack_words(&self) -> &[u32] {
         unsafe {
-            dealloc(
-                self.stack_start.offset(-(THREAD_STACK_SIZE as isize)),
-                layout,
+            core::slice::from_raw_parts(self.stack_bottom() as *const u32, self.stack_size / 4)
+        }
+    }
+
+    /// Panics if the guard region at the bottom of the user or kernel stack was overwritten.
+    fn check_stack_guard(&self) {
+        if self.stack_start.is_null() {
+            return;
+        }
+        if self.stack_words()[..STACK_GUARD_SIZE / 4]
+            .iter()
+            .any(|&word| word != STACK_PAINT)
+        {
+            panic!(
+                "stack overflow in thread {}: stack size {:#X} exceeded",
+                self.id, self.stack_size
             );
         }
+        if let Some(kernel_stack) = &self.kernel_stack {
+            if kernel_stack[..STACK_GUARD_SIZE / 8]
+                .iter()
+                .any(|&word| word != KERNEL_STACK_PAINT)
+            {
+                panic!(
+                    "kernel stack overflow in thread {}: stack size {:#X} exceeded",
+                    self.id, KERNEL_STACK_SIZE
+                );
+            }
+        }
+    }
+
+    /// Returns the maximum amount of stack the thread used so far.
+    fn stack_high_water_mark(&self) -> usize {
+        if self.stack_start.is_null() {
+            return 0;
+        }
+        let unused_words = self
+            .stack_words()
+            .iter()
+            .take_while(|&&word| word == STACK_PAINT)
+            .count();
+        self.stack_size - unused_words * 4
+    }
+}
+
+impl Drop for TCB {
+    fn drop(&mut self) {
+        self.release_stack();
     }
 }
 
 pub static mut THREADS: Vec<TCB> = Vec::<TCB>::new();
 static mut RUNNING_THREAD_ID: ThreadId = 0;
+/// `TCB::kernel_stack_top()` of the running thread, where `exception_routine!`  
+/// saves the context of the thread when it interrupts user mode.
+pub(crate) static mut KERNEL_STACK_TOP: *mut u8 = core::ptr::null_mut();
 static mut LAST_THREAD_ID: ThreadId = 0;
+static mut SCHEDULER: Option<ActiveScheduler> = None;
+static mut TIMER_QUEUE: Option<TimerQueue> = None;
+
+pub(crate) type TimeoutValue = usize;
+pub(crate) type ThreadId = usize;
+/// The function run by a thread with its argument, its return value is the thread's exit code.
+///
+/// Plain values instead of a boxed closure, the thread calls the entry in  
+/// `ProcessorMode::User` and cannot access memory of the kernel heap.
+#[derive(Clone, Copy)]
+pub(crate) struct ThreadEntry {
+    pub(crate) function: rost_api::syscalls::ThreadEntry,
+    pub(crate) arg: *mut c_void,
+}
+type Priority = rost_api::syscalls::Priority;
 
-type TimeoutValue = usize;
-type ThreadId = usize;
+/// Timing of a periodic real-time thread, all values in real time units.
+pub(crate) struct Periodic {
+    pub(crate) period: TimeoutValue,
+    /// deadline relative to the release of a period
+    pub(crate) relative_deadline: TimeoutValue,
+    /// start of the current period
+    pub(crate) release: TimeoutValue,
+    pub(crate) deadline_misses: usize,
+}
+
+impl Periodic {
+    /// Returns the absolute deadline of the current period.
+    pub(crate) fn deadline(&self) -> TimeoutValue {
+        self.release + self.relative_deadline
+    }
+}
 
 #[derive(PartialEq, Eq, Debug)]
 pub(crate) enum ThreadMessage {
     DBGU(char),
+    /// expiry of a timer of the `ThreadServices::Timer` service
+    Timer(TimerId),
+    /// message of another thread sent with `send_message`
+    Mailbox(Vec<u8>),
 }
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub(crate) enum WaitingReason {
     DBGU,
-    Sleep(TimeoutValue),
-    Join(BTreeSet<ThreadId>, Option<TimeoutValue>),
+    Timer,
+    Sleep,
+    NextPeriod,
+    Join(BTreeSet<ThreadId>),
+    Message,
+    PipeRead(PipeId),
+    PipeWrite(PipeId),
+    Mutex(SyncObjectId),
+    Semaphore(SyncObjectId),
+    Condvar(SyncObjectId),
+    /// woken by the first of several reasons, see `wait_any()`
+    Any(Vec<WaitingReason>),
+}
+
+/// The way a wait started by `wait()` ended.
+#[derive(PartialEq, Eq, Debug)]
+pub(crate) enum WaitResult {
+    Woken,
+    TimedOut,
 }
 
 #[derive(PartialEq, Eq, Debug)]
@@ -69,17 +278,16 @@ pub(crate) enum ThreadState {
 }
 
 /// Initializes the first thread to run on the processor after boot.
-pub fn init_runtime<F>(entry: F) -> !
-where
-    F: FnMut() + 'static,
-{
+pub fn init_runtime(entry: rost_api::syscalls::ThreadEntry) -> ! {
     assert!(processor::ProcessorMode::System == processor::get_processor_mode());
 
     unsafe {
         THREADS.reserve(24);
+        SCHEDULER = Some(scheduler::create_scheduler());
+        TIMER_QUEUE = Some(TimerQueue::new());
     }
 
-    fn idle_thread() {
+    extern "C" fn idle_thread(_: *mut c_void) -> usize {
         trace!("executing idle thread");
         loop {
             unsafe {
@@ -92,18 +300,39 @@ where
         }
     }
 
-    let id = create_thread_internal(Box::new(idle_thread));
+    let id = create_thread_internal(
+        ThreadEntry {
+            function: idle_thread,
+            arg: core::ptr::null_mut(),
+        },
+        None,
+        THREAD_STACK_SIZE,
+    )
+    .expect("no memory for the idle thread");
     assert!(id == IDLE_THREAD_ID);
+    let idle_thread = get_thread_by_id(id).unwrap();
+    idle_thread.priority = IDLE_THREAD_PRIORITY;
+    idle_thread.base_priority = IDLE_THREAD_PRIORITY;
     assert!(get_thread_by_id(id).unwrap().parent_thread_id == IDLE_THREAD_ID);
 
-    let id = create_thread_internal(Box::new(entry));
+    let id = create_thread_internal(
+        ThreadEntry {
+            function: entry,
+            arg: core::ptr::null_mut(),
+        },
+        None,
+        THREAD_STACK_SIZE,
+    )
+    .expect("no memory for the first thread");
     assert!(id == 1);
     assert!(get_thread_by_id(id).unwrap().parent_thread_id == IDLE_THREAD_ID);
     unsafe {
         RUNNING_THREAD_ID = id;
+        get_scheduler().dequeue(id);
         let thread = get_current_thread();
         thread.state = ThreadState::Running;
-        thread.stack_current = thread.stack_start;
+        thread.stack_current = thread.kernel_stack_start();
+        KERNEL_STACK_TOP = thread.kernel_stack_top();
 
         asm!("mov sp, {stack_address}
               mov pc, {start_address}",
@@ -118,11 +347,27 @@ pub fn print_threads() {
         crate::println!("threads:");
         for thread in &THREADS {
             crate::println!(
-                "  id: {} state: {:?} last_stack_size: {:#X}",
+                "  id: {} process: {} parent: {} state: {:?}{}{} priority: {} effective_priority: {} last_kernel_stack_size: {:#X} stack_high_water: {:#X}/{:#X}",
                 thread.id,
+                thread.process_id,
+                thread.parent_thread_id,
                 thread.state,
-                thread.stack_start.offset_from(thread.stack_current) as u32,
+                if thread.suspended { " (suspended)" } else { "" },
+                if thread.traced { " (traced)" } else { "" },
+                thread.base_priority,
+                thread.priority,
+                thread.kernel_stack_used(),
+                thread.stack_high_water_mark(),
+                thread.stack_size,
             );
+            if let Some(periodic) = &thread.periodic {
+                crate::println!(
+                    "    period: {} deadline: {} deadline_misses: {}",
+                    periodic.period,
+                    periodic.relative_deadline,
+                    periodic.deadline_misses,
+                );
+            }
         }
     }
 }
@@ -142,118 +387,878 @@ pub fn get_thread_by_id<'a>(thread_id: usize) -> Option<&'a mut TCB> {
     unsafe { THREADS.iter_mut().find(|t| t.id == thread_id) }
 }
 
+/// returns the scheduling policy selected at build time
+fn get_scheduler<'a>() -> &'a mut ActiveScheduler {
+    unsafe { SCHEDULER.as_mut().expect("scheduler not initialized") }
+}
+
+/// returns the queue of pending wakeup timestamps
+fn get_timer_queue<'a>() -> &'a mut TimerQueue {
+    unsafe { TIMER_QUEUE.as_mut().expect("timer queue not initialized") }
+}
+
+/// Sets a thread to `ThreadState::Ready` and hands it to the scheduler.
+///
+/// Returns true if the thread should preempt the running thread  
+/// and the caller should call `schedule()`.
+pub(crate) fn wake_thread(thread: &mut TCB) -> bool {
+    thread.state = ThreadState::Ready;
+    if let Some(wakeup_timestamp) = thread.wakeup_timestamp.take() {
+        get_timer_queue().remove(wakeup_timestamp, thread.id);
+    }
+    if thread.id == IDLE_THREAD_ID || thread.suspended {
+        return false;
+    }
+    get_scheduler().enqueue(thread);
+
+    unsafe {
+        match get_thread_by_id(RUNNING_THREAD_ID) {
+            Some(running_thread) => {
+                running_thread.id == IDLE_THREAD_ID
+                    || get_scheduler().should_preempt(thread, running_thread)
+            }
+            None => false,
+        }
+    }
+}
+
+/// Wakes every thread waiting for the given reason.
+///
+/// Returns true if one of them should preempt the running thread  
+/// and the caller should call `schedule()`.
+pub(crate) fn wake_waiting_threads(reason: &WaitingReason) -> bool {
+    let mut preempt = false;
+    unsafe {
+        for thread in THREADS.iter_mut() {
+            if thread.is_waiting_for(reason) {
+                preempt |= wake_thread(thread);
+            }
+        }
+    }
+    preempt
+}
+
 /// Prepares newly created threads for lifes challenges.   
 ///
-/// Gets executed when the thread is scheduled for the first time  
-/// and `switch_thread()` returns to this function. Enables interrupts  
-/// and switches the processor to `ProcessorMode::User`. Then calls the  
-/// users closure and provides an `exit_thread()` guard beneath.  
+/// Gets executed on the kernel stack when the thread is scheduled for the first  
+/// time and `switch_thread()` returns to this function. Enables interrupts  
+/// and calls the users entry function in `ProcessorMode::User` on the user stack,  
+/// with `thread_return()` as return address, which passes the function's  
+/// return value on as exit code.  
+/// The entry is taken from the TCB before the switch, user threads cannot read kernel memory.
 unsafe extern "C" fn new_thread_entry() {
     assert!(processor::ProcessorMode::System == processor::get_processor_mode());
-    processor::set_interrupts_enabled!(true);
+    let thread = get_current_thread();
+    let entry = thread.entry.take().expect("thread entry already called");
     // run idle thread in system mode for low power mode
-    if RUNNING_THREAD_ID > 0 {
-        processor::switch_processor_mode!(processor::ProcessorMode::User);
+    if thread.id == IDLE_THREAD_ID {
+        processor::set_interrupts_enabled!(true);
+        (entry.function)(entry.arg);
+        unreachable!("idle thread returned");
+    }
+    enter_user_mode(
+        entry.function as usize,
+        entry.arg as usize,
+        thread.stack_start,
+        thread_return as usize,
+    );
+}
+
+/// Return address of the entry function of a user thread.
+extern "C" fn thread_return(exit_code: usize) -> ! {
+    let error = rost_api::syscalls::exit_thread(exit_code).unwrap_err();
+    unreachable!("exit_thread failed: {:?}", error);
+}
+
+/// Runs the handler of a signal in place of the interrupted thread.
+///
+/// `schedule()` switches to this function instead of the saved context  
+/// of a thread with a pending signal, passing the signal, its handler and  
+/// the user stack pointer of the thread in r0 to r2. The handler runs in  
+/// `ProcessorMode::User` on the user stack below the interrupted context,  
+/// which `signal_return()` continues afterwards.
+unsafe extern "C" fn signal_entry(signal: Signal, handler: SignalHandler, user_stack: *mut u8) {
+    enter_user_mode(
+        handler as usize,
+        signal as usize,
+        user_stack,
+        signal_handler_return as usize,
+    );
+}
+
+/// Return address of a signal handler.
+extern "C" fn signal_handler_return() -> ! {
+    let error = rost_api::syscalls::signal_return().unwrap_err();
+    unreachable!("signal_return failed: {:?}", error);
+}
+
+/// Leaves the kernel stack and calls `function(arg)` in `ProcessorMode::User`  
+/// on `user_stack` with interrupts enabled.
+///
+/// `function` returns to `return_address`, which must not return.  
+/// The next exception from user mode starts at `KERNEL_STACK_TOP` again,  
+/// what is left of the kernel stack below it gets discarded.
+unsafe fn enter_user_mode(
+    function: usize,
+    arg: usize,
+    user_stack: *mut u8,
+    return_address: usize,
+) -> ! {
+    let mut cpsr: usize;
+    asm!("mrs {0}, cpsr", out(reg) cpsr);
+    // user mode with interrupts enabled, set together with the stack pointer
+    let user_cpsr = (cpsr & !0x9F) | processor::ProcessorMode::User as usize;
+    // AAPCS requires an 8 byte aligned stack at public interfaces
+    let user_stack = user_stack as usize & !7;
+    asm!(
+        "mov sp, {user_stack}",
+        "mov lr, {return_address}",
+        "msr cpsr_c, {user_cpsr}",
+        "bx {function}",
+        user_stack = in(reg) user_stack,
+        return_address = in(reg) return_address,
+        user_cpsr = in(reg) user_cpsr,
+        function = in(reg) function,
+        in("r0") arg,
+        options(noreturn)
+    );
+}
+
+/// Takes the next pending signal of a thread which is not running a handler already.
+fn take_pending_signal(thread: &mut TCB) -> Option<(Signal, SignalHandler)> {
+    if thread.signal_context.is_some() {
+        return None;
     }
+    let signal = *thread.pending_signals.iter().next()?;
+    thread.pending_signals.remove(&signal);
+    thread
+        .signal_handlers
+        .get(&signal)
+        .map(|&handler| (signal, handler))
+}
 
-    (get_current_thread().entry)();
-    rost_api::syscalls::exit_thread();
+/// Builds a fake context below the saved context of the thread like  
+/// `create_thread_internal()` does, which enters `signal_entry()`.  
+/// Returns the stack pointer to switch to or None if the fake context would  
+/// reach into the guard region of the kernel stack or the handler would start  
+/// in the guard region of the user stack.
+unsafe fn divert_to_signal_handler(
+    thread: &mut TCB,
+    signal: Signal,
+    handler: SignalHandler,
+) -> Option<*mut u8> {
+    let kernel_stack_bottom = thread.kernel_stack_start().sub(KERNEL_STACK_SIZE);
+    let kernel_stack_left = thread.stack_current as usize - kernel_stack_bottom as usize;
+    let user_stack = thread.saved_user_stack();
+    let user_stack_left = (user_stack as usize).saturating_sub(thread.stack_bottom() as usize);
+    if kernel_stack_left < STACK_GUARD_SIZE + CONTEXT_SIZE || user_stack_left <= STACK_GUARD_SIZE {
+        return None;
+    }
+    thread.signal_context = Some(thread.stack_current);
+    let stack_current = thread.stack_current.sub(CONTEXT_SIZE);
+
+    // CPSR, LR and r0-r12 as popped by `switch_thread()`
+    core::ptr::write_volatile(
+        stack_current.offset(0) as *mut usize,
+        processor::ProcessorMode::System as usize,
+    );
+    core::ptr::write_volatile(stack_current.offset(4) as *mut usize, signal_entry as usize);
+    core::ptr::write_volatile(stack_current.offset(8) as *mut usize, signal as usize);
+    core::ptr::write_volatile(stack_current.offset(12) as *mut usize, handler as usize);
+    core::ptr::write_volatile(stack_current.offset(16) as *mut usize, user_stack as usize);
+    Some(stack_current)
 }
 
 /// Creates TCB and Stack for a new thread.
 ///
 /// Takes the entry function provided by the user and creates  
-/// a TCB and stack for the new thread. Builds up a fake stack
-/// to be popped when the thread is first switched to by `switch_thread()`.  
-/// This fake stack contains a Processor Status in System Mode and
+/// a TCB and stacks for the new thread. Builds up a fake context on the  
+/// kernel stack to be popped when the thread is first switched to by `switch_thread()`.  
+/// This fake context contains a Processor Status in System Mode and
 /// the address which gets popped into the Link Register pointing
-/// to `new_thread_entry()`.
-pub fn create_thread_internal(entry: Box<dyn FnMut() + 'static>) -> usize {
+/// to `new_thread_entry()`.  
+/// Threads with `Periodic` timing are scheduled earliest deadline first.  
+/// A stack size of zero selects the default stack size, other sizes have to be  
+/// in the range of 1 KiB to 1 MiB, otherwise `SyscallError::InvalidArgument`  
+/// is returned. The whole stack gets filled with `STACK_PAINT` to detect  
+/// overflows and measure the used stack. User stacks live on the  
+/// user heap, which user threads may access, but are not charged to a quota.  
+/// The kernel stack holding the saved contexts of the thread lives on the kernel heap.  
+/// The new thread belongs to the process of the running thread.  
+/// Returns `SyscallError::OutOfMemory` if the stack cannot be allocated.
+pub(crate) fn create_thread_internal(
+    entry: ThreadEntry,
+    periodic: Option<Periodic>,
+    stack_size: usize,
+) -> Result<usize, SyscallError> {
+    let process_id = get_thread_by_id(unsafe { RUNNING_THREAD_ID })
+        .map_or(process::KERNEL_PROCESS_ID, |parent| parent.process_id);
+    create_thread_in_process(process_id, entry, periodic, stack_size)
+}
+
+/// Creates the main thread of a new process as a child of the running thread.  
+/// The thread starts with `DEFAULT_HEAP_QUOTA`, independent of the quota of its parent.
+pub(crate) fn create_process_thread(
+    process_id: ProcessId,
+    entry: ThreadEntry,
+) -> Result<usize, SyscallError> {
+    create_thread_in_process(process_id, entry, None, 0)
+}
+
+/// Creates a thread of any process, see `create_thread_internal()`.  
+/// The stack is taken from the heap of the process.
+fn create_thread_in_process(
+    process_id: ProcessId,
+    entry: ThreadEntry,
+    periodic: Option<Periodic>,
+    stack_size: usize,
+) -> Result<usize, SyscallError> {
     assert!(processor::ProcessorMode::System == processor::get_processor_mode());
+    let stack_size = match stack_size {
+        0 => THREAD_STACK_SIZE,
+        MIN_THREAD_STACK_SIZE..=MAX_THREAD_STACK_SIZE => stack_size,
+        _ => return Err(SyscallError::InvalidArgument),
+    };
     unsafe {
-        let id = LAST_THREAD_ID;
-        LAST_THREAD_ID += 1;
+        // keep the stack pointer 8 byte aligned
+        let stack_size = (stack_size + 7) & !7;
 
-        let layout = Layout::from_size_align(THREAD_STACK_SIZE, core::mem::align_of::<u64>())
-            .expect("Bad layout");
+        let layout =
+            Layout::from_size_align(stack_size, core::mem::align_of::<u64>()).expect("Bad layout");
 
-        let buffer = alloc(layout);
-        if buffer.is_null() {
-            panic!("buffer is null");
-        }
+        let buffer =
+            user_heap::allocate_stack(process_id, layout).ok_or(SyscallError::OutOfMemory)?;
+        let id = LAST_THREAD_ID;
+        LAST_THREAD_ID += 1;
+        core::slice::from_raw_parts_mut(buffer as *mut u32, stack_size / 4).fill(STACK_PAINT);
 
-        let stack_start = buffer.add(THREAD_STACK_SIZE);
+        let stack_start = buffer.add(stack_size);
+        let kernel_stack = vec![KERNEL_STACK_PAINT; KERNEL_STACK_SIZE / 8].into_boxed_slice();
+        // the main thread of a new process does not inherit the quota of the spawning thread
+        let heap_quota = get_thread_by_id(RUNNING_THREAD_ID)
+            .filter(|parent| parent.process_id == process_id)
+            .map_or(rost_api::syscalls::DEFAULT_HEAP_QUOTA, |parent| {
+                parent.heap_quota
+            });
 
         let mut tcb = TCB {
             id,
+            process_id,
             parent_thread_id: RUNNING_THREAD_ID,
+            exit_code: None,
+            detached: false,
+            suspended: false,
+            traced: false,
+            heap_used: 0,
+            heap_quota,
+            priority: rost_api::syscalls::PRIORITY_DEFAULT,
+            base_priority: rost_api::syscalls::PRIORITY_DEFAULT,
+            periodic,
+            wakeup_timestamp: None,
+            wait_timed_out: false,
             state: ThreadState::Ready,
-            stack_current: stack_start,
+            stack_current: core::ptr::null_mut(),
             stack_start,
-            entry,
+            stack_size,
+            kernel_stack: Some(kernel_stack),
+            entry: Some(entry),
             subscribed_services: BTreeMap::new(),
+            mailbox: VecDeque::new(),
+            signal_handlers: BTreeMap::new(),
+            pending_signals: BTreeSet::new(),
+            signal_context: None,
         };
 
-        tcb.stack_current = tcb.stack_current.offset(15 * -4);
+        tcb.stack_current = tcb.kernel_stack_start().sub(CONTEXT_SIZE);
 
+        // interrupts stay disabled until `new_thread_entry()` enters user mode
         core::ptr::write_volatile(
             (tcb.stack_current.offset(0)) as *mut usize,
-            processor::ProcessorMode::System as usize,
+            processor::ProcessorMode::System as usize | 0x80,
         );
         core::ptr::write_volatile(
             (tcb.stack_current.offset(4)) as *mut usize,
             new_thread_entry as usize,
         );
 
+        if id != IDLE_THREAD_ID {
+            get_scheduler().enqueue(&tcb);
+        }
         THREADS.push(tcb);
-        id
+        Ok(id)
     }
 }
 
 /// Function called by the Kernel to set the running thread to `ThreadState::Stopped`.
-pub fn exit_internal() {
+///
+/// The exit code stays in the TCB until the parent joins the thread.
+pub fn exit_internal(exit_code: usize) {
     assert!(processor::ProcessorMode::System == processor::get_processor_mode());
+    stop_thread(get_current_thread(), exit_code);
+    schedule(None);
+}
+
+/// Sets a thread to `ThreadState::Stopped` and removes it from the scheduler.
+///
+/// Wakes the parent if it joins the thread and hands running children  
+/// over to the parent. The other threads of a process get killed when  
+/// its main thread stops. Mutexes still owned by the thread are passed on  
+/// to their waiters and a mutex owner stops inheriting the priority of  
+/// a thread killed while waiting. Timers of the thread get cancelled and  
+/// its heap allocations get charged to the parent.  
+/// Returns true if a woken thread should preempt the running thread.
+fn stop_thread(thread: &mut TCB, exit_code: usize) -> bool {
+    let waited_mutex_id = match thread.state {
+        ThreadState::Waiting(WaitingReason::Mutex(mutex_id)) => Some(mutex_id),
+        _ => None,
+    };
+    thread.state = ThreadState::Stopped;
+    thread.exit_code = Some(exit_code);
+    thread.suspended = false;
+    get_scheduler().remove(thread.id);
+    let mut preempt = sync::release_owned_mutexes(thread.id);
+    if let Some(mutex_id) = waited_mutex_id {
+        preempt |= sync::remove_mutex_waiter(mutex_id, thread.id);
+    }
+    timer_service::cancel_thread_timers(thread.id);
+    let heir_id = get_thread_by_id(thread.parent_thread_id)
+        .filter(|parent| {
+            parent.process_id == thread.process_id && parent.state != ThreadState::Stopped
+        })
+        .map(|parent| parent.id);
+    user_heap::release_thread(thread, heir_id);
+
+    if process::get_main_thread_id(thread.process_id) == Some(thread.id) {
+        unsafe {
+            let (thread_id, process_id) = (thread.id, thread.process_id);
+            for other_thread in THREADS.iter_mut().filter(|t| {
+                t.process_id == process_id && t.id != thread_id && t.state != ThreadState::Stopped
+            }) {
+                preempt |= terminate_thread(other_thread);
+            }
+        }
+    }
+
     unsafe {
-        let current_thread = get_current_thread();
-        current_thread.state = ThreadState::Stopped;
+        // running children get adopted by the parent, stopped ones get removed with the thread
+        let (thread_id, parent_thread_id) = (thread.id, thread.parent_thread_id);
+        for child in THREADS.iter_mut().filter(|t| {
+            t.parent_thread_id == thread_id && t.id != thread_id && t.state != ThreadState::Stopped
+        }) {
+            child.parent_thread_id = parent_thread_id;
+            child.detached = true;
+        }
+    }
 
-        // remove id from joined parent thread if available
-        if let Some(parent_thread) = get_thread_by_id(current_thread.parent_thread_id) {
-            if let ThreadState::Waiting(WaitingReason::Join(joined_thread_ids, _)) =
-                &mut parent_thread.state
-            {
-                let present = joined_thread_ids.remove(&RUNNING_THREAD_ID);
+    // remove id from joined parent thread if available
+    if let Some(parent_thread) = get_thread_by_id(thread.parent_thread_id) {
+        match &mut parent_thread.state {
+            ThreadState::Waiting(WaitingReason::Join(joined_thread_ids)) => {
+                let present = joined_thread_ids.remove(&thread.id);
                 if present && joined_thread_ids.is_empty() {
-                    parent_thread.state = ThreadState::Ready;
+                    preempt |= wake_thread(parent_thread);
                 }
             }
+            ThreadState::Waiting(WaitingReason::Any(reasons)) => {
+                let joins_thread = |reason: &WaitingReason| match reason {
+                    WaitingReason::Join(joined_thread_ids) => {
+                        joined_thread_ids.contains(&thread.id)
+                    }
+                    _ => false,
+                };
+                if reasons.iter().any(joins_thread) {
+                    preempt |= wake_thread(parent_thread);
+                }
+            }
+            _ => {}
         }
     }
-    schedule(None);
+    preempt
 }
 
-pub fn wakeup_elapsed_threads() {
+/// Returns true if the thread with `ancestor_id` created the given thread  
+/// or one of the threads it descends from.
+fn is_ancestor(ancestor_id: ThreadId, thread: &TCB) -> bool {
+    let mut parent_thread_id = thread.parent_thread_id;
+    loop {
+        if parent_thread_id == ancestor_id {
+            return true;
+        }
+        if parent_thread_id == IDLE_THREAD_ID {
+            return false;
+        }
+        match get_thread_by_id(parent_thread_id) {
+            Some(parent_thread) => parent_thread_id = parent_thread.parent_thread_id,
+            None => return false,
+        }
+    }
+}
+
+/// Returns a child of the running thread which it may join.
+///
+/// Fails with `SyscallError::NotAChild` for threads of other parents and  
+/// with `SyscallError::Detached` for children adopted after their parent stopped.
+pub(crate) fn get_joinable_thread<'a>(thread_id: ThreadId) -> Result<&'a mut TCB, SyscallError> {
+    let thread = get_thread_by_id(thread_id).ok_or(SyscallError::UnknownThread)?;
+    if thread.parent_thread_id != get_current_thread().id {
+        return Err(SyscallError::NotAChild);
+    }
+    if thread.detached {
+        return Err(SyscallError::Detached);
+    }
+    Ok(thread)
+}
+
+/// Returns another thread the running thread may control.
+///
+/// Only descendants of the running thread, which are not stopped, can be  
+/// killed, suspended or resumed.
+fn get_controllable_thread<'a>(thread_id: ThreadId) -> Result<&'a mut TCB, SyscallError> {
+    let current_thread_id = get_current_thread().id;
+    let thread = get_thread_by_id(thread_id)
+        .filter(|thread| thread.state != ThreadState::Stopped)
+        .ok_or(SyscallError::UnknownThread)?;
+    if thread.id == current_thread_id
+        || thread.id == IDLE_THREAD_ID
+        || !is_ancestor(current_thread_id, thread)
+    {
+        return Err(SyscallError::NotPermitted);
+    }
+    Ok(thread)
+}
+
+/// Stops a descendant of the running thread.
+///
+/// The killed thread loses its subscribed services and pending timer,  
+/// a joining parent receives `EXIT_CODE_KILLED`. Fails if the thread  
+/// does not exist or may not be killed by the running thread.
+pub fn kill_thread(thread_id: ThreadId) -> Result<(), SyscallError> {
+    let thread = get_controllable_thread(thread_id)?;
+
+    if terminate_thread(thread) {
+        schedule(None);
+    }
+    Ok(())
+}
+
+/// Stops a thread which did not exit by itself with `EXIT_CODE_KILLED`.
+///
+/// Returns true if a woken thread should preempt the running thread.
+fn terminate_thread(thread: &mut TCB) -> bool {
+    if let Some(wakeup_timestamp) = thread.wakeup_timestamp.take() {
+        get_timer_queue().remove(wakeup_timestamp, thread.id);
+    }
+    thread.subscribed_services.clear();
+
+    stop_thread(thread, rost_api::syscalls::EXIT_CODE_KILLED)
+}
+
+/// Prevents a descendant of the running thread from being scheduled until it gets resumed.
+///
+/// A waiting thread still receives its events but stays off the processor.  
+/// Fails if the thread does not exist or may not be suspended.
+pub fn suspend_thread(thread_id: ThreadId) -> Result<(), SyscallError> {
+    let thread = get_controllable_thread(thread_id)?;
+
+    if !thread.suspended {
+        thread.suspended = true;
+        if thread.state == ThreadState::Ready {
+            get_scheduler().dequeue(thread.id);
+        }
+    }
+    Ok(())
+}
+
+/// Allows a suspended descendant of the running thread to be scheduled again.
+///
+/// Fails if the thread does not exist or may not be resumed.
+pub fn resume_thread(thread_id: ThreadId) -> Result<(), SyscallError> {
+    let thread = get_controllable_thread(thread_id)?;
+
+    if thread.suspended {
+        thread.suspended = false;
+        if thread.state == ThreadState::Ready && wake_thread(thread) {
+            schedule(None);
+        }
+    }
+    Ok(())
+}
+
+/// Starts or stops recording the syscalls of a descendant of the running thread.
+///
+/// Fails if the thread does not exist or may not be traced.
+pub fn trace_thread(thread_id: ThreadId, enabled: bool) -> Result<(), SyscallError> {
+    get_controllable_thread(thread_id)?.traced = enabled;
+    Ok(())
+}
+
+/// Sets how many bytes of the user heap a descendant of the running thread may allocate.
+///
+/// Fails if the thread does not exist or may not be controlled by the running thread.
+pub fn set_heap_quota(thread_id: ThreadId, quota: usize) -> Result<(), SyscallError> {
+    get_controllable_thread(thread_id)?.heap_quota = quota;
+    Ok(())
+}
+
+/// Removes a stopped child thread after its parent joined it and returns its exit code.
+pub(crate) fn reap_thread(thread_id: ThreadId) -> usize {
     unsafe {
-        let current_timestamp = system_timer::get_current_real_time() as usize;
-        // find waiting thread with elapsed timestamp
-        let thread = THREADS.iter_mut().find(|t| {
-            if let ThreadState::Waiting(WaitingReason::Sleep(wakeup_timestamp)) = t.state {
-                if wakeup_timestamp <= current_timestamp {
-                    return true;
-                }
+        let pos = THREADS
+            .iter()
+            .position(|t| t.id == thread_id && t.id != RUNNING_THREAD_ID)
+            .expect("reap_thread: invalid thread_id given");
+        let thread = THREADS.remove(pos);
+        debug_assert!(thread.state == ThreadState::Stopped);
+        thread.exit_code.unwrap_or_default()
+    }
+}
+
+/// Returns true if a stopped thread has to be kept until its parent joins it.
+fn has_joinable_parent(thread: &TCB) -> bool {
+    !thread.detached
+        && thread.parent_thread_id != IDLE_THREAD_ID
+        && get_thread_by_id(thread.parent_thread_id)
+            .map_or(false, |parent| parent.state != ThreadState::Stopped)
+}
+
+/// Wakes every waiting thread whose wakeup timestamp elapsed.
+///
+/// The woken threads are only handed to the scheduler, which is called  
+/// if one of them should preempt the running thread.
+pub fn wakeup_elapsed_threads() {
+    let current_timestamp = system_timer::get_current_real_time() as usize;
+    let mut preempt = false;
+
+    for (wakeup_timestamp, thread_id) in get_timer_queue().pop_expired(current_timestamp) {
+        if let Some(thread) = get_thread_by_id(thread_id) {
+            debug_assert!(matches!(thread.state, ThreadState::Waiting(_)));
+            debug_assert!(thread.wakeup_timestamp == Some(wakeup_timestamp));
+            thread.wakeup_timestamp = None;
+            thread.wait_timed_out = true;
+            preempt |= wake_thread(thread);
+        }
+    }
+
+    if preempt {
+        schedule(None);
+    }
+}
+
+/// Ends the current period of the running periodic thread.
+///
+/// Counts a deadline miss if the period's work finished after its deadline  
+/// and waits for the release of the next period. The next release is always  
+/// one period after the last one, independent of when this gets called.  
+/// Returns the deadline misses of the thread so far.
+pub fn wait_next_period() -> Result<usize, SyscallError> {
+    let current_time = system_timer::get_current_real_time() as usize;
+    let current_thread = get_current_thread();
+    let periodic = current_thread
+        .periodic
+        .as_mut()
+        .ok_or(SyscallError::NotPermitted)?;
+
+    if current_time > periodic.deadline() {
+        periodic.deadline_misses += 1;
+    }
+    periodic.release += periodic.period;
+    let deadline_misses = periodic.deadline_misses;
+
+    // next period already started, keep running
+    if periodic.release <= current_time {
+        return Ok(deadline_misses);
+    }
+
+    let release = periodic.release;
+    wait(WaitingReason::NextPeriod, Some(release));
+    Ok(deadline_misses)
+}
+
+/// Blocks the running thread until it gets woken for the given reason.
+///
+/// With a timeout the thread also gets woken once the real time reaches  
+/// the given timestamp, which is reported as `WaitResult::TimedOut`.  
+/// Every `WaitingReason` can be combined with a timeout.
+pub(crate) fn wait(reason: WaitingReason, timeout: Option<TimeoutValue>) -> WaitResult {
+    if let Some(timeout) = timeout {
+        if timeout <= system_timer::get_current_real_time() as usize {
+            return WaitResult::TimedOut;
+        }
+    }
+    let current_thread = get_current_thread();
+    current_thread.state = ThreadState::Waiting(reason);
+    current_thread.wakeup_timestamp = timeout;
+    current_thread.wait_timed_out = false;
+    if let Some(timeout) = timeout {
+        get_timer_queue().insert(timeout, current_thread.id);
+    }
+
+    schedule(None);
+
+    if get_current_thread().wait_timed_out {
+        WaitResult::TimedOut
+    } else {
+        WaitResult::Woken
+    }
+}
+
+/// Returns true if waiting for `reason` would not block the running thread.
+///
+/// Fails for services the thread did not subscribe, threads which are not  
+/// its children and reasons `wait_any` cannot combine.
+fn is_wait_over(reason: &WaitingReason) -> Result<bool, SyscallError> {
+    let current_thread = get_current_thread();
+    match reason {
+        WaitingReason::DBGU => Ok(!current_thread
+            .subscribed_services
+            .get(&rost_api::syscalls::ThreadServices::DBGU)
+            .ok_or(SyscallError::NotSubscribed)?
+            .is_empty()),
+        WaitingReason::Join(joined_thread_ids) => {
+            let mut over = false;
+            for &thread_id in joined_thread_ids {
+                // like `join_thread`, unknown or already joined threads are an error
+                over |= get_joinable_thread(thread_id)?.state == ThreadState::Stopped;
             }
-            false
-        });
+            Ok(over)
+        }
+        WaitingReason::Timer => Ok(!current_thread
+            .subscribed_services
+            .get(&rost_api::syscalls::ThreadServices::Timer)
+            .ok_or(SyscallError::NotSubscribed)?
+            .is_empty()),
+        WaitingReason::Message => Ok(!current_thread.mailbox.is_empty()),
+        WaitingReason::PipeRead(pipe_id) => Ok(crate::pipe::is_readable(*pipe_id)),
+        _ => Err(SyscallError::InvalidArgument),
+    }
+}
+
+/// Blocks the running thread until the first of several reasons occurs.
+///
+/// Returns the index of the first reason which does not block anymore,  
+/// without waiting if one of them is already over. Only DBGU input, timer  
+/// ticks, the exit of a child, mailbox messages and readable pipes can be combined.  
+/// Fails with `SyscallError::TimedOut` if the timeout elapsed first.
+pub(crate) fn wait_any(
+    reasons: Vec<WaitingReason>,
+    timeout: Option<TimeoutValue>,
+) -> Result<usize, SyscallError> {
+    loop {
+        for (index, reason) in reasons.iter().enumerate() {
+            if is_wait_over(reason)? {
+                return Ok(index);
+            }
+        }
+        // another thread may have emptied a pipe before this one ran, then wait again
+        if wait(WaitingReason::Any(reasons.clone()), timeout) == WaitResult::TimedOut {
+            return Err(SyscallError::TimedOut);
+        }
+    }
+}
+
+/// Gets called on every system timer tick and calls the scheduler  
+/// when the running thread has used up its time slice.
+pub fn scheduler_tick() {
+    let running_thread = match unsafe { get_thread_by_id(RUNNING_THREAD_ID) } {
+        Some(thread) => thread,
+        None => return,
+    };
+    if running_thread.id != IDLE_THREAD_ID && get_scheduler().tick(running_thread) {
+        schedule(None);
+    }
+}
+
+/// Changes the effective priority of a thread and requeues it if it is ready.
+///
+/// Returns true if the thread should preempt the running thread  
+/// and the caller should call `schedule()`.
+pub(crate) fn set_effective_priority(thread: &mut TCB, priority: Priority) -> bool {
+    if thread.state == ThreadState::Ready {
+        // requeue with the new priority
+        get_scheduler().dequeue(thread.id);
+        thread.priority = priority;
+        return wake_thread(thread);
+    }
+    thread.priority = priority;
+    false
+}
 
-        // found waiting thread with elapsed timestamp -> schedule
-        if let Some(thread) = thread {
-            thread.state = ThreadState::Ready;
-            schedule(Some(thread.id));
+/// Sets the base priority of the given thread.
+///
+/// Only the running thread itself or its parent may change the priority.  
+/// The idle thread always keeps `IDLE_THREAD_PRIORITY`. While the thread  
+/// inherits a higher priority from the waiters of its mutexes, only the  
+/// base priority changes. Priorities outside of `PRIORITY_MIN..=PRIORITY_MAX`  
+/// are rejected with `SyscallError::InvalidArgument`.
+pub fn set_priority(thread_id: ThreadId, priority: Priority) -> Result<(), SyscallError> {
+    if !(rost_api::syscalls::PRIORITY_MIN..=rost_api::syscalls::PRIORITY_MAX).contains(&priority) {
+        return Err(SyscallError::InvalidArgument);
+    }
+    let current_thread_id = get_current_thread().id;
+    let thread = get_thread_by_id(thread_id).ok_or(SyscallError::UnknownThread)?;
+    if thread.id == IDLE_THREAD_ID
+        || (thread.id != current_thread_id && thread.parent_thread_id != current_thread_id)
+    {
+        return Err(SyscallError::NotPermitted);
+    }
+    thread.base_priority = priority;
+
+    // a lowered running thread may have to give way
+    if sync::update_inherited_priority(thread.id) || thread.id == current_thread_id {
+        schedule(None);
+    }
+    Ok(())
+}
+
+/// Returns the base priority of the given thread.
+pub fn get_priority(thread_id: ThreadId) -> Result<Priority, SyscallError> {
+    get_thread_by_id(thread_id)
+        .map(|t| t.base_priority)
+        .ok_or(SyscallError::UnknownThread)
+}
+
+/// Sets or removes the handler of a signal for the running thread.
+///
+/// Removing a handler discards the signal if it is pending.
+pub fn register_signal_handler(signal: Signal, handler: Option<SignalHandler>) {
+    let thread = get_current_thread();
+    match handler {
+        Some(handler) => {
+            thread.signal_handlers.insert(signal, handler);
         }
+        None => {
+            thread.signal_handlers.remove(&signal);
+            thread.pending_signals.remove(&signal);
+        }
+    }
+}
+
+/// Marks a signal as pending for a descendant of the running thread with a handler for it.
+///
+/// The handler runs the next time the thread gets scheduled, waiting  
+/// threads are not woken by a signal. Signals for threads without a  
+/// handler are discarded, which is reported as false. Fails if the  
+/// thread does not exist or may not be controlled by the running thread.
+pub fn send_signal(thread_id: ThreadId, signal: Signal) -> Result<bool, SyscallError> {
+    let thread = get_controllable_thread(thread_id)?;
+    if !thread.signal_handlers.contains_key(&signal) {
+        return Ok(false);
+    }
+    thread.pending_signals.insert(signal);
+    Ok(true)
+}
+
+/// Continues the context the running thread was in before its signal handler started.
+///
+/// The context of the handler, including this syscall, is discarded.  
+/// Fails with `SyscallError::NotPermitted` if no signal handler is running.
+pub fn signal_return() -> Result<(), SyscallError> {
+    let thread = get_current_thread();
+    let signal_context = thread
+        .signal_context
+        .take()
+        .ok_or(SyscallError::NotPermitted)?;
+    let discarded_context: *mut u8 = core::ptr::null_mut();
+    unsafe {
+        processor::set_interrupts_enabled!(false);
+        KERNEL_STACK_TOP = thread.kernel_stack_top();
+        switch_thread(&discarded_context, &signal_context);
+    }
+    unreachable!("signal_return: discarded context continued");
+}
+
+/// Turns a fault in the code of the running user thread into a signal.
+///
+/// Runs the handler of the signal if the thread registered one, in  
+/// `ProcessorMode::User` on the user stack below the faulting context.  
+/// The faulting instruction cannot be continued, so the thread exits with  
+/// `EXIT_CODE_FAULT` afterwards, right away if the user stack has no room left.
+pub fn handle_user_fault(signal: Signal) -> ! {
+    let thread = get_current_thread();
+    log::error!("thread {} received {:?}", thread.id, signal);
+    let user_stack = thread.saved_user_stack();
+    let user_stack_left = (user_stack as usize).saturating_sub(thread.stack_bottom() as usize);
+    match thread.signal_handlers.get(&signal) {
+        Some(&handler) if user_stack_left > STACK_GUARD_SIZE => unsafe {
+            enter_user_mode(
+                handler as usize,
+                signal as usize,
+                user_stack,
+                fault_handler_return as usize,
+            )
+        },
+        _ => {
+            exit_internal(rost_api::syscalls::EXIT_CODE_FAULT);
+            unreachable!("faulted thread continued");
+        }
+    }
+}
+
+/// Return address of the signal handler of a fault.
+extern "C" fn fault_handler_return() -> ! {
+    let error = rost_api::syscalls::exit_thread(rost_api::syscalls::EXIT_CODE_FAULT).unwrap_err();
+    unreachable!("exit_thread failed: {:?}", error);
+}
+
+/// Puts a copy of `data` into the mailbox of a thread and wakes it if it waits for a message.
+///
+/// Never blocks the sender, a full mailbox is reported as  
+/// `SyscallError::MailboxFull` so the sender can retry later.
+pub(crate) fn send_message(thread_id: ThreadId, data: &[u8]) -> Result<(), SyscallError> {
+    if data.len() > rost_api::syscalls::MAX_MESSAGE_SIZE {
+        return Err(SyscallError::MessageTooLarge);
+    }
+    let thread = get_thread_by_id(thread_id)
+        .filter(|thread| thread.id != IDLE_THREAD_ID && thread.state != ThreadState::Stopped)
+        .ok_or(SyscallError::UnknownThread)?;
+    if thread.mailbox.len() >= rost_api::syscalls::MAILBOX_CAPACITY {
+        return Err(SyscallError::MailboxFull);
+    }
+
+    thread
+        .mailbox
+        .push_back(ThreadMessage::Mailbox(data.to_vec()));
+    if thread.is_waiting_for(&WaitingReason::Message) && wake_thread(thread) {
+        schedule(None);
+    }
+    Ok(())
+}
+
+/// Copies the oldest message of the running thread's mailbox into `buffer`.
+///
+/// Waits for a message if the mailbox is empty. A message larger than  
+/// the buffer stays in the mailbox. Returns the length of the message.
+pub(crate) fn receive_message(
+    buffer: &mut [u8],
+    timeout: Option<TimeoutValue>,
+) -> Result<usize, SyscallError> {
+    if get_current_thread().mailbox.is_empty()
+        && wait(WaitingReason::Message, timeout) == WaitResult::TimedOut
+    {
+        return Err(SyscallError::TimedOut);
     }
+
+    let mailbox = &mut get_current_thread().mailbox;
+    let length = match mailbox.front() {
+        Some(ThreadMessage::Mailbox(data)) if data.len() > buffer.len() => {
+            return Err(SyscallError::BufferTooSmall)
+        }
+        Some(ThreadMessage::Mailbox(data)) => {
+            buffer[..data.len()].copy_from_slice(data);
+            data.len()
+        }
+        _ => panic!("receive_message: woken without a message"),
+    };
+    mailbox.pop_front();
+    Ok(length)
 }
 
-pub fn handle_dbgu_new_character_event(character: char) {
+/// Passes a received character to every thread subscribed to the DBGU service.
+///
+/// Returns true if a woken thread should preempt the running one and  
+/// the caller should call `schedule()` after finishing the interrupt.
+pub fn handle_dbgu_new_character_event(character: char) -> bool {
+    let mut preempt = false;
     unsafe {
         for thread in &mut THREADS {
             if let Some(messages) = thread
@@ -262,22 +1267,61 @@ pub fn handle_dbgu_new_character_event(character: char) {
             {
                 messages.push_back(ThreadMessage::DBGU(character));
             }
-            if let ThreadState::Waiting(WaitingReason::DBGU) = thread.state {
+            if thread.is_waiting_for(&WaitingReason::DBGU) {
                 debug_assert!(thread
                     .subscribed_services
                     .contains_key(&rost_api::syscalls::ThreadServices::DBGU));
-                thread.state = ThreadState::Ready;
+                preempt |= wake_thread(thread);
             }
         }
     }
+    preempt
+}
+
+/// Passes the tick of an expired timer to the thread owning it.
+///
+/// The tick is dropped while the previous tick of the same timer is still  
+/// queued, so a slow thread does not pile up ticks. Returns true if the  
+/// woken thread should preempt the running one and the caller should call `schedule()`.
+pub(crate) fn handle_timer_event(thread_id: ThreadId, timer_id: TimerId) -> bool {
+    let thread = match get_thread_by_id(thread_id) {
+        Some(thread) => thread,
+        None => return false,
+    };
+    if let Some(messages) = thread
+        .subscribed_services
+        .get_mut(&rost_api::syscalls::ThreadServices::Timer)
+    {
+        let tick = ThreadMessage::Timer(timer_id);
+        if !messages.contains(&tick) {
+            messages.push_back(tick);
+        }
+    }
+    thread.is_waiting_for(&WaitingReason::Timer) && wake_thread(thread)
+}
+
+/// Removes the queued tick of a cancelled timer.
+pub(crate) fn discard_timer_tick(thread_id: ThreadId, timer_id: TimerId) {
+    if let Some(messages) = get_thread_by_id(thread_id).and_then(|thread| {
+        thread
+            .subscribed_services
+            .get_mut(&rost_api::syscalls::ThreadServices::Timer)
+    }) {
+        messages.retain(|message| *message != ThreadMessage::Timer(timer_id));
+    }
 }
 
 /// Schedules and switches to a new thread to run on the processor.    
 ///
-/// This function needs to be called in a privileged mode and cycles  
-/// through threads until it finds the next one that is ready. It then  
-/// calls `switch_thread` to switch to the selected thread.  
-/// TCBs and Stacks of threads with `ThreadState::Stopped` are removed.
+/// This function needs to be called in a privileged mode and asks the  
+/// scheduling policy selected at build time for the next thread to run.  
+/// If the policy has nothing ready the running thread continues or, if it  
+/// is waiting or stopped, the idle thread runs. It then calls `switch_thread`  
+/// to switch to the selected thread.  
+/// TCBs and Stacks of threads with `ThreadState::Stopped` are removed,  
+/// only the TCB is kept for a parent which can still join the thread.  
+/// A thread without stack left for the handler of a pending signal exits  
+/// with `EXIT_CODE_FAULT` instead of running.
 pub fn schedule(next_thread_id: Option<usize>) {
     assert!(processor::ProcessorMode::System == processor::get_processor_mode());
     unsafe {
@@ -288,7 +1332,26 @@ pub fn schedule(next_thread_id: Option<usize>) {
         processor::set_interrupts_enabled!(false);
 
         // remove stopped threads but not the current one if stopped
-        THREADS.retain(|t| t.state != ThreadState::Stopped || t.id == RUNNING_THREAD_ID);
+        let mut reaped_thread_ids = Vec::new();
+        for thread in THREADS
+            .iter_mut()
+            .filter(|t| t.state == ThreadState::Stopped && t.id != RUNNING_THREAD_ID)
+        {
+            if has_joinable_parent(thread) {
+                thread.release_stack();
+            } else {
+                reaped_thread_ids.push(thread.id);
+            }
+        }
+        if !reaped_thread_ids.is_empty() {
+            THREADS.retain(|t| !reaped_thread_ids.contains(&t.id));
+        }
+        // processes end once none of their threads has a stack anymore
+        process::release_processes(|process_id| {
+            THREADS
+                .iter()
+                .any(|t| t.process_id == process_id && !t.stack_start.is_null())
+        });
 
         let running_thread_pos = THREADS
             .iter()
@@ -296,59 +1359,65 @@ pub fn schedule(next_thread_id: Option<usize>) {
             .unwrap();
         let running_thread = &mut THREADS[running_thread_pos];
 
-        let mut next_thread_pos: usize;
-
-        // first check for optional argument: specific next_thread_id to schedule
-        if let Some(next_thread_id) = next_thread_id {
-            if let Some(pos) = THREADS.iter().position(|t| t.id == next_thread_id) {
-                next_thread_pos = pos;
-            } else {
-                panic!("scheduler: invalid thread_id given");
+        let next_thread_id = match next_thread_id {
+            // first check for optional argument: specific next_thread_id to schedule
+            Some(next_thread_id) => {
+                get_scheduler().dequeue(next_thread_id);
+                next_thread_id
             }
-        } else {
-            next_thread_pos = running_thread_pos;
-
-            // simple round-robin-scheduler
-            // find the next thread which is ready from the current position
-            while THREADS[next_thread_pos].state != ThreadState::Ready {
-                next_thread_pos += 1;
-
-                // cycle from the beginning if last pos in threads array
-                if next_thread_pos == THREADS.len() {
-                    // start at first real thread after idle
-                    next_thread_pos = 1;
-                    // but if we are already in the idle thread nothing else to do
-                    if running_thread.id == 0 {
-                        debug_assert!(!THREADS
-                            .iter()
-                            .any(|t| t.id != IDLE_THREAD_ID && t.state == ThreadState::Ready));
-                        return;
-                    }
-                }
-                // back at the thread we started our journey
-                if next_thread_pos == running_thread_pos {
-                    // no other thread ready and this thread is stopped or waiting
-                    // then switch to the idle thread
-                    match running_thread.state {
-                        ThreadState::Waiting(_) | ThreadState::Stopped => {
-                            next_thread_pos = 0;
-                            break;
-                        }
-                        _ => {
-                            // else stay in the same thread
-                            return;
-                        }
-                    }
+            None => {
+                let running_thread_runnable = running_thread.id != IDLE_THREAD_ID
+                    && running_thread.state == ThreadState::Running;
+                let picked_thread_id = get_scheduler().pick_next(if running_thread_runnable {
+                    Some(running_thread)
+                } else {
+                    None
+                });
+
+                match picked_thread_id {
+                    Some(picked_thread_id) => picked_thread_id,
+                    // nothing else to do, stay in the same thread
+                    None if running_thread.state == ThreadState::Running => return,
+                    // the running thread is waiting or stopped
+                    None => IDLE_THREAD_ID,
                 }
             }
-        }
+        };
+
+        let next_thread_pos = match THREADS.iter().position(|t| t.id == next_thread_id) {
+            Some(pos) => pos,
+            None => panic!("scheduler: invalid thread_id given"),
+        };
         let next_thread = &mut THREADS[next_thread_pos];
         assert!(next_thread.state == ThreadState::Ready);
 
+        // a pending signal diverts the thread to its handler first
+        let next_stack = match take_pending_signal(next_thread) {
+            Some((signal, handler)) => match divert_to_signal_handler(next_thread, signal, handler)
+            {
+                Some(stack) => stack,
+                None => {
+                    // no room left for the handler, the thread faults like on a stack overflow
+                    log::error!(
+                        "stack overflow in thread {}: no room for the handler of {:?}",
+                        next_thread.id,
+                        signal
+                    );
+                    stop_thread(next_thread, rost_api::syscalls::EXIT_CODE_FAULT);
+                    schedule(None);
+                    return;
+                }
+            },
+            None => next_thread.stack_current,
+        };
+
         next_thread.state = ThreadState::Running;
         // only switch back old thread to ready if not waiting or stopped
         if running_thread.state == ThreadState::Running {
             running_thread.state = ThreadState::Ready;
+            if running_thread.id != IDLE_THREAD_ID {
+                get_scheduler().enqueue(running_thread);
+            }
         }
         RUNNING_THREAD_ID = next_thread.id;
 
@@ -369,27 +1438,27 @@ pub fn schedule(next_thread_id: Option<usize>) {
             "t#: {} switch thread from {} sp:{:#X} to {} sp:{:#X}",
             THREADS.len(),
             running_thread.id,
-            running_thread
-                .stack_start
-                .offset_from(running_thread.stack_current) as u32,
+            running_thread.kernel_stack_used(),
             next_thread.id,
-            next_thread
-                .stack_start
-                .offset_from(next_thread.stack_current) as u32
+            next_thread.kernel_stack_used()
         );
 
-        switch_thread(&running_thread.stack_current, &next_thread.stack_current);
+        running_thread.check_stack_guard();
+        next_thread.check_stack_guard();
+
+        if next_thread.process_id != running_thread.process_id {
+            process::switch_address_space(next_thread.process_id);
+        }
+        KERNEL_STACK_TOP = next_thread.kernel_stack_top();
+        switch_thread(&running_thread.stack_current, &next_stack);
 
-        // always reset scheduler interval counter to prevent immediate rescheduling
-        // maybe called from wakeup or other places
-        SCHEDULER_INTERVAL_COUNTER = SCHEDULER_INTERVAL;
         processor::set_interrupts_enabled!(true);
     }
 }
 
 /// Switches from the current thread to the thread whose stack  
 /// pointer is passed in r1. The function saves the current context
-/// to the stack of the current thread, saves the stack pointer in the
+/// to the kernel stack of the current thread, saves the stack pointer in the
 /// TCB and switches the stack pointer with the stack pointer of the
 /// new thread. It then pops the context off the new threads stack
 /// and returns to the now different instruction pointed to by the
@@ -0,0 +1,48 @@
+use crate::threads::{ThreadId, TimeoutValue};
+use alloc::collections::btree_set::BTreeSet;
+use alloc::vec::Vec;
+
+/// Expiry timestamps sorted by real time, by default wakeup timestamps of waiting threads.
+///
+/// Every thread waiting with a timeout has exactly one entry which  
+/// gets removed when the thread is woken, either by its timeout or  
+/// by the event it was waiting for. The timer service keeps the  
+/// expiries of its timers in a separate queue.
+pub(crate) struct TimerQueue<Id: Ord + Copy = ThreadId> {
+    timers: BTreeSet<(TimeoutValue, Id)>,
+}
+
+impl<Id: Ord + Copy> TimerQueue<Id> {
+    pub(crate) fn new() -> Self {
+        TimerQueue {
+            timers: BTreeSet::new(),
+        }
+    }
+
+    /// Adds a timer to expire for the given id at `timestamp`.
+    pub(crate) fn insert(&mut self, timestamp: TimeoutValue, id: Id) {
+        self.timers.insert((timestamp, id));
+    }
+
+    /// Removes a timer before it expired, e.g. of a thread which got woken by an event.
+    pub(crate) fn remove(&mut self, timestamp: TimeoutValue, id: Id) {
+        self.timers.remove(&(timestamp, id));
+    }
+
+    /// Removes and returns all timers which elapsed at `current_timestamp`,  
+    /// earliest first. Only touches the expired timers.
+    pub(crate) fn pop_expired(
+        &mut self,
+        current_timestamp: TimeoutValue,
+    ) -> Vec<(TimeoutValue, Id)> {
+        let mut expired = Vec::new();
+        while let Some(&timer) = self.timers.iter().next() {
+            if timer.0 > current_timestamp {
+                break;
+            }
+            self.timers.remove(&timer);
+            expired.push(timer);
+        }
+        expired
+    }
+}
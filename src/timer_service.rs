@@ -0,0 +1,113 @@
+use crate::system_timer;
+use crate::threads::{self, ThreadId, TimeoutValue};
+use crate::timer_queue::TimerQueue;
+use alloc::collections::btree_map::BTreeMap;
+
+pub(crate) type TimerId = usize;
+
+/// Timer of a thread subscribed to `ThreadServices::Timer`.
+///
+/// Every expiry puts a tick with the timer id into the service queue  
+/// of the thread. One-shot timers are removed after their first tick.
+struct Timer {
+    thread_id: ThreadId,
+    /// interval of a periodic timer in real time units, None for one-shot timers
+    period: Option<TimeoutValue>,
+    expiry: TimeoutValue,
+}
+
+static mut TIMERS: Option<BTreeMap<TimerId, Timer>> = None;
+static mut TIMER_QUEUE: Option<TimerQueue<TimerId>> = None;
+static mut LAST_TIMER_ID: TimerId = 0;
+
+fn get_timers<'a>() -> &'a mut BTreeMap<TimerId, Timer> {
+    unsafe { TIMERS.get_or_insert_with(BTreeMap::new) }
+}
+
+fn get_timer_queue<'a>() -> &'a mut TimerQueue<TimerId> {
+    unsafe { TIMER_QUEUE.get_or_insert_with(TimerQueue::new) }
+}
+
+/// Starts a timer for the running thread which expires after `interval` real time units.
+///
+/// The current unit may be almost over, so the first expiry is one unit later.  
+/// Periodic timers expire again every `interval` units, measured from  
+/// the previous expiry so the ticks do not drift. The interval has to be at least one unit.
+pub(crate) fn create_timer(interval: TimeoutValue, periodic: bool) -> TimerId {
+    debug_assert!(interval > 0);
+    let expiry = (system_timer::get_current_real_time() as usize)
+        .saturating_add(interval)
+        .saturating_add(1);
+    unsafe {
+        LAST_TIMER_ID += 1;
+        get_timers().insert(
+            LAST_TIMER_ID,
+            Timer {
+                thread_id: threads::get_current_thread().id,
+                period: if periodic { Some(interval) } else { None },
+                expiry,
+            },
+        );
+        get_timer_queue().insert(expiry, LAST_TIMER_ID);
+        LAST_TIMER_ID
+    }
+}
+
+/// Stops a timer of the running thread and discards its pending tick.
+///
+/// Returns false if the timer does not exist or belongs to another thread.
+pub(crate) fn cancel_timer(timer_id: TimerId) -> bool {
+    let thread_id = threads::get_current_thread().id;
+    match get_timers().get(&timer_id) {
+        Some(timer) if timer.thread_id == thread_id => {
+            get_timer_queue().remove(timer.expiry, timer_id);
+            get_timers().remove(&timer_id);
+            threads::discard_timer_tick(thread_id, timer_id);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Stops all timers of a thread which unsubscribed the service or stopped.
+pub(crate) fn cancel_thread_timers(thread_id: ThreadId) {
+    let timer_queue = get_timer_queue();
+    get_timers().retain(|&timer_id, timer| {
+        if timer.thread_id == thread_id {
+            timer_queue.remove(timer.expiry, timer_id);
+            return false;
+        }
+        true
+    });
+}
+
+/// Delivers a tick for every timer which expired and restarts the periodic ones.
+///
+/// The woken threads are only handed to the scheduler, which is called  
+/// if one of them should preempt the running thread.
+pub fn deliver_elapsed_timers() {
+    let current_timestamp = system_timer::get_current_real_time() as usize;
+    let mut preempt = false;
+
+    for (expiry, timer_id) in get_timer_queue().pop_expired(current_timestamp) {
+        let timer = match get_timers().get_mut(&timer_id) {
+            Some(timer) => timer,
+            None => continue,
+        };
+        preempt |= threads::handle_timer_event(timer.thread_id, timer_id);
+
+        match timer.period {
+            Some(period) => {
+                timer.expiry = expiry + period;
+                get_timer_queue().insert(timer.expiry, timer_id);
+            }
+            None => {
+                get_timers().remove(&timer_id);
+            }
+        }
+    }
+
+    if preempt {
+        threads::schedule(None);
+    }
+}
@@ -0,0 +1,216 @@
+use crate::mmu;
+use crate::process::{self, ProcessId};
+use crate::threads::{self, ThreadId, TCB};
+use alloc::boxed::Box;
+use alloc::collections::btree_map::BTreeMap;
+use alloc::vec;
+use core::alloc::Layout;
+use rost_allocator::{Heap, HeapStats};
+use rost_api::syscalls::SyscallError;
+
+/// Memory of the `Allocate` syscall, separate from the kernel heap so user  
+/// threads cannot exhaust the memory the kernel needs. Ends below the mode stacks.
+pub(crate) const USER_HEAP_START: usize = 0x2200_0000;
+pub(crate) const USER_HEAP_END: usize = 0x22F0_0000;
+
+/// Allocation on the user heap, charged to the thread which allocated it.
+struct UserAllocation {
+    thread_id: ThreadId,
+    /// process whose heap holds the allocation
+    process_id: ProcessId,
+    layout: Layout,
+    /// bytes counted in `heap_used` of the thread
+    size: usize,
+}
+
+static mut USER_HEAP: Option<Heap> = None;
+static mut USER_ALLOCATIONS: Option<BTreeMap<usize, UserAllocation>> = None;
+
+fn get_user_heap<'a>() -> &'a mut Heap {
+    unsafe {
+        USER_HEAP.get_or_insert_with(|| {
+            let mut heap = Heap::empty();
+            // the user heap is never freed
+            Box::leak(init_heap(
+                &mut heap,
+                USER_HEAP_START,
+                USER_HEAP_END - USER_HEAP_START,
+            ));
+            heap
+        })
+    }
+}
+
+/// Hands memory user threads can write to to `heap` and returns its bookkeeping.
+///
+/// The bookkeeping lives on the kernel heap, so user threads cannot forge it.
+///
+/// # Safety
+/// The memory must be valid and unused, the bookkeeping must outlive the heap.
+pub(crate) unsafe fn init_heap(heap: &mut Heap, start: usize, size: usize) -> Box<[u32]> {
+    let mut metadata = vec![0u32; Heap::metadata_size(size) / 4].into_boxed_slice();
+    heap.init_with_metadata(start, size, metadata.as_mut_ptr() as *mut u8);
+    metadata
+}
+
+/// Returns the heap of a process, threads of the kernel process share the user heap.
+fn get_heap<'a>(process_id: ProcessId) -> &'a mut Heap {
+    match process::get_heap(process_id) {
+        Some(heap) => heap,
+        None => get_user_heap(),
+    }
+}
+
+fn get_user_allocations<'a>() -> &'a mut BTreeMap<usize, UserAllocation> {
+    unsafe { USER_ALLOCATIONS.get_or_insert_with(BTreeMap::new) }
+}
+
+/// Allocates memory for the running thread and charges it to its heap quota.
+///
+/// The memory is taken from the heap of the thread's process.  
+/// The allocation is charged with the size of the block the heap uses for it,  
+/// so many small allocations cannot slip past the quota.
+pub(crate) fn allocate(layout: Layout) -> Result<*mut u8, SyscallError> {
+    let thread = threads::get_current_thread();
+    let size = Heap::allocation_size(layout).ok_or(SyscallError::OutOfMemory)?;
+    if thread.heap_used + size > thread.heap_quota {
+        return Err(SyscallError::QuotaExceeded);
+    }
+
+    let ptr = get_heap(thread.process_id).allocate(layout);
+    if ptr.is_null() {
+        return Err(SyscallError::OutOfMemory);
+    }
+    thread.heap_used += size;
+    get_user_allocations().insert(
+        ptr as usize,
+        UserAllocation {
+            thread_id: thread.id,
+            process_id: thread.process_id,
+            layout,
+            size,
+        },
+    );
+    Ok(ptr)
+}
+
+/// Frees memory of `allocate` and credits it to the thread which allocated it.
+///
+/// Any thread of the same process may free the memory, e.g. a child its  
+/// boxed entry closure. Fails for pointers which were not allocated with  
+/// the same layout, so a bad pointer cannot corrupt the heap.
+pub(crate) fn deallocate(ptr: *mut u8, layout: Layout) -> Result<(), SyscallError> {
+    let process_id = threads::get_current_thread().process_id;
+    let allocations = get_user_allocations();
+    match allocations.get(&(ptr as usize)) {
+        Some(allocation) if allocation.layout == layout && allocation.process_id == process_id => {}
+        _ => return Err(SyscallError::InvalidArgument),
+    }
+    let allocation = allocations.remove(&(ptr as usize)).unwrap();
+
+    unsafe {
+        get_heap(process_id).deallocate(ptr, layout);
+    }
+    if let Some(thread) = threads::get_thread_by_id(allocation.thread_id) {
+        thread.heap_used -= allocation.size;
+    }
+    Ok(())
+}
+
+/// Returns true if `ptr` points into the user heap.
+pub(crate) fn contains(ptr: *mut u8) -> bool {
+    (USER_HEAP_START..USER_HEAP_END).contains(&(ptr as usize))
+}
+
+/// Allocates a stack for a thread of a process, which the thread has to access  
+/// in `ProcessorMode::User`.
+///
+/// Stacks are owned by the kernel and not charged to the quota of a thread.
+pub(crate) fn allocate_stack(process_id: ProcessId, layout: Layout) -> Option<*mut u8> {
+    let ptr = get_heap(process_id).allocate(layout);
+    if ptr.is_null() {
+        None
+    } else {
+        Some(ptr)
+    }
+}
+
+/// Frees a stack of `allocate_stack`.
+pub(crate) fn deallocate_stack(process_id: ProcessId, ptr: *mut u8, layout: Layout) {
+    unsafe {
+        get_heap(process_id).deallocate(ptr, layout);
+    }
+}
+
+/// Takes a section of the user heap for the image or the heap of a process.
+pub(crate) fn allocate_section() -> Option<usize> {
+    let layout = Layout::from_size_align(mmu::SECTION_SIZE, mmu::SECTION_SIZE).unwrap();
+    let ptr = get_user_heap().allocate(layout);
+    if ptr.is_null() {
+        None
+    } else {
+        Some(ptr as usize)
+    }
+}
+
+/// Gives a section of `allocate_section` back to the user heap.
+pub(crate) fn deallocate_section(section: usize) {
+    let layout = Layout::from_size_align(mmu::SECTION_SIZE, mmu::SECTION_SIZE).unwrap();
+    unsafe {
+        get_user_heap().deallocate(section as *mut u8, layout);
+    }
+}
+
+/// Charges the outstanding allocations of a stopped thread to its heir, usually its parent.
+///
+/// The memory may still be used by other threads of the process, so it stays  
+/// allocated and the heir's quota pays for it. Without an heir the allocations  
+/// get freed, which only happens to the main thread of a process.
+pub(crate) fn release_thread(thread: &mut TCB, heir_id: Option<ThreadId>) {
+    let thread_id = thread.id;
+    thread.heap_used = 0;
+    match heir_id.and_then(threads::get_thread_by_id) {
+        Some(heir) => {
+            for allocation in get_user_allocations()
+                .values_mut()
+                .filter(|allocation| allocation.thread_id == thread_id)
+            {
+                allocation.thread_id = heir.id;
+                heir.heap_used += allocation.size;
+            }
+        }
+        None => get_user_allocations().retain(|&ptr, allocation| {
+            if allocation.thread_id != thread_id {
+                return true;
+            }
+            unsafe {
+                get_heap(allocation.process_id).deallocate(ptr as *mut u8, allocation.layout);
+            }
+            false
+        }),
+    }
+}
+
+/// Forgets the allocations of a process whose heap gets freed as a whole.
+pub(crate) fn release_process(process_id: ProcessId) {
+    get_user_allocations().retain(|_, allocation| allocation.process_id != process_id);
+}
+
+pub fn get_user_heap_stats() -> HeapStats {
+    get_user_heap().stats()
+}
+
+/// Prints the user heap statistics and the heap usage of every thread.
+pub fn print_user_heap_usage() {
+    crate::println!("user heap: {}", get_user_heap_stats());
+    unsafe {
+        for thread in &threads::THREADS {
+            crate::println!(
+                "  thread {}: {:#X} of {:#X}",
+                thread.id,
+                thread.heap_used,
+                thread.heap_quota
+            );
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use crate::mmu::{self, UserAccess};
+use core::mem::{align_of, size_of};
+use rost_api::syscalls::{PeriodicParameters, SyscallError, TransferParameters};
+
+/// Types which user threads may pass to the kernel through memory.
+///
+/// # Safety
+/// Every bit pattern has to be a valid value of the type, the kernel reads  
+/// whatever the user thread wrote. Enums have to be read as their integer.
+pub(crate) unsafe trait UserData {}
+
+unsafe impl UserData for u8 {}
+unsafe impl UserData for usize {}
+unsafe impl UserData for PeriodicParameters {}
+unsafe impl UserData for TransferParameters {}
+
+/// Pointer to a value in the memory of the thread which made the syscall.
+pub(crate) struct UserPtr<T: UserData> {
+    ptr: *const T,
+}
+
+impl<T: UserData> UserPtr<T> {
+    pub(crate) fn new(ptr: *const T) -> Self {
+        UserPtr { ptr }
+    }
+
+    /// Copies the value after checking that the thread may read it.
+    pub(crate) fn read(&self) -> Result<T, SyscallError> {
+        UserSlice::new(self.ptr, 1).check(UserAccess::ReadOnly)?;
+        Ok(unsafe { core::ptr::read(self.ptr) })
+    }
+}
+
+/// Buffer of `length` values in the memory of the thread which made the syscall.
+///
+/// The kernel runs syscalls in the address space of the calling thread, so the  
+/// range is checked against the translation table the MMU currently uses before  
+/// the kernel touches it. Fails with `SyscallError::BadAddress` for memory the  
+/// thread may not access, misaligned pointers and ranges which overflow.  
+/// The borrowed slices must not outlive the syscall.
+pub(crate) struct UserSlice<T: UserData> {
+    ptr: *mut T,
+    length: usize,
+}
+
+impl<T: UserData> UserSlice<T> {
+    pub(crate) fn new(ptr: *const T, length: usize) -> Self {
+        UserSlice {
+            ptr: ptr as *mut T,
+            length,
+        }
+    }
+
+    pub(crate) fn new_mut(ptr: *mut T, length: usize) -> Self {
+        UserSlice { ptr, length }
+    }
+
+    fn check(&self, access: UserAccess) -> Result<(), SyscallError> {
+        let size = self
+            .length
+            .checked_mul(size_of::<T>())
+            .ok_or(SyscallError::BadAddress)?;
+        if self.ptr as usize & (align_of::<T>() - 1) != 0
+            || !mmu::is_user_accessible(self.ptr as usize, size, access)
+        {
+            return Err(SyscallError::BadAddress);
+        }
+        Ok(())
+    }
+
+    /// Borrows the buffer after checking that the thread may read it.
+    pub(crate) fn as_slice<'a>(&self) -> Result<&'a [T], SyscallError> {
+        self.check(UserAccess::ReadOnly)?;
+        if self.length == 0 {
+            return Ok(&[]);
+        }
+        Ok(unsafe { core::slice::from_raw_parts(self.ptr, self.length) })
+    }
+
+    /// Borrows the buffer after checking that the thread may write it.
+    pub(crate) fn as_mut_slice<'a>(&mut self) -> Result<&'a mut [T], SyscallError> {
+        self.check(UserAccess::ReadWrite)?;
+        if self.length == 0 {
+            return Ok(&mut []);
+        }
+        Ok(unsafe { core::slice::from_raw_parts_mut(self.ptr, self.length) })
+    }
+}
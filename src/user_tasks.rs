@@ -1,18 +1,25 @@
+//! Tasks of the REPL, which run in user threads built into the kernel.
+//!
+//! User threads cannot access kernel memory, so the tasks only use syscalls  
+//! and statics placed in the `.user_data` section.
+
 use core::time::Duration;
 
-use crate::system_timer;
-use crate::{alloc::borrow::ToOwned, print};
-use crate::{allocator, println, threads};
+use crate::{alloc::borrow::ToOwned, print, println};
 use alloc::boxed::Box;
+use alloc::collections::vec_deque::VecDeque;
 use alloc::string::String;
 use alloc::vec::Vec;
-use log::trace;
 use rand::Rng;
 use rand::SeedableRng;
 use rand_pcg::Pcg64;
+use rost_api::syscalls::KernelInfo;
 
+#[link_section = ".user_data"]
 pub static mut RNG: Option<Pcg64> = None;
+#[link_section = ".user_data"]
 pub static mut TASK3_ACTIVE: bool = false;
+#[link_section = ".user_data"]
 pub static mut TASK4_ACTIVE: bool = false;
 
 /// prints a character for a random range between min and max
@@ -27,20 +34,21 @@ where
     }
 }
 
-pub fn task4_dbgu(last_char: char) {
+fn task4_dbgu(last_char: char) {
     if unsafe { TASK4_ACTIVE } && last_char != 'q' {
         rost_api::syscalls::create_thread(move || {
             // print 3 times and wait between
             print_character_random(last_char, 5, 30);
-            rost_api::syscalls::sleep_ms(1000);
+            rost_api::syscalls::sleep_ms(1000).unwrap();
             if unsafe { TASK4_ACTIVE } {
                 print_character_random(last_char, 5, 30);
             }
-            rost_api::syscalls::sleep_ms(1000);
+            rost_api::syscalls::sleep_ms(1000).unwrap();
             if unsafe { TASK4_ACTIVE } {
                 print_character_random(last_char, 5, 30);
             }
-        });
+        })
+        .unwrap();
     }
 }
 
@@ -52,6 +60,7 @@ const KEY_TAB: char = 0x9 as char;
 const KEY_ESCAPE: u8 = 0x1B;
 const KEY_LEFT_SQUARE_BRACKET: u8 = 0x5B;
 
+#[link_section = ".user_data"]
 static mut THREAD_TEST_COUNT: usize = 0;
 
 struct Command {
@@ -59,6 +68,7 @@ struct Command {
     handler: Box<dyn FnMut() + 'static>,
 }
 
+#[link_section = ".user_data"]
 static mut COMMANDS: Vec<Command> = Vec::new();
 
 impl Command {
@@ -76,34 +86,47 @@ fn add_command(name: &str, handler: impl FnMut() + 'static) {
     }
 }
 
+fn print_thread_control_result(
+    command: &str,
+    thread_id: usize,
+    result: Result<(), rost_api::syscalls::SyscallError>,
+) {
+    match result {
+        Ok(()) => {
+            println!("{}: thread {}", command, thread_id);
+        }
+        Err(error) => {
+            println!("{}: thread {} failed: {:?}", command, thread_id, error);
+        }
+    }
+}
+
 /// Simple Read–eval–print loop with some basic commands
 pub fn read_eval_print_loop() {
     add_command("task3", || unsafe {
         TASK3_ACTIVE = true;
-        let id = rost_api::syscalls::create_thread(crate::custom_user_code_thread);
-        rost_api::syscalls::join_thread(id, None);
+        let id = rost_api::syscalls::create_thread(crate::custom_user_code_thread).unwrap();
+        let _ = rost_api::syscalls::join_thread(id, None);
         TASK3_ACTIVE = false;
     });
     add_command("task4", || unsafe {
         TASK4_ACTIVE = true;
-        rost_api::syscalls::subscribe(rost_api::syscalls::ThreadServices::DBGU);
+        rost_api::syscalls::subscribe(rost_api::syscalls::ThreadServices::DBGU).unwrap();
         loop {
-            let last_char = rost_api::syscalls::receive_character_from_dbgu() as char;
+            let last_char = rost_api::syscalls::receive_character_from_dbgu().unwrap() as char;
             if last_char == 'q' {
                 break;
             }
+            task4_dbgu(last_char);
         }
         TASK4_ACTIVE = false;
     });
     add_command("task5", || {
-        /// wait for x realtime clock units
-        fn busy_wait_ms(mut units: usize) {
-            units /= system_timer::get_real_time_unit_interval().as_millis() as usize;
-            let last = rost_api::syscalls::get_current_realtime();
-            loop {
-                if rost_api::syscalls::get_current_realtime() - last >= units {
-                    break;
-                }
+        /// wait for x milliseconds without sleeping
+        fn busy_wait_ms(time_ms: u64) {
+            let last = rost_api::syscalls::get_uptime().unwrap();
+            while rost_api::syscalls::get_uptime().unwrap() - last < Duration::from_millis(time_ms)
+            {
             }
         }
         fn run_thread(last_char: char) {
@@ -116,15 +139,16 @@ pub fn read_eval_print_loop() {
                 } else {
                     for _ in 0..11 {
                         print!("{}", last_char);
-                        rost_api::syscalls::sleep_ms(500);
+                        rost_api::syscalls::sleep_ms(500).unwrap();
                     }
                 }
-            });
+            })
+            .unwrap();
         }
 
-        rost_api::syscalls::subscribe(rost_api::syscalls::ThreadServices::DBGU);
+        rost_api::syscalls::subscribe(rost_api::syscalls::ThreadServices::DBGU).unwrap();
         loop {
-            let last_char = rost_api::syscalls::receive_character_from_dbgu() as char;
+            let last_char = rost_api::syscalls::receive_character_from_dbgu().unwrap() as char;
             if last_char == 'q' {
                 break;
             }
@@ -133,14 +157,14 @@ pub fn read_eval_print_loop() {
         }
     });
     add_command("uptime", || {
-        println!(
-            "uptime: {:?}",
-            system_timer::get_current_real_time_as_duration()
-        );
+        println!("uptime: {:?}", rost_api::syscalls::get_uptime().unwrap());
     });
     add_command("custom_code", || {
-        let id = rost_api::syscalls::create_thread(crate::custom_user_code_thread);
-        rost_api::syscalls::join_thread(id, None);
+        let id = rost_api::syscalls::create_thread(crate::custom_user_code_thread).unwrap();
+        println!(
+            "custom code exited: {:?}",
+            rost_api::syscalls::join_thread(id, None)
+        );
     });
     add_command("software_interrupt", || unsafe {
         asm!("swi #99");
@@ -156,37 +180,299 @@ pub fn read_eval_print_loop() {
         );
     });
     add_command("heap_size", || {
-        println!(
-            "current heap size: {:#X}, left: {:#X}",
-            allocator::get_current_heap_size(),
-            allocator::get_heap_size_left()
-        );
+        rost_api::syscalls::print_kernel_info(KernelInfo::HeapSize).unwrap();
+    });
+    add_command("heap_stats", || {
+        rost_api::syscalls::print_kernel_info(KernelInfo::HeapStats).unwrap();
     });
     add_command("threads", || {
-        threads::print_threads();
+        rost_api::syscalls::print_kernel_info(KernelInfo::Threads).unwrap();
+    });
+    add_command("processes", || {
+        rost_api::syscalls::print_kernel_info(KernelInfo::Processes).unwrap();
     });
     add_command("sleep_test", || {
         println!(
             "sleep with duration 5s - start_at: {:?}",
-            system_timer::get_current_real_time_as_duration()
+            rost_api::syscalls::get_uptime().unwrap()
         );
         println!(
             "reported_duration: {:?}",
-            core::time::Duration::from_millis(rost_api::syscalls::sleep_ms(5000) as u64)
+            core::time::Duration::from_millis(rost_api::syscalls::sleep_ms(5000).unwrap() as u64)
+        );
+        println!("stop_at: {:?}", rost_api::syscalls::get_uptime().unwrap());
+    });
+    add_command("periodic_test", || {
+        println!(
+            "periodic thread with period 100ms - start_at: {:?}",
+            rost_api::syscalls::get_uptime().unwrap()
+        );
+        let id = rost_api::syscalls::create_periodic_thread(100, 50, || {
+            for period in 0..10 {
+                let deadline_misses = rost_api::syscalls::wait_next_period().unwrap();
+                println!(
+                    "period {} at {:?} deadline_misses: {}",
+                    period,
+                    rost_api::syscalls::get_uptime().unwrap(),
+                    deadline_misses
+                );
+            }
+        })
+        .unwrap();
+        let _ = rost_api::syscalls::join_thread(id, None);
+    });
+    add_command("timeout_test", || {
+        let id = rost_api::syscalls::create_thread(|| {
+            rost_api::syscalls::sleep_ms(1000).unwrap();
+        })
+        .unwrap();
+        println!(
+            "join with timeout 200ms: {:?}",
+            rost_api::syscalls::join_thread(id, Some(200))
+        );
+        println!(
+            "join without timeout: {:?}",
+            rost_api::syscalls::join_thread(id, None)
+        );
+
+        rost_api::syscalls::subscribe(rost_api::syscalls::ThreadServices::DBGU).unwrap();
+        println!("waiting 2s for a character...");
+        println!(
+            "received: {:?}",
+            rost_api::syscalls::receive_character_from_dbgu_timeout(Some(2000))
+        );
+        rost_api::syscalls::unsubscribe(rost_api::syscalls::ThreadServices::DBGU).unwrap();
+    });
+    add_command("sync_test", || {
+        use rost_api::sync::{Condvar, Mutex, Semaphore};
+        // bounded buffer: producers wait for free slots, the consumer for items
+        let items: &'static (Mutex<VecDeque<usize>>, Condvar) =
+            Box::leak(Box::new((Mutex::new(VecDeque::new()), Condvar::new())));
+        let free_slots: &'static Semaphore = Box::leak(Box::new(Semaphore::new(2)));
+
+        let mut producer_ids = Vec::new();
+        for producer in 0..3 {
+            producer_ids.push(
+                rost_api::syscalls::create_thread(move || {
+                    for item in 0..3 {
+                        free_slots.acquire();
+                        items.0.lock().push_back(producer * 10 + item);
+                        items.1.notify_one();
+                    }
+                })
+                .unwrap(),
+            );
+        }
+
+        for _ in 0..9 {
+            let mut queue = items.0.lock();
+            while queue.is_empty() {
+                queue = items.1.wait(queue);
+            }
+            let item = queue.pop_front().unwrap();
+            drop(queue);
+            free_slots.release();
+            println!("consumed item {} of producer {}", item % 10, item / 10);
+        }
+        for id in producer_ids {
+            let _ = rost_api::syscalls::join_thread(id, None);
+        }
+    });
+    add_command("message_test", || {
+        use rost_api::syscalls::{receive_message, send_message, SyscallError};
+        let consumer_id = rost_api::syscalls::create_thread(|| {
+            let mut buffer = [0u8; 32];
+            loop {
+                match receive_message(&mut buffer, Some(1000)) {
+                    Ok(length) => {
+                        println!(
+                            "received: {}",
+                            core::str::from_utf8(&buffer[..length]).unwrap_or("<invalid utf8>")
+                        );
+                    }
+                    Err(SyscallError::TimedOut) => break,
+                    Err(error) => {
+                        println!("receive failed: {:?}", error);
+                    }
+                }
+                rost_api::syscalls::sleep_ms(100).unwrap();
+            }
+        })
+        .unwrap();
+
+        for number in 0..24 {
+            let message = alloc::format!("message {}", number);
+            // back-pressure: retry until the consumer made room in its mailbox
+            while send_message(consumer_id, message.as_bytes()) == Err(SyscallError::MailboxFull) {
+                println!("mailbox full, waiting...");
+                rost_api::syscalls::sleep_ms(200).unwrap();
+            }
+        }
+        let _ = rost_api::syscalls::join_thread(consumer_id, None);
+    });
+    add_command("pipe_test", || {
+        use core::fmt::Write;
+        use rost_api::pipe::Pipe;
+        // producer -> "numbers" -> squaring worker -> "squares" -> REPL output
+        // created here, so both threads can open them in any order
+        let _numbers = Pipe::create("numbers").unwrap();
+        let squares = Pipe::create("squares").unwrap();
+
+        let producer_id = rost_api::syscalls::create_thread(|| {
+            let mut numbers = Pipe::open("numbers").unwrap();
+            for number in 1..=20 {
+                writeln!(numbers, "{}", number).unwrap();
+            }
+            writeln!(numbers, "end").unwrap();
+        })
+        .unwrap();
+        let worker_id = rost_api::syscalls::create_thread(|| {
+            let numbers = Pipe::open("numbers").unwrap();
+            let mut squares = Pipe::open("squares").unwrap();
+            let mut line = String::new();
+            let mut buffer = [0u8; 16];
+            loop {
+                let length = numbers.read(&mut buffer).unwrap();
+                for &byte in &buffer[..length] {
+                    if byte != b'\n' {
+                        line.push(byte as char);
+                        continue;
+                    }
+                    if line == "end" {
+                        writeln!(squares, "end").unwrap();
+                        return;
+                    }
+                    let number: usize = line.parse().unwrap();
+                    writeln!(squares, "{}^2 = {}", number, number * number).unwrap();
+                    line.clear();
+                }
+            }
+        })
+        .unwrap();
+
+        let mut buffer = [0u8; 64];
+        let mut line = String::new();
+        'reading: loop {
+            let length = squares.read(&mut buffer).unwrap();
+            for &byte in &buffer[..length] {
+                line.push(byte as char);
+                if byte == b'\n' {
+                    print!("{}", line);
+                    if line == "end\n" {
+                        break 'reading;
+                    }
+                    line.clear();
+                }
+            }
+        }
+        let _ = rost_api::syscalls::join_thread(producer_id, None);
+        let _ = rost_api::syscalls::join_thread(worker_id, None);
+    });
+    add_command("wait_any_test", || {
+        use rost_api::syscalls::{wait_any, Event};
+        let worker_id = rost_api::syscalls::create_thread(|| {
+            rost_api::syscalls::sleep_ms(5000).unwrap();
+        })
+        .unwrap();
+
+        rost_api::syscalls::subscribe(rost_api::syscalls::ThreadServices::DBGU).unwrap();
+        println!("press keys, the worker finishes after 5s");
+        let events = [Event::dbgu(), Event::thread_exit(worker_id)];
+        loop {
+            match wait_any(&events, Some(1000)) {
+                Ok(0) => {
+                    println!(
+                        "key: {}",
+                        rost_api::syscalls::receive_character_from_dbgu().unwrap() as char
+                    );
+                }
+                Ok(_) => {
+                    println!(
+                        "worker finished: {:?}",
+                        rost_api::syscalls::join_thread(worker_id, None)
+                    );
+                    break;
+                }
+                Err(_) => {
+                    println!("tick");
+                }
+            }
+        }
+        rost_api::syscalls::unsubscribe(rost_api::syscalls::ThreadServices::DBGU).unwrap();
+    });
+    add_command("signal_test", || {
+        use rost_api::syscalls::{register_signal_handler, send_signal, Signal};
+        extern "C" fn print_signal(signal: Signal) {
+            println!("handling {:?} in thread", signal);
+        }
+
+        let worker_id = rost_api::syscalls::create_thread(|| {
+            register_signal_handler(Signal::User1, Some(print_signal)).unwrap();
+            register_signal_handler(Signal::SegmentationFault, Some(print_signal)).unwrap();
+            for _ in 0..3 {
+                println!("worker sleeping");
+                rost_api::syscalls::sleep_ms(500).unwrap();
+            }
+            // the fault is delivered as signal, afterwards the worker exits
+            unsafe {
+                asm!(
+                    "
+                 ldr {tmp}, =0x90000000
+                 str {tmp}, [{tmp}]", tmp = out(reg) _
+                );
+            }
+        })
+        .unwrap();
+
+        for _ in 0..3 {
+            rost_api::syscalls::sleep_ms(250).unwrap();
+            println!("send User1: {:?}", send_signal(worker_id, Signal::User1));
+        }
+        println!(
+            "send User2 without handler: {:?}",
+            send_signal(worker_id, Signal::User2)
+        );
+        println!(
+            "worker exited: {:?}",
+            rost_api::syscalls::join_thread(worker_id, None)
         );
         println!(
-            "stop_at: {:?}",
-            system_timer::get_current_real_time_as_duration()
+            "signal_return without handler: {:?}",
+            rost_api::syscalls::signal_return()
+        );
+    });
+    add_command("timer_test", || {
+        use rost_api::syscalls::{cancel_timer, create_timer, receive_timer_tick};
+        rost_api::syscalls::subscribe(rost_api::syscalls::ThreadServices::Timer).unwrap();
+        let periodic_id = create_timer(300, true).unwrap();
+        let oneshot_id = create_timer(1000, false).unwrap();
+        println!(
+            "periodic timer {} every 300ms, one-shot timer {} after 1s",
+            periodic_id, oneshot_id
+        );
+        for _ in 0..8 {
+            let timer_id = receive_timer_tick().unwrap();
+            println!(
+                "tick of timer {} at {:?}",
+                timer_id,
+                rost_api::syscalls::get_uptime().unwrap()
+            );
+        }
+        println!("cancel periodic timer: {:?}", cancel_timer(periodic_id));
+        println!(
+            "cancel expired one-shot timer: {:?}",
+            cancel_timer(oneshot_id)
         );
+        rost_api::syscalls::unsubscribe(rost_api::syscalls::ThreadServices::Timer).unwrap();
     });
     add_command("thread_test", || unsafe {
         THREAD_TEST_COUNT = 0;
         let mut thread_ids: Vec<usize> = Vec::new();
 
         fn sleep_ms_thread(id: usize, time_ms: usize) {
-            let slept_duration = core::time::Duration::from_millis(rost_api::syscalls::sleep_ms(
-                id * time_ms,
-            ) as u64);
+            let slept_duration = core::time::Duration::from_millis(
+                rost_api::syscalls::sleep_ms(id * time_ms).unwrap() as u64,
+            );
             let expected_duration = core::time::Duration::from_millis((id * time_ms) as u64);
             assert!(
                 slept_duration
@@ -201,25 +487,24 @@ pub fn read_eval_print_loop() {
         }
 
         for id in 0..=250 {
-            thread_ids.push(rost_api::syscalls::create_thread(move || {
-                THREAD_TEST_COUNT += 1;
-                sleep_ms_thread(id, 50);
-                THREAD_TEST_COUNT += 1;
-                if THREAD_TEST_COUNT == 500 {
-                    threads::print_threads();
-                    println!(
-                        "current heap size: {:#X}, left: {:#X}",
-                        allocator::get_current_heap_size(),
-                        allocator::get_heap_size_left()
-                    );
-                }
-                sleep_ms_thread(id, 75);
-                THREAD_TEST_COUNT += 1;
-            }));
+            thread_ids.push(
+                rost_api::syscalls::create_thread(move || {
+                    THREAD_TEST_COUNT += 1;
+                    sleep_ms_thread(id, 50);
+                    THREAD_TEST_COUNT += 1;
+                    if THREAD_TEST_COUNT == 500 {
+                        rost_api::syscalls::print_kernel_info(KernelInfo::Threads).unwrap();
+                        rost_api::syscalls::print_kernel_info(KernelInfo::HeapSize).unwrap();
+                    }
+                    sleep_ms_thread(id, 75);
+                    THREAD_TEST_COUNT += 1;
+                })
+                .unwrap(),
+            );
         }
 
         for id in thread_ids {
-            rost_api::syscalls::join_thread(id, None);
+            let _ = rost_api::syscalls::join_thread(id, None);
         }
 
         assert_eq!(THREAD_TEST_COUNT, 753);
@@ -231,24 +516,28 @@ pub fn read_eval_print_loop() {
         let mut thread_ids: Vec<usize> = Vec::new();
 
         for id in 0..3 {
-            thread_ids.push(rost_api::syscalls::create_thread(move || {
-                rost_api::syscalls::subscribe(rost_api::syscalls::ThreadServices::DBGU);
-                println!(
-                    "dbgu_test: thread {} got {}",
-                    id,
-                    rost_api::syscalls::receive_character_from_dbgu() as char
-                );
-                rost_api::syscalls::sleep_ms(50);
-                println!(
-                    "dbgu_test: thread {} got {}",
-                    id,
-                    rost_api::syscalls::receive_character_from_dbgu() as char
-                );
-            }));
+            thread_ids.push(
+                rost_api::syscalls::create_thread(move || {
+                    rost_api::syscalls::subscribe(rost_api::syscalls::ThreadServices::DBGU)
+                        .unwrap();
+                    println!(
+                        "dbgu_test: thread {} got {}",
+                        id,
+                        rost_api::syscalls::receive_character_from_dbgu().unwrap() as char
+                    );
+                    rost_api::syscalls::sleep_ms(50).unwrap();
+                    println!(
+                        "dbgu_test: thread {} got {}",
+                        id,
+                        rost_api::syscalls::receive_character_from_dbgu().unwrap() as char
+                    );
+                })
+                .unwrap(),
+            );
         }
 
         for id in thread_ids {
-            rost_api::syscalls::join_thread(id, None);
+            let _ = rost_api::syscalls::join_thread(id, None);
         }
 
         println!("dbgu_test: the end");
@@ -261,7 +550,7 @@ pub fn read_eval_print_loop() {
     let mut history: Vec<String> = Vec::new();
 
     loop {
-        rost_api::syscalls::subscribe(rost_api::syscalls::ThreadServices::DBGU);
+        rost_api::syscalls::subscribe(rost_api::syscalls::ThreadServices::DBGU).unwrap();
 
         let mut char_buf = alloc::string::String::new();
 
@@ -273,6 +562,7 @@ pub fn read_eval_print_loop() {
                 print!("{} ", cmd.name);
             }
         }
+        print!("\nthread control: kill <id> stop <id> cont <id>");
         print!("\n$ ");
 
         let mut found_autocomplete_commands: Vec<&str> = Vec::new();
@@ -287,11 +577,12 @@ pub fn read_eval_print_loop() {
         }
 
         loop {
-            let last_char: char = rost_api::syscalls::receive_character_from_dbgu() as char;
+            let last_char: char =
+                rost_api::syscalls::receive_character_from_dbgu().unwrap() as char;
 
             if last_char == KEY_ENTER {
                 println!();
-                rost_api::syscalls::unsubscribe(rost_api::syscalls::ThreadServices::DBGU);
+                rost_api::syscalls::unsubscribe(rost_api::syscalls::ThreadServices::DBGU).unwrap();
 
                 if !char_buf.is_empty() {
                     if let Some(pos) = history.iter().position(|s| **s == char_buf) {
@@ -407,23 +698,84 @@ pub fn read_eval_print_loop() {
             }
         }
 
-        trace!(
-            "current heap size: {:#X}, left: {:#X}",
-            allocator::get_current_heap_size(),
-            allocator::get_heap_size_left()
-        );
-
         unsafe {
             if let Some(cmd) = COMMANDS.iter_mut().find(|c| c.name == char_buf.as_str()) {
                 //println!("Executing command: {}", cmd.name);
-                let id = rost_api::syscalls::create_thread(move || (cmd.handler)());
-                rost_api::syscalls::join_thread(id, None);
+                let id = rost_api::syscalls::create_thread(move || (cmd.handler)()).unwrap();
+                let _ = rost_api::syscalls::join_thread(id, None);
             } else {
-                // builtin commands
-                match char_buf.as_str() {
-                    "quit" => {
+                // builtin commands, run in the repl thread which is
+                // an ancestor of all threads started by commands
+                let mut words = char_buf.split_whitespace();
+                let command = words.next().unwrap_or_default();
+                let thread_id = words.next().and_then(|id| id.parse::<usize>().ok());
+                match (command, thread_id) {
+                    ("quit", None) => {
                         break;
                     }
+                    ("kill", Some(id)) => {
+                        print_thread_control_result(
+                            "kill",
+                            id,
+                            rost_api::syscalls::kill_thread(id),
+                        );
+                    }
+                    ("stop", Some(id)) => {
+                        print_thread_control_result(
+                            "stop",
+                            id,
+                            rost_api::syscalls::suspend_thread(id),
+                        );
+                    }
+                    ("cont", Some(id)) => {
+                        print_thread_control_result(
+                            "cont",
+                            id,
+                            rost_api::syscalls::resume_thread(id),
+                        );
+                    }
+                    ("spawn", None) => {
+                        // the custom user code loaded into qemu, every process gets a copy
+                        let image = core::slice::from_raw_parts(
+                            crate::memory::USER_CODE_START as *const u8,
+                            rost_api::syscalls::MAX_PROCESS_IMAGE_SIZE,
+                        );
+                        if image[..4] == [0; 4] {
+                            println!("-> no custom user code loaded");
+                        } else {
+                            match rost_api::syscalls::spawn_process(image) {
+                                Ok(id) => {
+                                    println!("spawn: process with main thread {}", id);
+                                }
+                                Err(error) => {
+                                    println!("spawn failed: {:?}", error);
+                                }
+                            }
+                        }
+                    }
+                    ("strace", None) => {
+                        rost_api::syscalls::print_kernel_info(KernelInfo::SyscallTrace).unwrap();
+                    }
+                    ("quota", Some(id)) => {
+                        match words.next().and_then(|quota| quota.parse::<usize>().ok()) {
+                            Some(quota) => print_thread_control_result(
+                                "quota",
+                                id,
+                                rost_api::syscalls::set_heap_quota(id, quota),
+                            ),
+                            None => {
+                                println!("-> usage: quota <thread id> <bytes>");
+                            }
+                        }
+                    }
+                    ("strace", Some(id)) => {
+                        let enabled = words.next() != Some("off");
+                        print_thread_control_result(
+                            "strace",
+                            id,
+                            rost_api::syscalls::trace_thread(id, enabled),
+                        );
+                    }
                     _ => {
                         println!("-> Unknown command: {}", char_buf);
                     }
@@ -2,5 +2,7 @@
 parent_path=$( cd "$(dirname "${BASH_SOURCE[0]}")" ; pwd -P )
 
 cd "$parent_path"
-mkdir target
-arm-none-eabi-gcc -nostdlib -Wl,-Tmemory.x src/main.c -march=armv4t -mthumb-interwork  -e main -o target/usercode_c.o
\ No newline at end of file
+mkdir -p target
+rustc --edition 2018 -o target/gen_c_header ../api/tools/gen_c_header.rs
+target/gen_c_header ../api/src include/rost.h
+arm-none-eabi-gcc -nostdlib -Wl,-Tmemory.x -Iinclude src/main.c -march=armv4t -mthumb-interwork  -e main -o target/usercode_c.o
@@ -0,0 +1,610 @@
+/* rost.h - syscalls of rOSt for C programs.
+ *
+ * Generated by api/tools/gen_c_header.rs from api/src/syscalls.rs, do not edit.
+ *
+ * Every syscall returns a uintptr_t, values of at least ROST_SYSCALL_ERROR_MIN
+ * are errors which rost_error_code decodes.
+ */
+
+#ifndef ROST_H
+#define ROST_H
+
+#include <stdint.h>
+
+#define ROST_SYSCALL_SEND_DBGU 10
+#define ROST_SYSCALL_RECEIVE_DBGU 11
+#define ROST_SYSCALL_WRITE_DBGU 12
+#define ROST_SYSCALL_READ_DBGU 13
+#define ROST_SYSCALL_PRINT_KERNEL_INFO 14
+#define ROST_SYSCALL_ALLOCATE 20
+#define ROST_SYSCALL_DEALLOCATE 21
+#define ROST_SYSCALL_SET_HEAP_QUOTA 22
+#define ROST_SYSCALL_CREATE_THREAD 30
+#define ROST_SYSCALL_EXIT_THREAD 31
+#define ROST_SYSCALL_YIELD_THREAD 32
+#define ROST_SYSCALL_JOIN_THREAD 33
+#define ROST_SYSCALL_SUBSCRIBE 34
+#define ROST_SYSCALL_UNSUBSCRIBE 35
+#define ROST_SYSCALL_SET_PRIORITY 36
+#define ROST_SYSCALL_GET_PRIORITY 37
+#define ROST_SYSCALL_CREATE_PERIODIC_THREAD 38
+#define ROST_SYSCALL_WAIT_NEXT_PERIOD 39
+#define ROST_SYSCALL_GET_CURRENT_REAL_TIME 40
+#define ROST_SYSCALL_SLEEP 41
+#define ROST_SYSCALL_CREATE_TIMER 42
+#define ROST_SYSCALL_CANCEL_TIMER 43
+#define ROST_SYSCALL_RECEIVE_TIMER 44
+#define ROST_SYSCALL_GET_UPTIME 45
+#define ROST_SYSCALL_KILL_THREAD 50
+#define ROST_SYSCALL_SUSPEND_THREAD 51
+#define ROST_SYSCALL_RESUME_THREAD 52
+#define ROST_SYSCALL_TRACE_THREAD 53
+#define ROST_SYSCALL_SPAWN_PROCESS 54
+#define ROST_SYSCALL_CREATE_MUTEX 60
+#define ROST_SYSCALL_LOCK_MUTEX 61
+#define ROST_SYSCALL_UNLOCK_MUTEX 62
+#define ROST_SYSCALL_CREATE_SEMAPHORE 63
+#define ROST_SYSCALL_WAIT_SEMAPHORE 64
+#define ROST_SYSCALL_SIGNAL_SEMAPHORE 65
+#define ROST_SYSCALL_CREATE_CONDVAR 66
+#define ROST_SYSCALL_WAIT_CONDVAR 67
+#define ROST_SYSCALL_SIGNAL_CONDVAR 68
+#define ROST_SYSCALL_BROADCAST_CONDVAR 69
+#define ROST_SYSCALL_DESTROY_SYNC_OBJECT 70
+#define ROST_SYSCALL_SEND_MESSAGE 80
+#define ROST_SYSCALL_RECEIVE_MESSAGE 81
+#define ROST_SYSCALL_CREATE_PIPE 90
+#define ROST_SYSCALL_OPEN_PIPE 91
+#define ROST_SYSCALL_READ_PIPE 92
+#define ROST_SYSCALL_WRITE_PIPE 93
+#define ROST_SYSCALL_CLOSE_PIPE 94
+#define ROST_SYSCALL_WAIT_ANY 100
+#define ROST_SYSCALL_REGISTER_SIGNAL_HANDLER 110
+#define ROST_SYSCALL_SEND_SIGNAL 111
+#define ROST_SYSCALL_SIGNAL_RETURN 112
+
+/*
+ * Errors of the syscalls, shared by all of them.
+ *
+ * The kernel encodes an error as `usize::MAX - code` in the return register,
+ * so results stay below `SYSCALL_ERROR_MIN`. The codes are stable, new
+ * errors only get appended. Codes unknown to this version of the api
+ * decode to `SyscallError::Unknown`.
+ */
+enum rost_error {
+    /* the timeout of a blocking syscall elapsed before the awaited event */
+    ROST_ERROR_TIMED_OUT = 0,
+    /* the kernel does not know the syscall id */
+    ROST_ERROR_UNKNOWN_SYSCALL = 1,
+    /* an argument is out of range, e.g. an unknown service or signal or an invalid layout */
+    ROST_ERROR_INVALID_ARGUMENT = 2,
+    /* no thread with this id exists, it already stopped or was joined */
+    ROST_ERROR_UNKNOWN_THREAD = 3,
+    /* the thread may not do this, e.g. kill a thread which is not its descendant */
+    ROST_ERROR_NOT_PERMITTED = 4,
+    /* the service is already subscribed by the thread */
+    ROST_ERROR_ALREADY_SUBSCRIBED = 5,
+    /* the service has to be subscribed first */
+    ROST_ERROR_NOT_SUBSCRIBED = 6,
+    /* a non-blocking receive found nothing to receive */
+    ROST_ERROR_WOULD_BLOCK = 7,
+    /* the kernel or user heap is exhausted */
+    ROST_ERROR_OUT_OF_MEMORY = 8,
+    /*
+     * the synchronization object does not exist, has another type or the operation
+     * is not allowed, e.g. unlocking a mutex owned by another thread
+     */
+    ROST_ERROR_INVALID_SYNC_OBJECT = 9,
+    /* the mailbox of the receiver holds `MAILBOX_CAPACITY` messages, try again later */
+    ROST_ERROR_MAILBOX_FULL = 10,
+    /* the message is larger than `MAX_MESSAGE_SIZE` */
+    ROST_ERROR_MESSAGE_TOO_LARGE = 11,
+    /* the next message is larger than the buffer, it stays in the mailbox */
+    ROST_ERROR_BUFFER_TOO_SMALL = 12,
+    /* `create_pipe` was called with the name of an existing pipe */
+    ROST_ERROR_PIPE_ALREADY_EXISTS = 13,
+    /* no pipe with this name or id exists, or it was closed while waiting */
+    ROST_ERROR_PIPE_NOT_FOUND = 14,
+    /* the pipe name is empty, longer than `MAX_PIPE_NAME_LENGTH` or not valid UTF-8 */
+    ROST_ERROR_INVALID_NAME = 15,
+    /* the timer does not exist anymore or belongs to another thread */
+    ROST_ERROR_UNKNOWN_TIMER = 16,
+    /* a buffer passed to the kernel is not in user memory */
+    ROST_ERROR_BAD_ADDRESS = 17,
+    /* the allocation would exceed the heap quota of the thread */
+    ROST_ERROR_QUOTA_EXCEEDED = 18,
+    /* the thread to join is not a child of the calling thread */
+    ROST_ERROR_NOT_A_CHILD = 19,
+    /* the thread to join was adopted after its parent stopped, nobody can join it */
+    ROST_ERROR_DETACHED = 20,
+};
+
+enum rost_thread_service {
+    ROST_SERVICE_DBGU = 10,
+    /* ticks of the timers created with `create_timer` */
+    ROST_SERVICE_TIMER = 20,
+};
+
+/* How long `read_dbgu` waits for characters. */
+enum rost_read_mode {
+    /* returns the received characters, fails with `SyscallError::WouldBlock` if there are none */
+    ROST_READ_NON_BLOCKING = 0,
+    /* waits for the first character, then returns it with all other received characters */
+    ROST_READ_AVAILABLE = 1,
+    /* waits until the buffer is full */
+    ROST_READ_FULL = 2,
+};
+
+/* Kernel state printed by `print_kernel_info`. */
+enum rost_kernel_info {
+    /* all threads with their state, priority and stack usage */
+    ROST_KERNEL_INFO_THREADS = 0,
+    /* used and free kernel heap, usage of the user heap per thread */
+    ROST_KERNEL_INFO_HEAP_SIZE = 1,
+    /* statistics of the kernel heap and its slab caches */
+    ROST_KERNEL_INFO_HEAP_STATS = 2,
+    /* syscalls recorded for threads traced with `trace_thread`, cleared afterwards */
+    ROST_KERNEL_INFO_SYSCALL_TRACE = 3,
+    /* all processes with their main thread and heap usage */
+    ROST_KERNEL_INFO_PROCESSES = 4,
+};
+
+/* Kinds of event sources `wait_any` can wait for. */
+enum rost_event_kind {
+    /* a character is available with the subscribed `ThreadServices::DBGU` */
+    ROST_EVENT_DBGU = 0,
+    /*
+     * the child thread `id` exited and can be joined, fails like `join_thread`,
+     * e.g. with `SyscallError::UnknownThread` if there is no such thread
+     */
+    ROST_EVENT_THREAD_EXIT = 1,
+    /* a message is in the mailbox of the current thread */
+    ROST_EVENT_MESSAGE = 2,
+    /* the pipe `id` can be read without waiting */
+    ROST_EVENT_PIPE_READABLE = 3,
+    /* a tick is available with the subscribed `ThreadServices::Timer` */
+    ROST_EVENT_TIMER = 4,
+};
+
+/* Signals delivered to user threads, numbered like their POSIX counterparts. */
+enum rost_signal {
+    ROST_SIGNAL_INTERRUPT = 2,
+    /* raised by the kernel for an undefined instruction in user code */
+    ROST_SIGNAL_ILLEGAL_INSTRUCTION = 4,
+    ROST_SIGNAL_USER1 = 10,
+    /* raised by the kernel for a data or prefetch abort in user code */
+    ROST_SIGNAL_SEGMENTATION_FAULT = 11,
+    ROST_SIGNAL_USER2 = 12,
+    ROST_SIGNAL_TERMINATE = 15,
+};
+
+/* Maximum amount of messages waiting in the mailbox of a thread. */
+#define ROST_MAILBOX_CAPACITY ((uintptr_t)16)
+/* Maximum size of a single message in bytes. */
+#define ROST_MAX_MESSAGE_SIZE ((uintptr_t)256)
+/* Maximum amount of events passed to `wait_any`. */
+#define ROST_MAX_WAIT_EVENTS ((uintptr_t)8)
+/* Size of the ring buffer of a pipe in bytes. */
+#define ROST_PIPE_CAPACITY ((uintptr_t)256)
+/* Maximum length of a pipe name in bytes. */
+#define ROST_MAX_PIPE_NAME_LENGTH ((uintptr_t)32)
+/* Maximum amount of bytes written to the DBGU by one syscall. */
+#define ROST_MAX_DBGU_WRITE_SIZE ((uintptr_t)256)
+/* Maximum size of the image of a process, which is mapped to the start of the user code. */
+#define ROST_MAX_PROCESS_IMAGE_SIZE ((uintptr_t)(1024 * 1024))
+/*
+ * Bytes of the user heap a thread may allocate unless its parent set another quota,
+ * new threads start with the quota of their parent.
+ */
+#define ROST_DEFAULT_HEAP_QUOTA ((uintptr_t)(1024 * 1024))
+/*
+ * Timeout in milliseconds of blocking syscalls which waits without limit.
+ * A timeout of zero does not wait, it fails with `SyscallError::TimedOut` right away.
+ */
+#define ROST_NO_TIMEOUT ((uintptr_t)UINTPTR_MAX)
+/*
+ * Highest exit code a thread can pass to `exit_thread`, larger ones are clamped by the
+ * kernel because the values above are reserved for the kernel's exit codes and errors.
+ */
+#define ROST_MAX_EXIT_CODE ((uintptr_t)(UINTPTR_MAX - 0x102))
+/* Exit code of a thread stopped by `kill_thread`. */
+#define ROST_EXIT_CODE_KILLED ((uintptr_t)(ROST_MAX_EXIT_CODE + 1))
+/* Exit code of a thread stopped by a fault in its code, e.g. a data abort. */
+#define ROST_EXIT_CODE_FAULT ((uintptr_t)(ROST_MAX_EXIT_CODE + 2))
+/* Lowest return register value which encodes a `SyscallError` instead of a result. */
+#define ROST_SYSCALL_ERROR_MIN ((uintptr_t)(ROST_EXIT_CODE_FAULT + 1))
+/* Lowest priority a user thread can have, only the idle thread is below it. */
+#define ROST_PRIORITY_MIN ((uint8_t)1)
+/* Priority of newly created threads. */
+#define ROST_PRIORITY_DEFAULT ((uint8_t)10)
+/* Highest possible priority. */
+#define ROST_PRIORITY_MAX ((uint8_t)UINT8_MAX)
+
+/* Returns non-zero if the return value of a syscall encodes an error. */
+static inline int rost_is_error(uintptr_t value)
+{
+    return value >= ROST_SYSCALL_ERROR_MIN;
+}
+
+/* Returns the error encoded in the return value of a syscall. */
+static inline enum rost_error rost_error_code(uintptr_t value)
+{
+    return (enum rost_error)(UINTPTR_MAX - value);
+}
+
+/* Entry of a thread, the return value is its exit code. */
+typedef uintptr_t (*rost_thread_entry)(void *arg);
+/* Handler of a signal, runs on the stack of the interrupted thread. */
+typedef void (*rost_signal_handler)(uint32_t signal);
+
+/* Timing of a periodic thread passed to the kernel by `create_periodic_thread`. */
+struct rost_periodic_parameters {
+    uintptr_t period_ms;
+    /*
+     * deadline relative to the start of each period, at least one real-time
+     * unit and at most `period_ms`
+     */
+    uintptr_t deadline_ms;
+};
+
+/* Buffer and timeout passed to the kernel by `read_pipe`, `write_pipe` and `read_dbgu`. */
+struct rost_transfer_parameters {
+    /* bytes to write, or space for the bytes read */
+    uint8_t * buffer;
+    uintptr_t length;
+    /* timeout in milliseconds, `NO_TIMEOUT` waits without limit */
+    uintptr_t timeout_ms;
+};
+
+/* Event source passed to the kernel by `wait_any`. */
+struct rost_event {
+    uint32_t kind;
+    /* thread or pipe id, unused for the other kinds */
+    uintptr_t id;
+};
+
+/*
+ * Issues a syscall with the arguments in r0-r2, evaluates to r0.
+ * The kernel returns with r1-r3 overwritten.
+ */
+#define ROST_SYSCALL(id, arg0, arg1, arg2) ({ \
+    register uintptr_t rost_r0 __asm__("r0") = (uintptr_t)(arg0); \
+    register uintptr_t rost_r1 __asm__("r1") = (uintptr_t)(arg1); \
+    register uintptr_t rost_r2 __asm__("r2") = (uintptr_t)(arg2); \
+    __asm__ volatile("swi %[call_id]" \
+                     : "+r"(rost_r0), "+r"(rost_r1), "+r"(rost_r2) \
+                     : [call_id] "i"(id) \
+                     : "r3", "memory"); \
+    rost_r0; \
+})
+
+/* Writes a character to the DBGU. */
+static inline uintptr_t rost_send_dbgu(uint8_t character)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_SEND_DBGU, character, 0, 0);
+}
+
+/* Receives a character of the subscribed DBGU service, a timeout of `NO_TIMEOUT` waits without limit. */
+static inline uintptr_t rost_receive_dbgu(uintptr_t blocking, uintptr_t timeout_ms)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_RECEIVE_DBGU, blocking, timeout_ms, 0);
+}
+
+/* Writes up to `MAX_DBGU_WRITE_SIZE` bytes to the DBGU at once, returns the amount written. */
+static inline uintptr_t rost_write_dbgu(const uint8_t *data, uintptr_t length)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_WRITE_DBGU, data, length, 0);
+}
+
+/*
+ * Reads characters of the subscribed DBGU service, returns the amount read.
+ * The timeout of `transfer` limits how long `mode` waits for them.
+ */
+static inline uintptr_t rost_read_dbgu(const struct rost_transfer_parameters *transfer, uint32_t mode)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_READ_DBGU, transfer, mode, 0);
+}
+
+/* Prints kernel state like the thread list to the DBGU, user threads cannot read it. */
+static inline uintptr_t rost_print_kernel_info(uint32_t info)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_PRINT_KERNEL_INFO, info, 0, 0);
+}
+
+/* Allocates memory on the user heap, returns its address. */
+static inline uintptr_t rost_allocate(uintptr_t size, uintptr_t align)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_ALLOCATE, size, align, 0);
+}
+
+/* Frees memory with the size and alignment it was allocated with. */
+static inline uintptr_t rost_deallocate(uint8_t *ptr, uintptr_t size, uintptr_t align)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_DEALLOCATE, ptr, size, align);
+}
+
+/* Sets how many bytes of the user heap a descendant of the current thread may allocate. */
+static inline uintptr_t rost_set_heap_quota(uintptr_t thread_id, uintptr_t quota)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_SET_HEAP_QUOTA, thread_id, quota, 0);
+}
+
+/*
+ * Creates a thread which calls `entry(arg)`, returns the id of the thread.
+ * A stack size of zero selects the default, others have to be 1 KiB to 1 MiB.
+ */
+static inline uintptr_t rost_create_thread(rost_thread_entry entry, void *arg, uintptr_t stack_size)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_CREATE_THREAD, entry, arg, stack_size);
+}
+
+/*
+ * Stops the current thread, the exit code is passed to the joining parent.
+ * Exit codes above `MAX_EXIT_CODE` are clamped to it, they are reserved for
+ * `EXIT_CODE_KILLED` and `EXIT_CODE_FAULT`.
+ */
+static inline uintptr_t rost_exit_thread(uintptr_t exit_code)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_EXIT_THREAD, exit_code, 0, 0);
+}
+
+/* Gives the processor to the next ready thread. */
+static inline uintptr_t rost_yield_thread(void)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_YIELD_THREAD, 0, 0, 0);
+}
+
+/*
+ * Waits for a child thread to exit and returns its exit code.
+ * Fails with `NotAChild` for other threads and `Detached` for adopted children.
+ * A timeout of `NO_TIMEOUT` waits without limit.
+ */
+static inline uintptr_t rost_join_thread(uintptr_t thread_id, uintptr_t timeout_ms)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_JOIN_THREAD, thread_id, timeout_ms, 0);
+}
+
+/* Subscribes a service, fails if it is already subscribed. */
+static inline uintptr_t rost_subscribe(uint32_t service)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_SUBSCRIBE, service, 0, 0);
+}
+
+/* Unsubscribes a subscribed service. */
+static inline uintptr_t rost_unsubscribe(uint32_t service)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_UNSUBSCRIBE, service, 0, 0);
+}
+
+/* Sets the priority of the current thread or one of its children. */
+static inline uintptr_t rost_set_priority(uintptr_t thread_id, uint8_t priority)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_SET_PRIORITY, thread_id, priority, 0);
+}
+
+/* Returns the priority of a thread without inherited priority. */
+static inline uintptr_t rost_get_priority(uintptr_t thread_id)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_GET_PRIORITY, thread_id, 0, 0);
+}
+
+/* Creates a periodic real-time thread which calls `entry(arg)`, returns the id of the thread. */
+static inline uintptr_t rost_create_periodic_thread(rost_thread_entry entry, void *arg, const struct rost_periodic_parameters *parameters)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_CREATE_PERIODIC_THREAD, entry, arg, parameters);
+}
+
+/* Waits for the next period of a periodic thread, returns its deadline misses so far. */
+static inline uintptr_t rost_wait_next_period(void)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_WAIT_NEXT_PERIOD, 0, 0, 0);
+}
+
+/* Returns the real time since boot in real time units. */
+static inline uintptr_t rost_get_current_real_time(void)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_GET_CURRENT_REAL_TIME, 0, 0, 0);
+}
+
+/* Sleeps at least `time_ms` milliseconds, returns the slept time in milliseconds. */
+static inline uintptr_t rost_sleep(uintptr_t time_ms)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_SLEEP, time_ms, 0, 0);
+}
+
+/* Starts a timer of the subscribed timer service, returns the timer id. */
+static inline uintptr_t rost_create_timer(uintptr_t interval_ms, uintptr_t periodic)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_CREATE_TIMER, interval_ms, periodic, 0);
+}
+
+/* Stops a timer of the current thread. */
+static inline uintptr_t rost_cancel_timer(uintptr_t timer_id)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_CANCEL_TIMER, timer_id, 0, 0);
+}
+
+/* Receives the timer id of the next tick, a timeout of `NO_TIMEOUT` waits without limit. */
+static inline uintptr_t rost_receive_timer(uintptr_t blocking, uintptr_t timeout_ms)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_RECEIVE_TIMER, blocking, timeout_ms, 0);
+}
+
+/* Returns the real time since boot in milliseconds. */
+static inline uintptr_t rost_get_uptime(void)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_GET_UPTIME, 0, 0, 0);
+}
+
+/* Stops a descendant of the current thread. */
+static inline uintptr_t rost_kill_thread(uintptr_t thread_id)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_KILL_THREAD, thread_id, 0, 0);
+}
+
+/* Stops scheduling a descendant of the current thread. */
+static inline uintptr_t rost_suspend_thread(uintptr_t thread_id)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_SUSPEND_THREAD, thread_id, 0, 0);
+}
+
+/* Continues a suspended thread. */
+static inline uintptr_t rost_resume_thread(uintptr_t thread_id)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_RESUME_THREAD, thread_id, 0, 0);
+}
+
+/* Starts or stops recording the syscalls of a descendant of the current thread. */
+static inline uintptr_t rost_trace_thread(uintptr_t thread_id, uintptr_t enabled)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_TRACE_THREAD, thread_id, enabled, 0);
+}
+
+/* Starts a process running a copy of a user binary, returns the id of its main thread. */
+static inline uintptr_t rost_spawn_process(const uint8_t *image, uintptr_t length)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_SPAWN_PROCESS, image, length, 0);
+}
+
+/* Creates a kernel mutex, returns its id. */
+static inline uintptr_t rost_create_mutex(void)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_CREATE_MUTEX, 0, 0, 0);
+}
+
+/* Locks a mutex, a timeout of `NO_TIMEOUT` waits without limit. */
+static inline uintptr_t rost_lock_mutex(uintptr_t mutex_id, uintptr_t timeout_ms)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_LOCK_MUTEX, mutex_id, timeout_ms, 0);
+}
+
+/* Unlocks a mutex owned by the current thread. */
+static inline uintptr_t rost_unlock_mutex(uintptr_t mutex_id)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_UNLOCK_MUTEX, mutex_id, 0, 0);
+}
+
+/* Creates a counting semaphore with `count` permits, returns its id. */
+static inline uintptr_t rost_create_semaphore(uintptr_t count)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_CREATE_SEMAPHORE, count, 0, 0);
+}
+
+/* Takes a permit of a semaphore, a timeout of `NO_TIMEOUT` waits without limit. */
+static inline uintptr_t rost_wait_semaphore(uintptr_t semaphore_id, uintptr_t timeout_ms)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_WAIT_SEMAPHORE, semaphore_id, timeout_ms, 0);
+}
+
+/* Returns a permit to a semaphore. */
+static inline uintptr_t rost_signal_semaphore(uintptr_t semaphore_id)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_SIGNAL_SEMAPHORE, semaphore_id, 0, 0);
+}
+
+/* Creates a condition variable, returns its id. */
+static inline uintptr_t rost_create_condvar(void)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_CREATE_CONDVAR, 0, 0, 0);
+}
+
+/*
+ * Unlocks the mutex and waits for a signal of the condition variable,
+ * a timeout of `NO_TIMEOUT` waits without limit.
+ */
+static inline uintptr_t rost_wait_condvar(uintptr_t condvar_id, uintptr_t mutex_id, uintptr_t timeout_ms)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_WAIT_CONDVAR, condvar_id, mutex_id, timeout_ms);
+}
+
+/* Wakes one thread waiting for the condition variable. */
+static inline uintptr_t rost_signal_condvar(uintptr_t condvar_id)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_SIGNAL_CONDVAR, condvar_id, 0, 0);
+}
+
+/* Wakes all threads waiting for the condition variable. */
+static inline uintptr_t rost_broadcast_condvar(uintptr_t condvar_id)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_BROADCAST_CONDVAR, condvar_id, 0, 0);
+}
+
+/* Removes a mutex, semaphore or condition variable. */
+static inline uintptr_t rost_destroy_sync_object(uintptr_t id)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_DESTROY_SYNC_OBJECT, id, 0, 0);
+}
+
+/* Puts a copy of the message into the mailbox of a thread. */
+static inline uintptr_t rost_send_message(uintptr_t thread_id, const uint8_t *data, uintptr_t length)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_SEND_MESSAGE, thread_id, data, length);
+}
+
+/*
+ * Takes the oldest message out of the mailbox, returns its length.
+ * A timeout of `NO_TIMEOUT` waits without limit.
+ */
+static inline uintptr_t rost_receive_message(uint8_t *buffer, uintptr_t length, uintptr_t timeout_ms)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_RECEIVE_MESSAGE, buffer, length, timeout_ms);
+}
+
+/* Creates and opens a named pipe, returns its id. */
+static inline uintptr_t rost_create_pipe(const uint8_t *name, uintptr_t length)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_CREATE_PIPE, name, length, 0);
+}
+
+/* Opens a named pipe, returns its id. */
+static inline uintptr_t rost_open_pipe(const uint8_t *name, uintptr_t length)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_OPEN_PIPE, name, length, 0);
+}
+
+/* Reads from a pipe, returns the amount of bytes read. */
+static inline uintptr_t rost_read_pipe(uintptr_t pipe_id, const struct rost_transfer_parameters *transfer)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_READ_PIPE, pipe_id, transfer, 0);
+}
+
+/* Writes the data into a pipe, returns the amount of bytes written. */
+static inline uintptr_t rost_write_pipe(uintptr_t pipe_id, const struct rost_transfer_parameters *transfer)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_WRITE_PIPE, pipe_id, transfer, 0);
+}
+
+/* Closes a pipe opened by a thread of the current process. */
+static inline uintptr_t rost_close_pipe(uintptr_t pipe_id)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_CLOSE_PIPE, pipe_id, 0, 0);
+}
+
+/*
+ * Waits for the first of `count` events, returns its index.
+ * A timeout of `NO_TIMEOUT` waits without limit.
+ */
+static inline uintptr_t rost_wait_any(const struct rost_event *events, uintptr_t count, uintptr_t timeout_ms)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_WAIT_ANY, events, count, timeout_ms);
+}
+
+/* Registers the handler of a signal for the current thread, None removes it. */
+static inline uintptr_t rost_register_signal_handler(uint32_t signal, rost_signal_handler handler)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_REGISTER_SIGNAL_HANDLER, signal, handler, 0);
+}
+
+/* Sends a signal to a descendant thread, returns zero if it has no handler for it. */
+static inline uintptr_t rost_send_signal(uintptr_t thread_id, uint32_t signal)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_SEND_SIGNAL, thread_id, signal, 0);
+}
+
+/* Continues the code interrupted by a signal, called by the kernel's signal entry only. */
+static inline uintptr_t rost_signal_return(void)
+{
+    return ROST_SYSCALL(ROST_SYSCALL_SIGNAL_RETURN, 0, 0, 0);
+}
+
+#endif /* ROST_H */
@@ -1,58 +1,58 @@
 #include <stddef.h>
-
-const unsigned int DBGU_SERVICE = 10;
-
-void subscribe_to_thread_service(unsigned int service)
-{
-    asm("swi #34");
-}
-
-char receive_char_from_dbgu()
-{
-    char out;
-    asm("mov r0, #1;" // blocking
-        "swi #11;"
-        "mov %0, r0;" : "=r"(out) :: "%r0");
-    return out;
-}
-
-void send_char_to_dbgu(char c)
-{
-    asm("swi #10");
-}
-
-void yield_thread()
-{
-    asm("swi #32");
-}
+#include "rost.h"
 
 void print_string(const char *chars)
 {
-    while (chars != NULL && *chars != '\0')
+    uintptr_t length = 0;
+    while (chars != NULL && chars[length] != '\0')
+    {
+        length++;
+    }
+    while (length > 0)
     {
-        send_char_to_dbgu(*chars);
-        chars++;
+        uintptr_t written = rost_write_dbgu((const uint8_t *)chars, length);
+        if (rost_is_error(written)) {
+            return;
+        }
+        chars += written;
+        length -= written;
     }
 }
 
+uintptr_t print_worker(void *arg)
+{
+    print_string((const char *)arg);
+    return 0;
+}
+
 int __attribute__((section(".text.main"))) main()
 {
-    subscribe_to_thread_service(DBGU_SERVICE);
+    rost_subscribe(ROST_SERVICE_DBGU);
     while (1) {
-        char received = receive_char_from_dbgu();
+        uintptr_t received = rost_receive_dbgu(1, ROST_NO_TIMEOUT);
+        if (rost_is_error(received)) {
+            break;
+        }
 
         if (received == 'q') {
             break;
         }
         if (received == 't') {
             print_string("tip\n");
-            yield_thread();
+            rost_yield_thread();
             print_string("top\n");
+        } else if (received == 'c') {
+            uintptr_t thread_id = rost_create_thread(print_worker, "hello from a child\n", 0);
+            if (rost_is_error(thread_id)) {
+                print_string("no thread\n");
+            } else {
+                rost_join_thread(thread_id, ROST_NO_TIMEOUT);
+            }
         } else {
             print_string("no: ");
-            send_char_to_dbgu(received);
-            send_char_to_dbgu('\n');
+            rost_send_dbgu(received);
+            rost_send_dbgu('\n');
         }
     }
     return 0;
-}
\ No newline at end of file
+}
@@ -15,7 +15,7 @@ static mut RNG: Option<Pcg64> = None;
 macro_rules! print {
     ($($arg:tt)*) =>  {
             let format_string = alloc::format!($($arg)*);
-            rost_api::syscalls::send_str_to_dbgu(&format_string);
+            rost_api::syscalls::send_str_to_dbgu(&format_string).unwrap();
     }
 }
 
@@ -40,10 +40,10 @@ where
 }
 
 fn task3() {
-    rost_api::syscalls::subscribe(rost_api::syscalls::ThreadServices::DBGU);
+    rost_api::syscalls::subscribe(rost_api::syscalls::ThreadServices::DBGU).unwrap();
     loop {
         // check for a new char in the dbgu buffer
-        let last_char = rost_api::syscalls::receive_character_from_dbgu() as char;
+        let last_char = rost_api::syscalls::receive_character_from_dbgu().unwrap() as char;
 
         // quit on q
         if last_char as char == 'q' {
@@ -51,9 +51,9 @@ fn task3() {
         }
         // print 3 times and wait between
         print_character_random(last_char, 1, 20);
-        rost_api::syscalls::sleep_ms(500);
+        rost_api::syscalls::sleep_ms(500).unwrap();
         print_character_random(last_char, 1, 20);
-        rost_api::syscalls::sleep_ms(500);
+        rost_api::syscalls::sleep_ms(500).unwrap();
         print_character_random(last_char, 1, 20);
     }
 }
@@ -82,11 +82,11 @@ unsafe impl Sync for SystemAlloc {}
 
 unsafe impl GlobalAlloc for SystemAlloc {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        rost_api::syscalls::allocate(layout.size(), layout.align())
+        rost_api::syscalls::allocate(layout.size(), layout.align()).unwrap_or(core::ptr::null_mut())
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
-        rost_api::syscalls::deallocate(ptr, layout.size(), layout.align())
+        let _ = rost_api::syscalls::deallocate(ptr, layout.size(), layout.align());
     }
 }
 